@@ -0,0 +1,273 @@
+//! Per-phase timings of a rendered frame
+
+use std::fmt;
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+/// A phase of rendering a frame. Phase names are shared by all backends so that timings can be compared between them.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FramePhase {
+    /// Running the draw function to encode drawing commands into the backend's scene
+    Encode,
+    /// Preparing encoded commands for rasterization (and submitting them to the GPU where applicable)
+    Flush,
+    /// Rasterizing the scene into pixels
+    Rasterize,
+    /// Presenting the rendered frame to the window
+    Present,
+    /// Waiting for the GPU or the windowing system
+    Wait,
+    /// Converting pixels into the format expected by the window surface
+    Swizzle,
+    /// Evicting stale entries from the backend's resource caches
+    CacheMaintenance,
+}
+
+impl FramePhase {
+    pub const ALL: [FramePhase; 7] = [
+        FramePhase::Encode,
+        FramePhase::Flush,
+        FramePhase::Rasterize,
+        FramePhase::Present,
+        FramePhase::Wait,
+        FramePhase::Swizzle,
+        FramePhase::CacheMaintenance,
+    ];
+
+    /// A stable name for the phase suitable for use as a telemetry key
+    pub fn name(self) -> &'static str {
+        match self {
+            FramePhase::Encode => "encode",
+            FramePhase::Flush => "flush",
+            FramePhase::Rasterize => "rasterize",
+            FramePhase::Present => "present",
+            FramePhase::Wait => "wait",
+            FramePhase::Swizzle => "swizzle",
+            FramePhase::CacheMaintenance => "cache_maintenance",
+        }
+    }
+}
+
+impl fmt::Display for FramePhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Timings for each phase of a rendered frame. Phases that a backend does not have are `None`.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct FrameStats {
+    pub encode: Option<Duration>,
+    pub flush: Option<Duration>,
+    pub rasterize: Option<Duration>,
+    pub present: Option<Duration>,
+    pub wait: Option<Duration>,
+    pub swizzle: Option<Duration>,
+    pub cache_maintenance: Option<Duration>,
+}
+
+impl FrameStats {
+    pub fn get(&self, phase: FramePhase) -> Option<Duration> {
+        match phase {
+            FramePhase::Encode => self.encode,
+            FramePhase::Flush => self.flush,
+            FramePhase::Rasterize => self.rasterize,
+            FramePhase::Present => self.present,
+            FramePhase::Wait => self.wait,
+            FramePhase::Swizzle => self.swizzle,
+            FramePhase::CacheMaintenance => self.cache_maintenance,
+        }
+    }
+
+    fn get_mut(&mut self, phase: FramePhase) -> &mut Option<Duration> {
+        match phase {
+            FramePhase::Encode => &mut self.encode,
+            FramePhase::Flush => &mut self.flush,
+            FramePhase::Rasterize => &mut self.rasterize,
+            FramePhase::Present => &mut self.present,
+            FramePhase::Wait => &mut self.wait,
+            FramePhase::Swizzle => &mut self.swizzle,
+            FramePhase::CacheMaintenance => &mut self.cache_maintenance,
+        }
+    }
+
+    /// Add a duration to a phase
+    pub fn add(&mut self, phase: FramePhase, duration: Duration) {
+        let time = self.get_mut(phase);
+        *time = Some(time.unwrap_or_default() + duration);
+    }
+
+    /// Add the timings of another frame (for example those of a wrapped renderer) to these timings
+    pub fn merge(&mut self, other: &FrameStats) {
+        for (phase, duration) in other.iter() {
+            self.add(phase, duration);
+        }
+    }
+
+    /// Iterate over the phases that were recorded along with their durations
+    pub fn iter(&self) -> impl Iterator<Item = (FramePhase, Duration)> + '_ {
+        FramePhase::ALL
+            .into_iter()
+            .filter_map(|phase| Some((phase, self.get(phase)?)))
+    }
+
+    /// The sum of all recorded phases
+    pub fn total(&self) -> Duration {
+        self.iter().map(|(_, duration)| duration).sum()
+    }
+}
+
+impl fmt::Display for FrameStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (phase, duration) in self.iter() {
+            write!(f, "{}: {:.2}ms ", phase, duration.as_secs_f64() * 1000.0)?;
+        }
+        write!(f, "(total: {:.2}ms)", self.total().as_secs_f64() * 1000.0)
+    }
+}
+
+/// Records the time spent in each phase of a frame.
+///
+/// Each call to [`FrameTimer::record`] attributes the time since the previous call (or since the timer was started)
+/// to the specified phase. Timing is unavailable on wasm, where no phases are recorded.
+pub struct FrameTimer {
+    stats: FrameStats,
+    #[cfg(not(target_arch = "wasm32"))]
+    last: Instant,
+}
+
+impl FrameTimer {
+    pub fn start() -> Self {
+        Self {
+            stats: FrameStats::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            last: Instant::now(),
+        }
+    }
+
+    /// Attribute the time since the last recorded phase to `phase`
+    pub fn record(&mut self, phase: FramePhase) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let now = Instant::now();
+            self.stats.add(phase, now - self.last);
+            self.last = now;
+        }
+        #[cfg(target_arch = "wasm32")]
+        let _ = phase;
+    }
+
+    /// Merge the timings of a nested renderer and restart timing from now, so that the nested
+    /// renderer's time is not counted twice
+    pub fn record_nested(&mut self, stats: Option<FrameStats>) {
+        if let Some(stats) = stats {
+            self.stats.merge(&stats);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.last = Instant::now();
+        }
+    }
+
+    pub fn finish(self) -> FrameStats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn add_accumulates_per_phase() {
+        let mut stats = FrameStats::default();
+        assert_eq!(stats.get(FramePhase::Encode), None);
+        stats.add(FramePhase::Encode, ms(2));
+        stats.add(FramePhase::Encode, ms(3));
+        stats.add(FramePhase::Present, ms(1));
+        assert_eq!(stats.encode, Some(ms(5)));
+        assert_eq!(stats.present, Some(ms(1)));
+        assert_eq!(stats.flush, None);
+        assert_eq!(stats.total(), ms(6));
+    }
+
+    #[test]
+    fn merge_adds_recorded_phases() {
+        let mut stats = FrameStats {
+            encode: Some(ms(1)),
+            ..Default::default()
+        };
+        let nested = FrameStats {
+            encode: Some(ms(2)),
+            rasterize: Some(ms(4)),
+            ..Default::default()
+        };
+        stats.merge(&nested);
+        assert_eq!(
+            stats.iter().collect::<Vec<_>>(),
+            [(FramePhase::Encode, ms(3)), (FramePhase::Rasterize, ms(4))]
+        );
+        assert_eq!(stats.total(), ms(7));
+        assert_eq!(FrameStats::default().total(), Duration::ZERO);
+    }
+
+    #[test]
+    fn phases_have_distinct_names() {
+        for (i, phase) in FramePhase::ALL.iter().enumerate() {
+            assert_eq!(phase.to_string(), phase.name());
+            assert!(
+                FramePhase::ALL[..i]
+                    .iter()
+                    .all(|other| other.name() != phase.name())
+            );
+        }
+    }
+
+    #[test]
+    fn display_lists_recorded_phases() {
+        let stats = FrameStats {
+            encode: Some(ms(1)),
+            wait: Some(Duration::from_micros(500)),
+            ..Default::default()
+        };
+        assert_eq!(
+            stats.to_string(),
+            "encode: 1.00ms wait: 0.50ms (total: 1.50ms)"
+        );
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn timer_attributes_elapsed_time_to_phases() {
+        let mut timer = FrameTimer::start();
+        std::thread::sleep(ms(10));
+        timer.record(FramePhase::Encode);
+        timer.record(FramePhase::Flush);
+        let stats = timer.finish();
+        assert!(stats.encode.unwrap() >= ms(10));
+        assert!(stats.flush.unwrap() < ms(10));
+        assert_eq!(stats.rasterize, None);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn nested_stats_are_not_counted_twice() {
+        let mut timer = FrameTimer::start();
+        // The time spent in the nested renderer is only counted through its stats
+        std::thread::sleep(ms(20));
+        timer.record_nested(Some(FrameStats {
+            rasterize: Some(ms(20)),
+            ..Default::default()
+        }));
+        timer.record_nested(None);
+        timer.record(FramePhase::Present);
+        let stats = timer.finish();
+        assert_eq!(stats.rasterize, Some(ms(20)));
+        assert!(stats.present.unwrap() < ms(20));
+    }
+}
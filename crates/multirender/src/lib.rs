@@ -1,5 +1,5 @@
 use kurbo::{Affine, Rect, Shape, Stroke};
-use peniko::{BlendMode, Brush, Color, Fill, FontData, ImageBrushRef, StyleRef};
+use peniko::{BlendMode, BrushRef, Color, Fill, FontData, ImageBrushRef, StyleRef};
 use recording::RenderCommand;
 use std::sync::Arc;
 
@@ -11,6 +11,19 @@ mod null_backend;
 pub use null_backend::*;
 pub mod recording;
 pub use recording::Scene;
+pub mod scale;
+pub use scale::*;
+pub mod frame_stats;
+pub use frame_stats::*;
+pub mod threaded;
+pub use threaded::*;
+pub mod picture_cache;
+pub use picture_cache::*;
+pub mod tiled;
+pub use tiled::*;
+
+#[cfg(test)]
+mod test_util;
 
 /// Abstraction for rendering a scene to a window
 pub trait WindowRenderer {
@@ -26,7 +39,11 @@ pub trait WindowRenderer {
     fn suspend(&mut self);
     fn is_active(&self) -> bool;
     fn set_size(&mut self, width: u32, height: u32);
-    fn render<F: FnOnce(&mut Self::ScenePainter<'_>)>(&mut self, draw_fn: F);
+    /// Set the factor used to map the logical units that the `ScenePainter` is drawn in to physical pixels.
+    /// The scale is applied as the root transform of every frame and persists until changed.
+    fn set_scale_factor(&mut self, scale_factor: f64);
+    /// Render a frame. Returns timings for each phase of the frame, or `None` if nothing was rendered.
+    fn render<F: FnOnce(&mut Self::ScenePainter<'_>)>(&mut self, draw_fn: F) -> Option<FrameStats>;
 }
 
 /// Abstraction for rendering a scene to an image buffer
@@ -34,15 +51,28 @@ pub trait ImageRenderer {
     type ScenePainter<'a>: PaintScene
     where
         Self: 'a;
+    /// The largest width or height (in physical pixels) that the renderer supports.
+    /// Use [`TiledRenderer`] to render larger images.
+    const MAX_DIMENSION: u32 = u32::MAX;
     fn new(width: u32, height: u32) -> Self;
     fn resize(&mut self, width: u32, height: u32);
+    /// Set the factor used to map the logical units that the `ScenePainter` is drawn in to physical pixels.
+    /// The scale is applied as the root transform of every render and is not cleared by `reset`.
+    fn set_scale_factor(&mut self, scale_factor: f64);
     fn reset(&mut self);
+    /// Render into `vec`, resizing it to fit the image. Returns timings for each phase of the render,
+    /// or `None` if nothing was rendered.
     fn render_to_vec<F: FnOnce(&mut Self::ScenePainter<'_>)>(
         &mut self,
         draw_fn: F,
         vec: &mut Vec<u8>,
-    );
-    fn render<F: FnOnce(&mut Self::ScenePainter<'_>)>(&mut self, draw_fn: F, buffer: &mut [u8]);
+    ) -> Option<FrameStats>;
+    /// Render into `buffer`. Returns timings for each phase of the render, or `None` if nothing was rendered.
+    fn render<F: FnOnce(&mut Self::ScenePainter<'_>)>(
+        &mut self,
+        draw_fn: F,
+        buffer: &mut [u8],
+    ) -> Option<FrameStats>;
 }
 
 /// Draw a scene to a buffer using an `ImageRenderer`
@@ -132,7 +162,13 @@ pub trait PaintScene {
 
     /// Append a recorded Scene Fragment to the current scene
     fn append_scene(&mut self, scene: Scene, scene_transform: Affine) {
-        for cmd in scene.commands {
+        self.replay_scene(&scene, scene_transform);
+    }
+
+    /// Draw the commands of a recorded Scene Fragment into the current scene, borrowing its shapes, brushes and
+    /// glyphs. Use this to draw the same scene many times without cloning it.
+    fn replay_scene(&mut self, scene: &Scene, scene_transform: Affine) {
+        for cmd in &scene.commands {
             match cmd {
                 RenderCommand::PushLayer(cmd) => self.push_layer(
                     cmd.blend,
@@ -147,22 +183,14 @@ pub trait PaintScene {
                 RenderCommand::Stroke(cmd) => self.stroke(
                     &cmd.style,
                     scene_transform * cmd.transform,
-                    match cmd.brush {
-                        Brush::Solid(alpha_color) => Brush::Solid(alpha_color),
-                        Brush::Gradient(ref gradient) => Brush::Gradient(gradient),
-                        Brush::Image(ref image) => Brush::Image(image.as_ref()),
-                    },
+                    BrushRef::from(&cmd.brush),
                     cmd.brush_transform,
                     &cmd.shape,
                 ),
                 RenderCommand::Fill(cmd) => self.fill(
                     cmd.fill,
                     scene_transform * cmd.transform,
-                    match cmd.brush {
-                        Brush::Solid(alpha_color) => Brush::Solid(alpha_color),
-                        Brush::Gradient(ref gradient) => Brush::Gradient(gradient),
-                        Brush::Image(ref image) => Brush::Image(image.as_ref()),
-                    },
+                    BrushRef::from(&cmd.brush),
                     cmd.brush_transform,
                     &cmd.shape,
                 ),
@@ -172,15 +200,11 @@ pub trait PaintScene {
                     cmd.hint,
                     &cmd.normalized_coords,
                     &cmd.style,
-                    match cmd.brush {
-                        Brush::Solid(alpha_color) => Brush::Solid(alpha_color),
-                        Brush::Gradient(ref gradient) => Brush::Gradient(gradient),
-                        Brush::Image(ref image) => Brush::Image(image.as_ref()),
-                    },
+                    BrushRef::from(&cmd.brush),
                     cmd.brush_alpha,
                     scene_transform * cmd.transform,
                     cmd.glyph_transform,
-                    cmd.glyphs.into_iter(),
+                    cmd.glyphs.iter().copied(),
                 ),
                 RenderCommand::BoxShadow(cmd) => self.draw_box_shadow(
                     scene_transform * cmd.transform,
@@ -1,6 +1,6 @@
 //! A dummy implementation of the MultiRender traits while simply ignores all commands
 
-use crate::{ImageRenderer, PaintScene, WindowHandle, WindowRenderer};
+use crate::{FrameStats, ImageRenderer, PaintScene, WindowHandle, WindowRenderer};
 use std::sync::Arc;
 
 #[derive(Copy, Clone, Default)]
@@ -40,7 +40,14 @@ impl WindowRenderer for NullWindowRenderer {
 
     fn set_size(&mut self, _width: u32, _height: u32) {}
 
-    fn render<F: FnOnce(&mut Self::ScenePainter<'_>)>(&mut self, _draw_fn: F) {}
+    fn set_scale_factor(&mut self, _scale_factor: f64) {}
+
+    fn render<F: FnOnce(&mut Self::ScenePainter<'_>)>(
+        &mut self,
+        _draw_fn: F,
+    ) -> Option<FrameStats> {
+        None
+    }
 }
 
 #[derive(Copy, Clone, Default)]
@@ -64,16 +71,25 @@ impl ImageRenderer for NullImageRenderer {
 
     fn resize(&mut self, _width: u32, _height: u32) {}
 
+    fn set_scale_factor(&mut self, _scale_factor: f64) {}
+
     fn reset(&mut self) {}
 
     fn render_to_vec<F: FnOnce(&mut Self::ScenePainter<'_>)>(
         &mut self,
         _draw_fn: F,
         _vec: &mut Vec<u8>,
-    ) {
+    ) -> Option<FrameStats> {
+        None
     }
 
-    fn render<F: FnOnce(&mut Self::ScenePainter<'_>)>(&mut self, _draw_fn: F, _buffer: &mut [u8]) {}
+    fn render<F: FnOnce(&mut Self::ScenePainter<'_>)>(
+        &mut self,
+        _draw_fn: F,
+        _buffer: &mut [u8],
+    ) -> Option<FrameStats> {
+        None
+    }
 }
 
 #[derive(Copy, Clone, Default)]
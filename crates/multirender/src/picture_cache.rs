@@ -0,0 +1,319 @@
+//! Caching of rasterized static content
+
+use crate::{ImageRenderer, PaintScene, Scene};
+use kurbo::{Affine, Rect};
+use peniko::{Blob, ImageAlphaType, ImageBrush, ImageData, ImageFormat, ImageQuality};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Arc;
+
+/// The default byte budget of a [`PictureCache`] (64 MiB)
+pub const DEFAULT_PICTURE_CACHE_BUDGET: usize = 64 * 1024 * 1024;
+
+struct CachedPicture {
+    image: ImageBrush,
+    bounds: Rect,
+    scale: f64,
+    last_used: u64,
+}
+
+impl CachedPicture {
+    fn byte_size(&self) -> usize {
+        self.image.image.data.len()
+    }
+}
+
+/// Caches rasterized versions of expensive static content such as charts and maps.
+///
+/// The first time content is drawn for a key it is recorded into a [`Scene`], rasterized at the current scale through
+/// an [`ImageRenderer`] and stored as an image. Later draws with the same key and scale draw the cached image instead.
+/// Content is re-rasterized when the scale or bounds for a key change, so callers should change the key whenever the
+/// content changes.
+///
+/// Entries are evicted in least recently used order once the cached images exceed the byte budget.
+///
+/// The renderer is expected to produce premultiplied RGBA8 pixels.
+pub struct PictureCache<R: ImageRenderer, K: Hash + Eq> {
+    renderer: Option<R>,
+    entries: HashMap<K, CachedPicture>,
+    budget: usize,
+    used: usize,
+    clock: u64,
+}
+
+impl<R: ImageRenderer, K: Hash + Eq + Clone> Default for PictureCache<R, K> {
+    fn default() -> Self {
+        Self::new(DEFAULT_PICTURE_CACHE_BUDGET)
+    }
+}
+
+impl<R: ImageRenderer, K: Hash + Eq + Clone> PictureCache<R, K> {
+    /// Create a cache that holds up to `budget` bytes of rasterized images.
+    /// The renderer is created when content is first rasterized.
+    pub fn new(budget: usize) -> Self {
+        Self {
+            renderer: None,
+            entries: HashMap::new(),
+            budget,
+            used: 0,
+            clock: 0,
+        }
+    }
+
+    /// Create a cache that rasterizes using the specified renderer
+    pub fn with_renderer(renderer: R, budget: usize) -> Self {
+        Self {
+            renderer: Some(renderer),
+            ..Self::new(budget)
+        }
+    }
+
+    /// The maximum number of bytes of rasterized images to keep
+    pub fn budget(&self) -> usize {
+        self.budget
+    }
+
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        self.evict(None);
+    }
+
+    /// The number of bytes of rasterized images currently held
+    pub fn used_bytes(&self) -> usize {
+        self.used
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    pub fn remove(&mut self, key: &K) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.used -= entry.byte_size();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.used = 0;
+    }
+
+    /// Draw the content for `key` into `painter`.
+    ///
+    /// `bounds` is the area of the content (in the content's own coordinate space) that is cached. Content outside
+    /// of it is clipped. `scale` is the scale that the content will be displayed at on screen (typically the
+    /// window's scale factor multiplied by any scale in `transform`), and determines the resolution it is rasterized
+    /// at. `record` is only called when the content needs to be (re-)rasterized.
+    pub fn draw(
+        &mut self,
+        painter: &mut impl PaintScene,
+        key: K,
+        bounds: Rect,
+        scale: f64,
+        transform: Affine,
+        record: impl FnOnce() -> Scene,
+    ) {
+        if raster_size::<R>(bounds, scale) == Err(RasterSize::TooLarge) {
+            // Too large for the renderer to rasterize in one image, so draw the content directly
+            self.remove(&key);
+            painter.push_clip_layer(transform, &bounds);
+            painter.append_scene(record(), transform);
+            painter.pop_layer();
+            return;
+        }
+
+        let Some(image) = self.get_or_rasterize(key, bounds, scale, record) else {
+            return;
+        };
+
+        let image_transform =
+            transform * Affine::translate(bounds.origin().to_vec2()) * Affine::scale(1.0 / scale);
+        painter.draw_image(image.as_ref(), image_transform);
+    }
+
+    /// Get the rasterized image for `key`, rasterizing the content returned by `record` if there is no cached image
+    /// for the specified bounds and scale. Returns `None` if the content is empty at this scale, or if it is larger than
+    /// [`ImageRenderer::MAX_DIMENSION`] of the renderer.
+    pub fn get_or_rasterize(
+        &mut self,
+        key: K,
+        bounds: Rect,
+        scale: f64,
+        record: impl FnOnce() -> Scene,
+    ) -> Option<ImageBrush> {
+        self.clock += 1;
+
+        if let Some(entry) = self.entries.get_mut(&key)
+            && entry.scale == scale
+            && entry.bounds == bounds
+        {
+            entry.last_used = self.clock;
+            return Some(entry.image.clone());
+        }
+        self.remove(&key);
+
+        let image = self.rasterize(record(), bounds, scale)?;
+        let entry = CachedPicture {
+            image: image.clone(),
+            bounds,
+            scale,
+            last_used: self.clock,
+        };
+        self.used += entry.byte_size();
+        self.entries.insert(key.clone(), entry);
+        self.evict(Some(&key));
+
+        Some(image)
+    }
+
+    fn rasterize(&mut self, scene: Scene, bounds: Rect, scale: f64) -> Option<ImageBrush> {
+        let (width, height) = raster_size::<R>(bounds, scale).ok()?;
+
+        let renderer = self.renderer.get_or_insert_with(|| R::new(width, height));
+        renderer.resize(width, height);
+        renderer.set_scale_factor(scale);
+
+        let mut buffer = Vec::new();
+        let scene_transform = Affine::translate(-bounds.origin().to_vec2());
+        renderer.render_to_vec(
+            |painter| {
+                painter.push_clip_layer(Affine::IDENTITY, &bounds.with_origin((0.0, 0.0)));
+                painter.append_scene(scene, scene_transform);
+                painter.pop_layer();
+            },
+            &mut buffer,
+        );
+        renderer.reset();
+
+        let image = ImageData {
+            data: Blob::new(Arc::new(buffer)),
+            format: ImageFormat::Rgba8,
+            alpha_type: ImageAlphaType::AlphaPremultiplied,
+            width,
+            height,
+        };
+        Some(ImageBrush::new(image).with_quality(ImageQuality::Low))
+    }
+
+    /// Evict least recently used entries until the cache fits in the budget, never evicting `keep`
+    fn evict(&mut self, keep: Option<&K>) {
+        while self.used > self.budget {
+            let Some(lru_key) = self
+                .entries
+                .iter()
+                .filter(|(key, _)| Some(*key) != keep)
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            self.remove(&lru_key);
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum RasterSize {
+    Empty,
+    TooLarge,
+}
+
+/// The size in pixels of `bounds` rasterized at `scale`, if it is non-empty and fits within the renderer's limits
+fn raster_size<R: ImageRenderer>(bounds: Rect, scale: f64) -> Result<(u32, u32), RasterSize> {
+    let width = (bounds.width() * scale).ceil();
+    let height = (bounds.height() * scale).ceil();
+    if !(width >= 1.0 && height >= 1.0) {
+        return Err(RasterSize::Empty);
+    }
+    let max = R::MAX_DIMENSION as f64;
+    if width > max || height > max {
+        return Err(RasterSize::TooLarge);
+    }
+    Ok((width as u32, height as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{CountingRenderer, scene_with_fills};
+
+    fn renders(cache: &PictureCache<CountingRenderer, u32>) -> usize {
+        cache.renderer.as_ref().map_or(0, |r| r.renders)
+    }
+
+    #[test]
+    fn rasterizes_once_per_key_and_scale() {
+        let mut cache = PictureCache::<CountingRenderer, u32>::default();
+        let mut painter = Scene::new();
+        let bounds = Rect::new(10.0, 10.0, 20.0, 20.0);
+
+        for _ in 0..3 {
+            cache.draw(&mut painter, 1, bounds, 1.0, Affine::IDENTITY, || {
+                scene_with_fills(1)
+            });
+        }
+        assert_eq!(renders(&cache), 1);
+        assert_eq!(painter.commands.len(), 3);
+        assert_eq!(cache.used_bytes(), 10 * 10 * 4);
+
+        cache.draw(&mut painter, 1, bounds, 2.0, Affine::IDENTITY, || {
+            scene_with_fills(1)
+        });
+        assert_eq!(renders(&cache), 2);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.used_bytes(), 20 * 20 * 4);
+
+        cache.draw(&mut painter, 2, bounds, 2.0, Affine::IDENTITY, || {
+            scene_with_fills(1)
+        });
+        assert_eq!(renders(&cache), 3);
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let entry_size = 10 * 10 * 4;
+        let mut cache = PictureCache::<CountingRenderer, u32>::new(entry_size * 2);
+        let bounds = Rect::new(0.0, 0.0, 10.0, 10.0);
+
+        cache.get_or_rasterize(1, bounds, 1.0, Scene::new);
+        cache.get_or_rasterize(2, bounds, 1.0, Scene::new);
+        cache.get_or_rasterize(1, bounds, 1.0, Scene::new);
+        cache.get_or_rasterize(3, bounds, 1.0, Scene::new);
+
+        assert!(cache.contains(&1));
+        assert!(!cache.contains(&2));
+        assert!(cache.contains(&3));
+        assert_eq!(cache.used_bytes(), entry_size * 2);
+    }
+
+    #[test]
+    fn draws_directly_above_max_dimension() {
+        let mut cache = PictureCache::<CountingRenderer, u32>::default();
+        let mut painter = Scene::new();
+        let bounds = Rect::new(0.0, 0.0, 10.0, 10.0);
+        let scale = CountingRenderer::MAX_DIMENSION as f64;
+
+        assert!(
+            cache
+                .get_or_rasterize(1, bounds, scale, Scene::new)
+                .is_none()
+        );
+
+        cache.draw(&mut painter, 1, bounds, scale, Affine::IDENTITY, || {
+            scene_with_fills(2)
+        });
+        assert_eq!(renders(&cache), 0);
+        assert!(cache.is_empty());
+        // The clip layer, both fills and the pop
+        assert_eq!(painter.commands.len(), 4);
+    }
+}
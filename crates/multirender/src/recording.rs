@@ -56,7 +56,7 @@ pub struct LayerCommand {
     pub blend: BlendMode,
     pub alpha: f32,
     pub transform: Affine,
-    #[cfg_attr(feature = "serde", serde(with = "svg_path"))]
+    #[cfg_attr(feature = "serde", serde(with = "bez_path"))]
     pub clip: BezPath, // TODO: more shape options
 }
 
@@ -67,7 +67,7 @@ pub struct LayerCommand {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct ClipCommand {
     pub transform: Affine,
-    #[cfg_attr(feature = "serde", serde(with = "svg_path"))]
+    #[cfg_attr(feature = "serde", serde(with = "bez_path"))]
     pub clip: BezPath, // TODO: more shape options
 }
 
@@ -79,7 +79,7 @@ pub struct StrokeCommand<Image> {
     pub transform: Affine,
     pub brush: Brush<ImageBrush<Image>>, // TODO: review ownership to avoid cloning. Should brushes be a "resource"?
     pub brush_transform: Option<Affine>,
-    #[cfg_attr(feature = "serde", serde(with = "svg_path"))]
+    #[cfg_attr(feature = "serde", serde(with = "bez_path"))]
     pub shape: BezPath, // TODO: more shape options
 }
 
@@ -91,7 +91,7 @@ pub struct FillCommand<Image> {
     pub transform: Affine,
     pub brush: Brush<ImageBrush<Image>>, // TODO: review ownership to avoid cloning. Should brushes be a "resource"?
     pub brush_transform: Option<Affine>,
-    #[cfg_attr(feature = "serde", serde(with = "svg_path"))]
+    #[cfg_attr(feature = "serde", serde(with = "bez_path"))]
     pub shape: BezPath, // TODO: more shape options
 }
 
@@ -293,24 +293,115 @@ impl PaintScene for Scene {
     }
 }
 
-/// Serde helper for serializing `BezPath` as an SVG path string.
+/// Serde helper for serializing `BezPath`.
+///
+/// Human-readable formats store the path as an SVG path string. Binary formats store raw verbs and
+/// points so that coordinates round-trip exactly.
 #[cfg(feature = "serde")]
-mod svg_path {
-    use kurbo::BezPath;
-    use serde::{self, Deserialize, Deserializer, Serializer};
+mod bez_path {
+    use kurbo::{BezPath, PathEl, Point};
+    use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
+
+    const MOVE_TO: u8 = 0;
+    const LINE_TO: u8 = 1;
+    const QUAD_TO: u8 = 2;
+    const CURVE_TO: u8 = 3;
+    const CLOSE_PATH: u8 = 4;
+
+    /// A path as a list of verbs and a flat list of point coordinates
+    #[derive(Serialize, Deserialize)]
+    struct RawPath {
+        verbs: Vec<u8>,
+        coords: Vec<f64>,
+    }
 
     pub fn serialize<S>(path: &BezPath, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        serializer.serialize_str(&path.to_svg())
+        if serializer.is_human_readable() {
+            return serializer.serialize_str(&path.to_svg());
+        }
+
+        let mut raw = RawPath {
+            verbs: Vec::with_capacity(path.elements().len()),
+            coords: Vec::new(),
+        };
+        let mut push_points = |points: &[Point]| {
+            for point in points {
+                raw.coords.extend([point.x, point.y]);
+            }
+        };
+        for el in path.elements() {
+            let verb = match *el {
+                PathEl::MoveTo(p) => {
+                    push_points(&[p]);
+                    MOVE_TO
+                }
+                PathEl::LineTo(p) => {
+                    push_points(&[p]);
+                    LINE_TO
+                }
+                PathEl::QuadTo(p1, p2) => {
+                    push_points(&[p1, p2]);
+                    QUAD_TO
+                }
+                PathEl::CurveTo(p1, p2, p3) => {
+                    push_points(&[p1, p2, p3]);
+                    CURVE_TO
+                }
+                PathEl::ClosePath => CLOSE_PATH,
+            };
+            raw.verbs.push(verb);
+        }
+        raw.serialize(serializer)
     }
 
     pub fn deserialize<'de, D>(deserializer: D) -> Result<BezPath, D::Error>
     where
         D: Deserializer<'de>,
     {
-        let s = String::deserialize(deserializer)?;
-        BezPath::from_svg(&s).map_err(serde::de::Error::custom)
+        if deserializer.is_human_readable() {
+            let s = String::deserialize(deserializer)?;
+            return BezPath::from_svg(&s).map_err(serde::de::Error::custom);
+        }
+
+        let raw = RawPath::deserialize(deserializer)?;
+        let mut points = raw
+            .coords
+            .chunks_exact(2)
+            .map(|coords| Point::new(coords[0], coords[1]));
+        let mut next = || {
+            points.next().ok_or_else(|| {
+                serde::de::Error::custom("path has fewer points than its verbs require")
+            })
+        };
+
+        if raw.verbs.first().is_some_and(|&verb| verb != MOVE_TO) {
+            return Err(serde::de::Error::custom("path does not begin with a move"));
+        }
+
+        let mut path = BezPath::new();
+        for verb in raw.verbs {
+            let el = match verb {
+                MOVE_TO => PathEl::MoveTo(next()?),
+                LINE_TO => PathEl::LineTo(next()?),
+                QUAD_TO => PathEl::QuadTo(next()?, next()?),
+                CURVE_TO => PathEl::CurveTo(next()?, next()?, next()?),
+                CLOSE_PATH => PathEl::ClosePath,
+                other => {
+                    return Err(serde::de::Error::custom(format!(
+                        "invalid path verb: {other}"
+                    )));
+                }
+            };
+            path.push(el);
+        }
+        if points.next().is_some() || raw.coords.len() % 2 != 0 {
+            return Err(serde::de::Error::custom(
+                "path has more points than its verbs require",
+            ));
+        }
+        Ok(path)
     }
 }
@@ -0,0 +1,59 @@
+//! Helpers for backends that map logical units to physical pixels using a scale factor
+
+use kurbo::Affine;
+
+/// The scale factor used when none has been set
+pub const DEFAULT_SCALE_FACTOR: f64 = 1.0;
+
+/// The root transform that maps logical units to physical pixels
+pub fn root_transform(scale_factor: f64) -> Affine {
+    Affine::scale(scale_factor)
+}
+
+/// Adjusts a flattening tolerance expressed in physical pixels to the logical units that shapes are specified in,
+/// so that curves are flattened with the same accuracy on screen regardless of the scale factor.
+pub fn scaled_tolerance(tolerance: f64, scale_factor: f64) -> f64 {
+    if scale_factor > 0.0 {
+        tolerance / scale_factor
+    } else {
+        tolerance
+    }
+}
+
+/// Whether a glyph run should be hinted at the specified scale factor.
+///
+/// Glyph positions are specified in logical units, so at fractional scale factors they do not line up with the
+/// physical pixel grid and hinted outlines would be snapped inconsistently from glyph to glyph.
+pub fn hint_at_scale(hint: bool, scale_factor: f64) -> bool {
+    hint && scale_factor.fract() == 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kurbo::Point;
+
+    #[test]
+    fn root_transform_scales_logical_units() {
+        let root = root_transform(1.5);
+        assert_eq!(root * Point::new(10.0, 4.0), Point::new(15.0, 6.0));
+        assert_eq!(root_transform(DEFAULT_SCALE_FACTOR), Affine::IDENTITY);
+    }
+
+    #[test]
+    fn tolerance_is_divided_by_scale() {
+        assert_eq!(scaled_tolerance(0.1, 2.0), 0.05);
+        assert_eq!(scaled_tolerance(0.1, 0.5), 0.2);
+        // Degenerate scale factors leave the tolerance unchanged rather than dividing by zero
+        assert_eq!(scaled_tolerance(0.1, 0.0), 0.1);
+        assert_eq!(scaled_tolerance(0.1, -1.0), 0.1);
+    }
+
+    #[test]
+    fn hinting_only_at_integer_scales() {
+        assert!(hint_at_scale(true, 1.0));
+        assert!(hint_at_scale(true, 2.0));
+        assert!(!hint_at_scale(true, 1.5));
+        assert!(!hint_at_scale(false, 2.0));
+    }
+}
@@ -0,0 +1,148 @@
+//! Helpers shared by unit tests
+
+use crate::recording::RenderCommand;
+use crate::{FrameStats, ImageRenderer, PaintScene, Scene};
+use kurbo::{Affine, Rect, Shape};
+use peniko::{Color, Fill};
+
+/// Writes the number of commands drawn into every byte of the buffer
+pub(crate) struct CountingRenderer {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) scene: Scene,
+    /// The number of times `render` has been called
+    pub(crate) renders: usize,
+}
+
+impl ImageRenderer for CountingRenderer {
+    type ScenePainter<'a> = Scene;
+
+    const MAX_DIMENSION: u32 = 4096;
+
+    fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            scene: Scene::new(),
+            renders: 0,
+        }
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        assert!(
+            width <= Self::MAX_DIMENSION && height <= Self::MAX_DIMENSION,
+            "{width}x{height} exceeds the maximum dimension"
+        );
+        self.width = width;
+        self.height = height;
+    }
+
+    fn set_scale_factor(&mut self, _scale_factor: f64) {}
+
+    fn reset(&mut self) {
+        self.scene.reset();
+    }
+
+    fn render_to_vec<F: FnOnce(&mut Self::ScenePainter<'_>)>(
+        &mut self,
+        draw_fn: F,
+        vec: &mut Vec<u8>,
+    ) -> Option<FrameStats> {
+        vec.resize((self.width * self.height * 4) as usize, 0);
+        self.render(draw_fn, vec)
+    }
+
+    fn render<F: FnOnce(&mut Self::ScenePainter<'_>)>(
+        &mut self,
+        draw_fn: F,
+        buffer: &mut [u8],
+    ) -> Option<FrameStats> {
+        self.renders += 1;
+        draw_fn(&mut self.scene);
+        buffer.fill(self.scene.commands.len() as u8);
+        Some(FrameStats::default())
+    }
+}
+
+pub(crate) fn scene_with_fills(count: usize) -> Scene {
+    let mut scene = Scene::new();
+    for _ in 0..count {
+        scene.fill(
+            Fill::NonZero,
+            Affine::IDENTITY,
+            Color::BLACK,
+            None,
+            &Rect::new(0.0, 0.0, 1.0, 1.0),
+        );
+    }
+    scene
+}
+
+/// Rasterizes the bounding boxes of filled shapes as opaque white pixels, so that tests can check where content ends up
+pub(crate) struct PositionRenderer {
+    width: u32,
+    height: u32,
+    scale_factor: f64,
+    scene: Scene,
+}
+
+impl ImageRenderer for PositionRenderer {
+    type ScenePainter<'a> = Scene;
+
+    fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            scale_factor: 1.0,
+            scene: Scene::new(),
+        }
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+    }
+
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+    }
+
+    fn reset(&mut self) {
+        self.scene.reset();
+    }
+
+    fn render_to_vec<F: FnOnce(&mut Self::ScenePainter<'_>)>(
+        &mut self,
+        draw_fn: F,
+        vec: &mut Vec<u8>,
+    ) -> Option<FrameStats> {
+        vec.resize((self.width * self.height * 4) as usize, 0);
+        self.render(draw_fn, vec)
+    }
+
+    fn render<F: FnOnce(&mut Self::ScenePainter<'_>)>(
+        &mut self,
+        draw_fn: F,
+        buffer: &mut [u8],
+    ) -> Option<FrameStats> {
+        draw_fn(&mut self.scene);
+        buffer.fill(0);
+
+        let root = Affine::scale(self.scale_factor);
+        for command in &self.scene.commands {
+            let RenderCommand::Fill(fill) = command else {
+                continue;
+            };
+            let bounds = (root * fill.transform).transform_rect_bbox(fill.shape.bounding_box());
+            for y in 0..self.height {
+                for x in 0..self.width {
+                    if bounds.contains((x as f64 + 0.5, y as f64 + 0.5)) {
+                        let start = ((y * self.width + x) * 4) as usize;
+                        buffer[start..start + 4].fill(255);
+                    }
+                }
+            }
+        }
+        Some(FrameStats::default())
+    }
+}
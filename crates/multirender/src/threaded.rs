@@ -0,0 +1,336 @@
+//! An `ImageRenderer` wrapper that rasterizes recorded scenes on a background thread
+
+use crate::{FrameStats, ImageRenderer, PaintScene, Scene};
+use kurbo::Affine;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender, TryRecvError, TrySendError};
+use std::thread::{self, JoinHandle};
+
+/// The number of frames that may be in flight at once by default: one being rasterized on the render thread
+/// while the next one is being recorded (double buffering).
+pub const DEFAULT_FRAMES_IN_FLIGHT: usize = 2;
+
+/// A frame rendered by a [`ThreadedImageRenderer`]
+#[derive(Clone, Debug)]
+pub struct RenderedFrame {
+    /// The rendered pixels
+    pub buffer: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    /// Timings reported by the wrapped renderer
+    pub stats: Option<FrameStats>,
+}
+
+/// Error returned when a scene could not be submitted to a [`ThreadedImageRenderer`].
+/// The scene is handed back to the caller.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SubmitError {
+    /// The maximum number of frames are already in flight
+    Full(Scene),
+    /// The render thread has exited (most likely because the renderer panicked)
+    Disconnected(Scene),
+}
+
+impl SubmitError {
+    pub fn into_scene(self) -> Scene {
+        match self {
+            SubmitError::Full(scene) | SubmitError::Disconnected(scene) => scene,
+        }
+    }
+}
+
+impl fmt::Display for SubmitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubmitError::Full(_) => f.write_str("too many frames in flight"),
+            SubmitError::Disconnected(_) => f.write_str("render thread has exited"),
+        }
+    }
+}
+
+impl std::error::Error for SubmitError {}
+
+enum Job {
+    Render(Scene),
+    Resize(u32, u32),
+    SetScaleFactor(f64),
+}
+
+/// Owns an [`ImageRenderer`] on a worker thread and rasterizes recorded [`Scene`]s there, so that the next frame
+/// can be recorded on the caller's thread while the previous one is rasterized.
+///
+/// Scenes are submitted through a bounded channel which holds at most `frames_in_flight - 1` scenes in addition to
+/// the one being rasterized. Finished frames are received with [`recv`](Self::recv) or
+/// [`try_recv`](Self::try_recv), and their buffers can be handed back with [`recycle`](Self::recycle)
+/// to avoid reallocating them for every frame.
+pub struct ThreadedImageRenderer<R: ImageRenderer> {
+    job_sender: Option<SyncSender<Job>>,
+    frame_receiver: Receiver<RenderedFrame>,
+    buffer_sender: Sender<Vec<u8>>,
+    thread: Option<JoinHandle<()>>,
+    in_flight: usize,
+    max_frames_in_flight: usize,
+    _renderer: PhantomData<fn() -> R>,
+}
+
+impl<R: ImageRenderer + 'static> ThreadedImageRenderer<R> {
+    /// Spawn a render thread with a double buffered queue
+    pub fn new(width: u32, height: u32) -> Self {
+        Self::with_frames_in_flight(width, height, DEFAULT_FRAMES_IN_FLIGHT)
+    }
+
+    /// Spawn a render thread that allows up to `frames_in_flight` submitted scenes that have not been received yet
+    pub fn with_frames_in_flight(width: u32, height: u32, frames_in_flight: usize) -> Self {
+        let frames_in_flight = frames_in_flight.max(1);
+        let (job_sender, job_receiver) = mpsc::sync_channel(frames_in_flight - 1);
+        let (frame_sender, frame_receiver) = mpsc::sync_channel(frames_in_flight);
+        let (buffer_sender, buffer_receiver) = mpsc::channel();
+
+        // The renderer is created on the render thread as it may not be `Send`
+        let thread = thread::Builder::new()
+            .name("multirender".into())
+            .spawn(move || {
+                render_thread::<R>(width, height, job_receiver, frame_sender, buffer_receiver)
+            })
+            .expect("Failed to spawn render thread");
+
+        Self {
+            job_sender: Some(job_sender),
+            frame_receiver,
+            buffer_sender,
+            thread: Some(thread),
+            in_flight: 0,
+            max_frames_in_flight: frames_in_flight,
+            _renderer: PhantomData,
+        }
+    }
+}
+
+impl<R: ImageRenderer> ThreadedImageRenderer<R> {
+    /// The number of submitted scenes that have not been received yet
+    pub fn frames_in_flight(&self) -> usize {
+        self.in_flight
+    }
+
+    /// Submit a scene for rendering. Blocks while the render thread's queue is full.
+    ///
+    /// Returns [`SubmitError::Full`] without blocking if `frames_in_flight` scenes have been submitted
+    /// whose frames have not been received yet, as waiting would never finish.
+    pub fn submit(&mut self, scene: Scene) -> Result<(), SubmitError> {
+        if self.in_flight >= self.max_frames_in_flight {
+            return Err(SubmitError::Full(scene));
+        }
+        match self.send(Job::Render(scene)) {
+            Ok(()) => {
+                self.in_flight += 1;
+                Ok(())
+            }
+            Err(Job::Render(scene)) => Err(SubmitError::Disconnected(scene)),
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Submit a scene for rendering without blocking
+    pub fn try_submit(&mut self, scene: Scene) -> Result<(), SubmitError> {
+        if self.in_flight >= self.max_frames_in_flight {
+            return Err(SubmitError::Full(scene));
+        }
+        let Some(sender) = &self.job_sender else {
+            return Err(SubmitError::Disconnected(scene));
+        };
+        match sender.try_send(Job::Render(scene)) {
+            Ok(()) => {
+                self.in_flight += 1;
+                Ok(())
+            }
+            Err(TrySendError::Full(Job::Render(scene))) => Err(SubmitError::Full(scene)),
+            Err(TrySendError::Disconnected(Job::Render(scene))) => {
+                Err(SubmitError::Disconnected(scene))
+            }
+            Err(_) => unreachable!(),
+        }
+    }
+
+    /// Resize the render target. Applies to scenes submitted after this call.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        let _ = self.send(Job::Resize(width, height));
+    }
+
+    /// Set the scale factor of the wrapped renderer. Applies to scenes submitted after this call.
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        let _ = self.send(Job::SetScaleFactor(scale_factor));
+    }
+
+    /// Wait for the next rendered frame. Returns `None` if no frames are in flight or the render thread has exited.
+    ///
+    /// Once the render thread has exited, the frames that were in flight will never be rendered, so none are counted
+    /// as in flight anymore and submitting returns [`SubmitError::Disconnected`].
+    pub fn recv(&mut self) -> Option<RenderedFrame> {
+        if self.in_flight == 0 {
+            return None;
+        }
+        match self.frame_receiver.recv() {
+            Ok(frame) => {
+                self.in_flight -= 1;
+                Some(frame)
+            }
+            Err(_) => {
+                self.in_flight = 0;
+                None
+            }
+        }
+    }
+
+    /// Receive the next rendered frame if one is ready
+    pub fn try_recv(&mut self) -> Option<RenderedFrame> {
+        match self.frame_receiver.try_recv() {
+            Ok(frame) => {
+                self.in_flight -= 1;
+                Some(frame)
+            }
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => {
+                self.in_flight = 0;
+                None
+            }
+        }
+    }
+
+    /// Receive all frames that are ready, returning only the most recent one
+    pub fn try_recv_latest(&mut self) -> Option<RenderedFrame> {
+        let mut latest = None;
+        while let Some(frame) = self.try_recv() {
+            if let Some(RenderedFrame { buffer, .. }) = latest.replace(frame) {
+                self.recycle(buffer);
+            }
+        }
+        latest
+    }
+
+    /// Hand a buffer back to the render thread so that it can be reused for a later frame
+    pub fn recycle(&mut self, buffer: Vec<u8>) {
+        let _ = self.buffer_sender.send(buffer);
+    }
+
+    fn send(&mut self, job: Job) -> Result<(), Job> {
+        let Some(sender) = &self.job_sender else {
+            return Err(job);
+        };
+        sender.send(job).map_err(|err| err.0)
+    }
+}
+
+impl<R: ImageRenderer> Drop for ThreadedImageRenderer<R> {
+    fn drop(&mut self) {
+        // Closing the job channel stops the render thread once it has rendered the scenes that are already queued.
+        // Frames that have not been received are discarded.
+        self.job_sender = None;
+        if let Some(thread) = self.thread.take() {
+            // Drain frames until the render thread exits so that it never blocks on a full channel
+            while self.frame_receiver.recv().is_ok() {}
+            let _ = thread.join();
+        }
+    }
+}
+
+fn render_thread<R: ImageRenderer>(
+    width: u32,
+    height: u32,
+    jobs: Receiver<Job>,
+    frames: SyncSender<RenderedFrame>,
+    buffers: Receiver<Vec<u8>>,
+) {
+    let mut renderer = R::new(width, height);
+    let (mut width, mut height) = (width, height);
+
+    while let Ok(job) = jobs.recv() {
+        match job {
+            Job::Resize(new_width, new_height) => {
+                width = new_width;
+                height = new_height;
+                renderer.resize(width, height);
+            }
+            Job::SetScaleFactor(scale_factor) => renderer.set_scale_factor(scale_factor),
+            Job::Render(scene) => {
+                let mut buffer = buffers.try_recv().unwrap_or_default();
+                let stats = renderer.render_to_vec(
+                    |painter| painter.append_scene(scene, Affine::IDENTITY),
+                    &mut buffer,
+                );
+                renderer.reset();
+
+                let frame = RenderedFrame {
+                    buffer,
+                    width,
+                    height,
+                    stats,
+                };
+                if frames.send(frame).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{CountingRenderer, scene_with_fills};
+
+    #[test]
+    fn renders_scenes_in_order() {
+        let mut renderer = ThreadedImageRenderer::<CountingRenderer>::new(2, 2);
+
+        renderer.submit(scene_with_fills(1)).unwrap();
+        renderer.submit(scene_with_fills(2)).unwrap();
+        assert!(matches!(
+            renderer.try_submit(scene_with_fills(3)),
+            Err(SubmitError::Full(_))
+        ));
+
+        let first = renderer.recv().unwrap();
+        assert_eq!(first.buffer, vec![1; 16]);
+        renderer.recycle(first.buffer);
+
+        renderer.resize(1, 1);
+        renderer.submit(scene_with_fills(3)).unwrap();
+
+        let second = renderer.recv().unwrap();
+        assert_eq!((second.width, second.height), (2, 2));
+        assert_eq!(second.buffer, vec![2; 16]);
+
+        let third = renderer.recv().unwrap();
+        assert_eq!((third.width, third.height), (1, 1));
+        assert_eq!(third.buffer, vec![3; 4]);
+
+        assert!(renderer.recv().is_none());
+        assert_eq!(renderer.frames_in_flight(), 0);
+    }
+
+    #[test]
+    fn drop_with_frames_in_flight() {
+        let mut renderer = ThreadedImageRenderer::<CountingRenderer>::new(16, 16);
+        renderer.submit(scene_with_fills(1)).unwrap();
+        renderer.submit(scene_with_fills(1)).unwrap();
+        drop(renderer);
+    }
+
+    #[test]
+    fn render_thread_panic_clears_frames_in_flight() {
+        let mut renderer = ThreadedImageRenderer::<CountingRenderer>::new(2, 2);
+        // The render thread panics when resizing beyond the maximum dimension
+        renderer.resize(CountingRenderer::MAX_DIMENSION + 1, 1);
+        let _ = renderer.submit(scene_with_fills(1));
+
+        assert!(renderer.recv().is_none());
+        assert_eq!(renderer.frames_in_flight(), 0);
+        assert!(matches!(
+            renderer.submit(scene_with_fills(1)),
+            Err(SubmitError::Disconnected(_))
+        ));
+        assert!(renderer.try_recv().is_none());
+        assert_eq!(renderer.frames_in_flight(), 0);
+    }
+}
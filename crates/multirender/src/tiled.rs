@@ -0,0 +1,272 @@
+//! Rendering of images that are larger than a single `ImageRenderer` target by splitting them into tiles
+
+use crate::{DEFAULT_SCALE_FACTOR, ImageRenderer, PaintScene, Scene};
+use kurbo::Affine;
+use std::fmt;
+use std::io::{self, Write};
+
+/// The default width and height of a tile in physical pixels
+pub const DEFAULT_TILE_SIZE: u32 = 2048;
+
+/// Error returned by [`TiledRenderer`]
+#[derive(Debug)]
+pub enum TiledRenderError {
+    /// The output image does not fit in memory on this platform
+    TooLarge { width: u32, height: u32 },
+    /// Writing pixels to the output failed
+    Io(io::Error),
+}
+
+impl fmt::Display for TiledRenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TiledRenderError::TooLarge { width, height } => {
+                write!(
+                    f,
+                    "an image of {width}x{height} pixels does not fit in memory"
+                )
+            }
+            TiledRenderError::Io(err) => write!(f, "failed to write pixels: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for TiledRenderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TiledRenderError::TooLarge { .. } => None,
+            TiledRenderError::Io(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for TiledRenderError {
+    fn from(err: io::Error) -> Self {
+        TiledRenderError::Io(err)
+    }
+}
+
+/// Renders a recorded [`Scene`] into an image of arbitrary size by rendering it one tile at a time through an
+/// [`ImageRenderer`], offsetting the scene for each tile.
+///
+/// Tiles are clamped to [`ImageRenderer::MAX_DIMENSION`]. The output is RGBA8 in the renderer's pixel format and can
+/// either be stitched into a single buffer, or streamed one row of pixels at a time (for example into a PNG encoder)
+/// so that only a single row of tiles is held in memory.
+pub struct TiledRenderer<R: ImageRenderer> {
+    renderer: R,
+    tile_size: u32,
+    scale_factor: f64,
+}
+
+impl<R: ImageRenderer> Default for TiledRenderer<R> {
+    fn default() -> Self {
+        Self::new(DEFAULT_TILE_SIZE)
+    }
+}
+
+impl<R: ImageRenderer> TiledRenderer<R> {
+    pub fn new(tile_size: u32) -> Self {
+        let tile_size = Self::clamp_tile_size(tile_size);
+        Self::with_renderer(R::new(tile_size, tile_size), tile_size)
+    }
+
+    pub fn with_renderer(renderer: R, tile_size: u32) -> Self {
+        Self {
+            renderer,
+            tile_size: Self::clamp_tile_size(tile_size),
+            scale_factor: DEFAULT_SCALE_FACTOR,
+        }
+    }
+
+    fn clamp_tile_size(tile_size: u32) -> u32 {
+        tile_size.clamp(1, R::MAX_DIMENSION)
+    }
+
+    pub fn tile_size(&self) -> u32 {
+        self.tile_size
+    }
+
+    /// Set the factor used to map the scene's logical units to output pixels
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+    }
+
+    /// Render `scene` into `buffer`, resizing it to `width * height * 4` bytes
+    pub fn render_to_vec(
+        &mut self,
+        scene: &Scene,
+        width: u32,
+        height: u32,
+        buffer: &mut Vec<u8>,
+    ) -> Result<(), TiledRenderError> {
+        let len = buffer_len(width, height).ok_or(TiledRenderError::TooLarge { width, height })?;
+        buffer.clear();
+        buffer
+            .try_reserve_exact(len)
+            .map_err(|_| TiledRenderError::TooLarge { width, height })?;
+        self.render_rows(scene, width, height, |rows| {
+            buffer.extend_from_slice(rows);
+            Ok(())
+        })
+    }
+
+    /// Render `scene` and write its pixels to `writer` row by row, top to bottom
+    pub fn render_to_writer(
+        &mut self,
+        scene: &Scene,
+        width: u32,
+        height: u32,
+        mut writer: impl Write,
+    ) -> Result<(), TiledRenderError> {
+        self.render_rows(scene, width, height, |rows| {
+            writer.write_all(rows)?;
+            Ok(())
+        })?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Render `scene` one row of tiles at a time. `sink` is called once per row of tiles with the pixels of
+    /// consecutive rows of the image, top to bottom, each row being `width * 4` bytes.
+    pub fn render_rows(
+        &mut self,
+        scene: &Scene,
+        width: u32,
+        height: u32,
+        mut sink: impl FnMut(&[u8]) -> Result<(), TiledRenderError>,
+    ) -> Result<(), TiledRenderError> {
+        let too_large = || TiledRenderError::TooLarge { width, height };
+        let row_stride = (width as usize).checked_mul(4).ok_or_else(too_large)?;
+        let strip_len = row_stride
+            .checked_mul(self.tile_size.min(height) as usize)
+            .ok_or_else(too_large)?;
+
+        let mut strip = Vec::new();
+        strip
+            .try_reserve_exact(strip_len)
+            .map_err(|_| too_large())?;
+        let mut tile = Vec::new();
+
+        self.renderer.set_scale_factor(self.scale_factor);
+
+        for tile_y in (0..height).step_by(self.tile_size as usize) {
+            let tile_height = self.tile_size.min(height - tile_y);
+            strip.clear();
+            strip.resize(row_stride * tile_height as usize, 0);
+
+            for tile_x in (0..width).step_by(self.tile_size as usize) {
+                let tile_width = self.tile_size.min(width - tile_x);
+                self.render_tile(scene, tile_x, tile_y, tile_width, tile_height, &mut tile);
+
+                // Copy the tile into the strip
+                let tile_stride = tile_width as usize * 4;
+                let offset = tile_x as usize * 4;
+                for (row, tile_row) in tile.chunks_exact(tile_stride).enumerate() {
+                    let start = row * row_stride + offset;
+                    strip[start..start + tile_stride].copy_from_slice(tile_row);
+                }
+            }
+
+            sink(&strip)?;
+        }
+
+        Ok(())
+    }
+
+    fn render_tile(
+        &mut self,
+        scene: &Scene,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        buffer: &mut Vec<u8>,
+    ) {
+        // The renderer applies the scale factor as the root transform, so the offset is specified in logical units
+        let offset = Affine::translate((
+            -(x as f64) / self.scale_factor,
+            -(y as f64) / self.scale_factor,
+        ));
+
+        self.renderer.resize(width, height);
+        self.renderer.render_to_vec(
+            |painter| painter.append_scene(scene.clone(), offset),
+            buffer,
+        );
+        self.renderer.reset();
+    }
+}
+
+fn buffer_len(width: u32, height: u32) -> Option<usize> {
+    (width as usize)
+        .checked_mul(height as usize)?
+        .checked_mul(4)
+        .filter(|len| *len <= isize::MAX as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::PositionRenderer;
+    use kurbo::Rect;
+    use peniko::{Color, Fill};
+
+    #[test]
+    fn stitches_tiles() {
+        // A 2x2 white square at (3, 1) in a 7x5 image
+        let mut scene = Scene::new();
+        scene.fill(
+            Fill::NonZero,
+            Affine::IDENTITY,
+            Color::WHITE,
+            None,
+            &Rect::new(3.0, 1.0, 5.0, 3.0),
+        );
+
+        let mut renderer = TiledRenderer::<PositionRenderer>::new(3);
+        let mut buffer = Vec::new();
+        renderer.render_to_vec(&scene, 7, 5, &mut buffer).unwrap();
+        assert_eq!(buffer.len(), 7 * 5 * 4);
+
+        for y in 0..5 {
+            for x in 0..7 {
+                let inside = (3..5).contains(&x) && (1..3).contains(&y);
+                let pixel = &buffer[(y * 7 + x) * 4..][..4];
+                assert_eq!(pixel[0] == 255, inside, "pixel ({x}, {y})");
+            }
+        }
+
+        let mut streamed = Vec::new();
+        renderer
+            .render_to_writer(&scene, 7, 5, &mut streamed)
+            .unwrap();
+        assert_eq!(streamed, buffer);
+    }
+
+    #[test]
+    fn scales_tile_offsets() {
+        let mut scene = Scene::new();
+        scene.fill(
+            Fill::NonZero,
+            Affine::IDENTITY,
+            Color::WHITE,
+            None,
+            &Rect::new(2.0, 0.0, 3.0, 1.0),
+        );
+
+        let mut renderer = TiledRenderer::<PositionRenderer>::new(4);
+        renderer.set_scale_factor(2.0);
+        let mut buffer = Vec::new();
+        renderer.render_to_vec(&scene, 8, 2, &mut buffer).unwrap();
+
+        let lit: Vec<usize> = (0..8).filter(|x| buffer[x * 4] == 255).collect();
+        assert_eq!(lit, vec![4, 5]);
+    }
+
+    #[test]
+    fn rejects_buffers_that_do_not_fit_in_memory() {
+        let mut renderer = TiledRenderer::<PositionRenderer>::new(16);
+        let result = renderer.render_to_vec(&Scene::new(), u32::MAX, u32::MAX, &mut Vec::new());
+        assert!(matches!(result, Err(TiledRenderError::TooLarge { .. })));
+    }
+}
@@ -0,0 +1,203 @@
+//! Opening scene archives and multi-frame archives through a common interface.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use multirender::recording::{RenderCommand, Scene};
+use multirender_serialize::{
+    CommandEncoding, FontMetadata, FrameArchiveReader, ImageMetadata, ResourceKind, SceneArchive,
+    SerializableRenderCommand, visit_resources,
+};
+use zip::ZipArchive;
+
+use crate::Result;
+
+/// A scene archive or a multi-frame archive. Scene archives are treated as a single frame.
+pub enum Archive {
+    Scene(SceneArchive),
+    Frames(FrameArchiveReader<BufReader<File>>),
+}
+
+/// An archive along with the path it was opened from.
+pub struct OpenArchive {
+    pub path: PathBuf,
+    pub archive: Archive,
+}
+
+impl OpenArchive {
+    /// Open the archive at `path`, detecting whether it is a multi-frame archive.
+    pub fn open(path: &Path) -> Result<Self> {
+        let is_frame_archive = open_zip(path)?
+            .file_names()
+            .any(|name| name == "frames.json");
+
+        let reader = BufReader::new(File::open(path)?);
+        let archive = if is_frame_archive {
+            Archive::Frames(FrameArchiveReader::new(reader)?)
+        } else {
+            Archive::Scene(SceneArchive::deserialize(reader)?)
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            archive,
+        })
+    }
+
+    pub fn version(&self) -> u32 {
+        match &self.archive {
+            Archive::Scene(archive) => archive.manifest.version,
+            Archive::Frames(reader) => reader.manifest().version,
+        }
+    }
+
+    pub fn command_encoding(&self) -> CommandEncoding {
+        match &self.archive {
+            Archive::Scene(archive) => archive.manifest.command_encoding,
+            Archive::Frames(reader) => reader.manifest().command_encoding,
+        }
+    }
+
+    pub fn images(&self) -> &[ImageMetadata] {
+        match &self.archive {
+            Archive::Scene(archive) => &archive.manifest.images,
+            Archive::Frames(reader) => &reader.manifest().images,
+        }
+    }
+
+    pub fn fonts(&self) -> &[FontMetadata] {
+        match &self.archive {
+            Archive::Scene(archive) => &archive.manifest.fonts,
+            Archive::Frames(reader) => &reader.manifest().fonts,
+        }
+    }
+
+    pub fn frame_count(&self) -> usize {
+        match &self.archive {
+            Archive::Scene(_) => 1,
+            Archive::Frames(reader) => reader.len(),
+        }
+    }
+
+    /// The viewport size recorded for a frame. Scene archives do not record a viewport size.
+    pub fn frame_size(&self, frame: usize) -> Option<(u32, u32)> {
+        match &self.archive {
+            Archive::Scene(_) => None,
+            Archive::Frames(reader) => reader
+                .manifest()
+                .frames
+                .get(frame)
+                .map(|meta| (meta.width, meta.height)),
+        }
+    }
+
+    /// The draw commands of a frame, with resources referenced by ID.
+    pub fn commands(&mut self, frame: usize) -> Result<Vec<SerializableRenderCommand>> {
+        self.check_frame(frame)?;
+        match &mut self.archive {
+            Archive::Scene(archive) => Ok(archive.commands.clone()),
+            Archive::Frames(reader) => Ok(reader
+                .frame_commands(frame)
+                .expect("frame index was checked")?),
+        }
+    }
+
+    /// The draw commands of a frame, with resource IDs replaced by IDs allocated from `resources`, so that commands
+    /// from different archives that reference resources with the same content compare equal.
+    pub fn canonical_commands(
+        &mut self,
+        frame: usize,
+        resources: &mut ResourceInterner,
+    ) -> Result<Vec<SerializableRenderCommand>> {
+        let mut commands = self.commands(frame)?;
+        for cmd in &mut commands {
+            visit_resources(cmd, |kind, id| {
+                let hash = match kind {
+                    ResourceKind::Image => self.images().get(id.0).map(|meta| &meta.entry),
+                    ResourceKind::Font => self.fonts().get(id.0).map(|meta| &meta.entry),
+                }
+                .map(|entry| entry.sha256_hash.clone());
+                // Dangling references keep a distinct ID so that they never compare equal to a real resource
+                id.0 = match hash {
+                    Some(hash) => resources.intern(kind, hash),
+                    None => usize::MAX - id.0,
+                };
+            });
+        }
+        Ok(commands)
+    }
+
+    /// Decode a frame into a scene.
+    pub fn scene(&mut self, frame: usize) -> Result<Scene> {
+        self.check_frame(frame)?;
+        match &mut self.archive {
+            Archive::Scene(archive) => Ok(archive.to_scene()?),
+            Archive::Frames(reader) => {
+                Ok(reader.frame(frame).expect("frame index was checked")?.scene)
+            }
+        }
+    }
+
+    /// Read a file stored in the archive, such as an image or a font.
+    pub fn read_file(&self, name: &str) -> Result<Vec<u8>> {
+        let mut zip = open_zip(&self.path)?;
+        let mut file = zip.by_name(name)?;
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    /// The name, size and compressed size of every file in the archive.
+    pub fn files(&self) -> Result<Vec<(String, u64, u64)>> {
+        let mut zip = open_zip(&self.path)?;
+        (0..zip.len())
+            .map(|index| {
+                let file = zip.by_index(index)?;
+                Ok((file.name().to_string(), file.size(), file.compressed_size()))
+            })
+            .collect()
+    }
+
+    fn check_frame(&self, frame: usize) -> Result<()> {
+        if frame >= self.frame_count() {
+            return Err(format!(
+                "{} has {} frame(s), there is no frame {frame}",
+                self.path.display(),
+                self.frame_count()
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// Allocates IDs for resources based on their kind and content hash.
+#[derive(Default)]
+pub struct ResourceInterner {
+    ids: HashMap<(ResourceKind, String), usize>,
+}
+
+impl ResourceInterner {
+    fn intern(&mut self, kind: ResourceKind, hash: String) -> usize {
+        let next = self.ids.len();
+        *self.ids.entry((kind, hash)).or_insert(next)
+    }
+}
+
+/// A short, stable name for the kind of a command.
+pub fn command_name<F, I>(cmd: &RenderCommand<F, I>) -> &'static str {
+    match cmd {
+        RenderCommand::PushLayer(_) => "push_layer",
+        RenderCommand::PushClipLayer(_) => "push_clip_layer",
+        RenderCommand::PopLayer => "pop_layer",
+        RenderCommand::Stroke(_) => "stroke",
+        RenderCommand::Fill(_) => "fill",
+        RenderCommand::GlyphRun(_) => "glyph_run",
+        RenderCommand::BoxShadow(_) => "box_shadow",
+    }
+}
+
+fn open_zip(path: &Path) -> Result<ZipArchive<BufReader<File>>> {
+    Ok(ZipArchive::new(BufReader::new(File::open(path)?))?)
+}
@@ -0,0 +1,147 @@
+//! `diff`: compare two archives at the command level and the pixel level.
+
+use std::path::Path;
+
+use image::{Rgba, RgbaImage};
+
+use crate::Result;
+use crate::archive::{OpenArchive, ResourceInterner, command_name};
+use crate::render::{RenderArgs, render};
+
+/// The maximum number of differing commands that are listed
+const MAX_LISTED_DIFFERENCES: usize = 10;
+
+/// Compare a frame of two archives. Returns whether the frames are identical.
+pub fn run(
+    a: &mut OpenArchive,
+    b: &mut OpenArchive,
+    args: &RenderArgs,
+    threshold: u8,
+    output: Option<&Path>,
+) -> Result<bool> {
+    let commands_equal = diff_commands(a, b, args.frame)?;
+    let pixels_equal = diff_pixels(a, b, args, threshold, output)?;
+    Ok(commands_equal && pixels_equal.unwrap_or(true))
+}
+
+/// Compare the commands of two frames. Resources are compared by their content hash rather than their ID.
+fn diff_commands(a: &mut OpenArchive, b: &mut OpenArchive, frame: usize) -> Result<bool> {
+    let mut resources = ResourceInterner::default();
+    let commands_a = a.canonical_commands(frame, &mut resources)?;
+    let commands_b = b.canonical_commands(frame, &mut resources)?;
+
+    let mut differences = commands_a
+        .iter()
+        .zip(&commands_b)
+        .enumerate()
+        .filter(|(_, (cmd_a, cmd_b))| cmd_a != cmd_b)
+        .peekable();
+
+    let equal = differences.peek().is_none() && commands_a.len() == commands_b.len();
+    if equal {
+        println!("Commands: identical ({} commands)", commands_a.len());
+        return Ok(true);
+    }
+
+    println!(
+        "Commands: {} vs {} commands",
+        commands_a.len(),
+        commands_b.len()
+    );
+    let mut count = 0;
+    for (index, (cmd_a, cmd_b)) in differences {
+        if count < MAX_LISTED_DIFFERENCES {
+            let (name_a, name_b) = (command_name(cmd_a), command_name(cmd_b));
+            if name_a == name_b {
+                println!("  #{index}: {name_a} differs");
+            } else {
+                println!("  #{index}: {name_a} -> {name_b}");
+            }
+        }
+        count += 1;
+    }
+    if count > MAX_LISTED_DIFFERENCES {
+        println!("  ... and {} more", count - MAX_LISTED_DIFFERENCES);
+    }
+    println!("  {count} differing commands");
+
+    let common = commands_a.len().min(commands_b.len());
+    if commands_a.len() > common {
+        println!(
+            "  {} commands only in the first archive",
+            commands_a.len() - common
+        );
+    }
+    if commands_b.len() > common {
+        println!(
+            "  {} commands only in the second archive",
+            commands_b.len() - common
+        );
+    }
+    Ok(false)
+}
+
+/// Render both frames and compare their pixels. Returns `None` if no output size is known.
+fn diff_pixels(
+    a: &mut OpenArchive,
+    b: &mut OpenArchive,
+    args: &RenderArgs,
+    threshold: u8,
+    output: Option<&Path>,
+) -> Result<Option<bool>> {
+    let Some((width, height)) = args.output_size(a).or_else(|| args.output_size(b)) else {
+        println!("Pixels: skipped, pass --width and --height to compare scene archives");
+        return Ok(None);
+    };
+
+    let image_a = render(&a.scene(args.frame)?, width, height, args)?;
+    let image_b = render(&b.scene(args.frame)?, width, height, args)?;
+
+    let mut differing = 0_u64;
+    let mut max_difference = 0;
+    let mut diff_image = output.map(|_| RgbaImage::new(width, height));
+    for (x, y, pixel_a) in image_a.enumerate_pixels() {
+        let pixel_b = image_b.get_pixel(x, y);
+        let difference = pixel_a
+            .0
+            .iter()
+            .zip(pixel_b.0)
+            .map(|(a, b)| a.abs_diff(b))
+            .max()
+            .unwrap_or(0);
+        max_difference = max_difference.max(difference);
+        let differs = difference > threshold;
+        if differs {
+            differing += 1;
+        }
+
+        if let Some(diff_image) = &mut diff_image {
+            // Differing pixels are drawn in red over a faded copy of the first image
+            let pixel = if differs {
+                Rgba([255, 0, 0, 255])
+            } else {
+                let [r, g, b, a] = pixel_a.0;
+                let luma = ((r as u32 * 3 + g as u32 * 6 + b as u32) / 10) as u8;
+                Rgba([luma, luma, luma, a / 4])
+            };
+            diff_image.put_pixel(x, y, pixel);
+        }
+    }
+
+    let total = width as u64 * height as u64;
+    if differing == 0 {
+        println!("Pixels: identical at {width}x{height} (max channel difference {max_difference})");
+    } else {
+        println!(
+            "Pixels: {differing} of {total} pixels differ ({:.3}%) at {width}x{height}, max channel difference {max_difference}",
+            differing as f64 * 100.0 / total as f64
+        );
+    }
+
+    if let (Some(diff_image), Some(output)) = (diff_image, output) {
+        diff_image.save(output)?;
+        println!("Wrote difference image to {}", output.display());
+    }
+
+    Ok(Some(differing == 0))
+}
@@ -0,0 +1,57 @@
+//! `extract`: write the images and fonts stored in an archive to a directory.
+
+use std::fs;
+use std::path::Path;
+
+use multirender_serialize::ResourceEntry;
+
+use crate::Result;
+use crate::archive::OpenArchive;
+
+pub fn run(archive: &OpenArchive, output: &Path, decompress_fonts: bool) -> Result<()> {
+    for image in archive.images() {
+        let data = archive.read_file(&image.entry.path)?;
+        write_resource(output, "images", file_name(&image.entry)?, &data)?;
+    }
+
+    for font in archive.fonts() {
+        let data = archive.read_file(&font.entry.path)?;
+        let name = file_name(&font.entry)?;
+        if decompress_fonts && data.starts_with(b"wOF2") {
+            let font_data = wuff::decompress_woff2(&data)
+                .map_err(|err| format!("failed to decompress {}: {err}", font.entry.path))?;
+            let extension = if font_data.starts_with(b"OTTO") {
+                "otf"
+            } else {
+                "ttf"
+            };
+            let stem = Path::new(name)
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or(name);
+            write_resource(output, "fonts", &format!("{stem}.{extension}"), &font_data)?;
+        } else {
+            write_resource(output, "fonts", name, &data)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The file name of a resource. Only the file name of the path recorded in the manifest is used, so that resources
+/// are never written outside of the output directory.
+fn file_name(entry: &ResourceEntry) -> Result<&str> {
+    Path::new(&entry.path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| format!("invalid resource path {:?}", entry.path).into())
+}
+
+fn write_resource(output: &Path, dir: &str, name: &str, data: &[u8]) -> Result<()> {
+    let dir = output.join(dir);
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(name);
+    fs::write(&path, data)?;
+    println!("{}", path.display());
+    Ok(())
+}
@@ -0,0 +1,168 @@
+//! `info`: print the manifest, resource sizes and command counts of an archive.
+
+use std::collections::BTreeMap;
+
+use multirender::recording::RenderCommand;
+use multirender_serialize::SerializableRenderCommand;
+
+use crate::Result;
+use crate::archive::{Archive, OpenArchive, command_name};
+
+pub fn run(archive: &mut OpenArchive) -> Result<()> {
+    println!("{}", archive.path.display());
+    match &archive.archive {
+        Archive::Scene(scene) => {
+            println!("  kind:             scene archive");
+            println!("  tolerance:        {}", scene.manifest.tolerance);
+        }
+        Archive::Frames(reader) => {
+            println!("  kind:             frame archive");
+            println!("  frames:           {}", reader.len());
+        }
+    }
+    println!("  format version:   {}", archive.version());
+    println!("  command encoding: {:?}", archive.command_encoding());
+
+    // Commands
+    let mut totals = CommandCounts::default();
+    if let Archive::Frames(_) = archive.archive {
+        println!();
+        println!("Frames:");
+    }
+    for frame in 0..archive.frame_count() {
+        let commands = archive.commands(frame)?;
+        let counts = CommandCounts::new(&commands);
+        if let Archive::Frames(reader) = &archive.archive {
+            let meta = &reader.manifest().frames[frame];
+            println!(
+                "  #{frame:<5} {:>10.3}s  {}x{}  {} commands",
+                meta.timestamp.as_secs_f64(),
+                meta.width,
+                meta.height,
+                counts.commands,
+            );
+        }
+        totals.add(&counts);
+    }
+
+    println!();
+    println!("Commands: {}", totals.commands);
+    for (name, count) in &totals.by_kind {
+        println!("  {name:<16} {count}");
+    }
+    println!("  glyphs           {}", totals.glyphs);
+    println!("  max layer depth  {}", totals.max_layer_depth);
+
+    // Resources
+    let files: BTreeMap<String, (u64, u64)> = archive
+        .files()?
+        .into_iter()
+        .map(|(name, size, compressed)| (name, (size, compressed)))
+        .collect();
+    let stored_size = |path: &str| files.get(path).map_or(0, |(size, _)| *size);
+
+    println!();
+    println!("Images: {}", archive.images().len());
+    for image in archive.images() {
+        println!(
+            "  #{:<4} {}x{} {:?} {:?} {:?}, {} raw, {} stored  {}",
+            image.entry.id.0,
+            image.width,
+            image.height,
+            image.encoding,
+            image.format,
+            image.alpha_type,
+            format_bytes(image.entry.size as u64),
+            format_bytes(stored_size(&image.entry.path)),
+            image.entry.path,
+        );
+    }
+
+    println!();
+    println!("Fonts: {}", archive.fonts().len());
+    for font in archive.fonts() {
+        println!(
+            "  #{:<4} {} raw, {} stored  {}",
+            font.entry.id.0,
+            format_bytes(font.entry.size as u64),
+            format_bytes(stored_size(&font.entry.path)),
+            font.entry.path,
+        );
+    }
+
+    println!();
+    println!("Files: {}", files.len());
+    let (mut total_size, mut total_compressed) = (0, 0);
+    for (name, (size, compressed)) in &files {
+        println!(
+            "  {:>10} {:>10}  {name}",
+            format_bytes(*size),
+            format_bytes(*compressed)
+        );
+        total_size += size;
+        total_compressed += compressed;
+    }
+    println!(
+        "  {:>10} {:>10}  (total, uncompressed and compressed)",
+        format_bytes(total_size),
+        format_bytes(total_compressed)
+    );
+
+    Ok(())
+}
+
+#[derive(Default)]
+struct CommandCounts {
+    commands: usize,
+    by_kind: BTreeMap<&'static str, usize>,
+    glyphs: usize,
+    max_layer_depth: usize,
+}
+
+impl CommandCounts {
+    fn new(commands: &[SerializableRenderCommand]) -> Self {
+        let mut counts = Self {
+            commands: commands.len(),
+            ..Self::default()
+        };
+        let mut depth = 0_usize;
+        for cmd in commands {
+            *counts.by_kind.entry(command_name(cmd)).or_default() += 1;
+            match cmd {
+                RenderCommand::PushLayer(_) | RenderCommand::PushClipLayer(_) => {
+                    depth += 1;
+                    counts.max_layer_depth = counts.max_layer_depth.max(depth);
+                }
+                RenderCommand::PopLayer => depth = depth.saturating_sub(1),
+                RenderCommand::GlyphRun(glyph_run) => counts.glyphs += glyph_run.glyphs.len(),
+                RenderCommand::Fill(_) | RenderCommand::Stroke(_) | RenderCommand::BoxShadow(_) => {
+                }
+            }
+        }
+        counts
+    }
+
+    fn add(&mut self, other: &Self) {
+        self.commands += other.commands;
+        for (name, count) in &other.by_kind {
+            *self.by_kind.entry(name).or_default() += count;
+        }
+        self.glyphs += other.glyphs;
+        self.max_layer_depth = self.max_layer_depth.max(other.max_layer_depth);
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
@@ -0,0 +1,109 @@
+//! Command-line tool for inspecting, validating, rendering, and comparing archives written by `multirender_serialize`.
+//!
+//! Both single-scene archives and multi-frame archives are supported. Multi-frame archives are rendered and compared
+//! one frame at a time, selected with `--frame`.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+
+mod archive;
+mod diff;
+mod extract;
+mod info;
+mod render;
+mod validate;
+
+use archive::OpenArchive;
+use render::RenderArgs;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+#[derive(Parser)]
+#[command(name = "multirender-archive", version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the manifest, resource sizes and command counts of an archive
+    Info { archive: PathBuf },
+    /// Check resource hashes, resource references and layer balance
+    Validate { archive: PathBuf },
+    /// Rasterize a frame of an archive to a PNG file
+    Render {
+        archive: PathBuf,
+        /// The PNG file to write
+        #[arg(short, long)]
+        output: PathBuf,
+        #[command(flatten)]
+        render: RenderArgs,
+    },
+    /// Write the images and fonts stored in an archive to a directory
+    Extract {
+        archive: PathBuf,
+        /// The directory to write `images/` and `fonts/` to
+        output: PathBuf,
+        /// Decompress WOFF2 fonts to TrueType/OpenType
+        #[arg(long)]
+        decompress_fonts: bool,
+    },
+    /// Compare a frame of two archives at the command level and the pixel level.
+    /// Exits with status 1 if they differ.
+    Diff {
+        a: PathBuf,
+        b: PathBuf,
+        #[command(flatten)]
+        render: RenderArgs,
+        /// Pixels are considered equal if no channel differs by more than this
+        #[arg(long, default_value_t = 0)]
+        threshold: u8,
+        /// Write an image highlighting the differing pixels to this PNG file
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse().command) {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run(command: Command) -> Result<ExitCode> {
+    match command {
+        Command::Info { archive } => info::run(&mut OpenArchive::open(&archive)?)?,
+        Command::Validate { archive } => return validate::run(&archive),
+        Command::Render {
+            archive,
+            output,
+            render,
+        } => render::run(&mut OpenArchive::open(&archive)?, &output, &render)?,
+        Command::Extract {
+            archive,
+            output,
+            decompress_fonts,
+        } => extract::run(&OpenArchive::open(&archive)?, &output, decompress_fonts)?,
+        Command::Diff {
+            a,
+            b,
+            render,
+            threshold,
+            output,
+        } => {
+            let mut a = OpenArchive::open(&a)?;
+            let mut b = OpenArchive::open(&b)?;
+            if !diff::run(&mut a, &mut b, &render, threshold, output.as_deref())? {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
@@ -0,0 +1,117 @@
+//! `render`: rasterize a frame of an archive to a PNG file.
+
+use std::path::Path;
+
+use clap::{Args, ValueEnum};
+use image::RgbaImage;
+use multirender::recording::Scene;
+use multirender::{DEFAULT_TILE_SIZE, ImageRenderer, TiledRenderer};
+
+use crate::Result;
+use crate::archive::OpenArchive;
+
+/// The renderer used to rasterize scenes
+#[derive(Copy, Clone, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Backend {
+    /// vello_cpu
+    VelloCpu,
+    /// Skia's raster backend (requires the `skia` feature)
+    Skia,
+}
+
+#[derive(Args)]
+pub struct RenderArgs {
+    /// The frame to render (multi-frame archives only)
+    #[arg(long, default_value_t = 0)]
+    pub frame: usize,
+    /// Width of the scene in logical pixels [default: the frame's viewport width]
+    #[arg(long)]
+    pub width: Option<u32>,
+    /// Height of the scene in logical pixels [default: the frame's viewport height]
+    #[arg(long)]
+    pub height: Option<u32>,
+    /// Scale factor applied to the scene and the output size
+    #[arg(long, default_value_t = 1.0, value_parser = parse_scale)]
+    pub scale: f64,
+    #[arg(long, value_enum, default_value_t = Backend::VelloCpu)]
+    pub backend: Backend,
+}
+
+/// Parse a scale factor, which must be finite and positive
+fn parse_scale(value: &str) -> std::result::Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(scale) if scale.is_finite() && scale > 0.0 => Ok(scale),
+        Ok(_) => Err("the scale must be a finite number greater than 0".into()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+impl RenderArgs {
+    /// The size of the output image in physical pixels, or `None` if neither the arguments nor the archive specify it.
+    pub fn output_size(&self, archive: &OpenArchive) -> Option<(u32, u32)> {
+        let viewport = archive.frame_size(self.frame);
+        let width = self.width.or(viewport.map(|(width, _)| width))?;
+        let height = self.height.or(viewport.map(|(_, height)| height))?;
+        let scale = |size: u32| ((size as f64 * self.scale).round() as u32).max(1);
+        Some((scale(width), scale(height)))
+    }
+}
+
+pub fn run(archive: &mut OpenArchive, output: &Path, args: &RenderArgs) -> Result<()> {
+    let (width, height) = args.output_size(archive).ok_or(
+        "scene archives do not record a viewport size, pass --width and --height to render them",
+    )?;
+    let scene = archive.scene(args.frame)?;
+    let image = render(&scene, width, height, args)?;
+    image.save(output)?;
+    println!("Wrote {width}x{height} image to {}", output.display());
+    Ok(())
+}
+
+/// Render `scene` into an RGBA8 image of `width` by `height` physical pixels, with unpremultiplied alpha.
+pub fn render(scene: &Scene, width: u32, height: u32, args: &RenderArgs) -> Result<RgbaImage> {
+    let mut pixels = match args.backend {
+        Backend::VelloCpu => render_tiled::<multirender_vello_cpu::VelloCpuImageRenderer>(
+            scene, width, height, args,
+        )?,
+        #[cfg(feature = "skia")]
+        Backend::Skia => {
+            render_tiled::<multirender_skia::SkiaImageRenderer>(scene, width, height, args)?
+        }
+        #[cfg(not(feature = "skia"))]
+        Backend::Skia => {
+            return Err(
+                "this build does not include the Skia backend, rebuild with `--features skia`"
+                    .into(),
+            );
+        }
+    };
+    // Renderers produce premultiplied alpha, while PNG files store unpremultiplied alpha
+    unpremultiply(&mut pixels);
+    Ok(RgbaImage::from_raw(width, height, pixels)
+        .expect("renderer produced a buffer of the wrong size"))
+}
+
+fn unpremultiply(pixels: &mut [u8]) {
+    for pixel in pixels.chunks_exact_mut(4) {
+        let alpha = u16::from(pixel[3]);
+        for channel in &mut pixel[..3] {
+            if let Some(value) = (u16::from(*channel) * 255 + alpha / 2).checked_div(alpha) {
+                *channel = value.min(255) as u8;
+            }
+        }
+    }
+}
+
+fn render_tiled<R: ImageRenderer>(
+    scene: &Scene,
+    width: u32,
+    height: u32,
+    args: &RenderArgs,
+) -> Result<Vec<u8>> {
+    let mut renderer = TiledRenderer::<R>::new(DEFAULT_TILE_SIZE.min(width.max(height)));
+    renderer.set_scale_factor(args.scale);
+    let mut pixels = Vec::new();
+    renderer.render_to_vec(scene, width, height, &mut pixels)?;
+    Ok(pixels)
+}
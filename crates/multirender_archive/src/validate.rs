@@ -0,0 +1,112 @@
+//! `validate`: check resource hashes, resource references and layer balance.
+
+use std::path::Path;
+use std::process::ExitCode;
+
+use multirender::recording::RenderCommand;
+use multirender_serialize::{ResourceKind, SerializableRenderCommand, visit_resources};
+
+use crate::Result;
+use crate::archive::{Archive, OpenArchive};
+
+pub fn run(path: &Path) -> Result<ExitCode> {
+    // Resource hashes of scene archives are verified when the archive is opened
+    let mut archive = match OpenArchive::open(path) {
+        Ok(archive) => archive,
+        Err(err) => {
+            println!("{}: invalid", path.display());
+            println!("  {err}");
+            return Ok(ExitCode::FAILURE);
+        }
+    };
+
+    let mut problems = Vec::new();
+    if let Archive::Frames(reader) = &mut archive.archive
+        && let Err(err) = reader.load_resources()
+    {
+        problems.push(err.to_string());
+    }
+
+    let is_frame_archive = matches!(archive.archive, Archive::Frames(_));
+    let mut command_count = 0;
+    for frame in 0..archive.frame_count() {
+        let prefix = if is_frame_archive {
+            format!("frame {frame}: ")
+        } else {
+            String::new()
+        };
+
+        let mut commands = match archive.commands(frame) {
+            Ok(commands) => commands,
+            Err(err) => {
+                problems.push(format!("{prefix}{err}"));
+                continue;
+            }
+        };
+        command_count += commands.len();
+
+        let frame_problems =
+            check_commands(&mut commands, archive.images().len(), archive.fonts().len());
+        let has_problems = !frame_problems.is_empty();
+        problems.extend(frame_problems.into_iter().map(|p| format!("{prefix}{p}")));
+
+        // Decoding the frame also decodes the fonts it uses
+        if !has_problems && let Err(err) = archive.scene(frame) {
+            problems.push(format!("{prefix}{err}"));
+        }
+    }
+
+    if problems.is_empty() {
+        println!(
+            "{}: ok ({} frame(s), {command_count} commands, {} images, {} fonts)",
+            path.display(),
+            archive.frame_count(),
+            archive.images().len(),
+            archive.fonts().len()
+        );
+        Ok(ExitCode::SUCCESS)
+    } else {
+        println!("{}: invalid", path.display());
+        for problem in &problems {
+            println!("  {problem}");
+        }
+        Ok(ExitCode::FAILURE)
+    }
+}
+
+/// Check that layers are balanced and that every referenced resource exists.
+fn check_commands(
+    commands: &mut [SerializableRenderCommand],
+    image_count: usize,
+    font_count: usize,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut depth = 0_usize;
+    for (index, cmd) in commands.iter_mut().enumerate() {
+        match cmd {
+            RenderCommand::PushLayer(_) | RenderCommand::PushClipLayer(_) => depth += 1,
+            RenderCommand::PopLayer => match depth.checked_sub(1) {
+                Some(new_depth) => depth = new_depth,
+                None => problems.push(format!("command {index}: pop_layer without a pushed layer")),
+            },
+            _ => {}
+        }
+
+        visit_resources(cmd, |kind, id| {
+            let (name, count) = match kind {
+                ResourceKind::Image => ("image", image_count),
+                ResourceKind::Font => ("font", font_count),
+            };
+            if id.0 >= count {
+                problems.push(format!(
+                    "command {index}: references {name} {} but the archive has {count} {name}(s)",
+                    id.0
+                ));
+            }
+        });
+    }
+    if depth > 0 {
+        problems.push(format!("{depth} layer(s) are never popped"));
+    }
+    problems
+}
@@ -0,0 +1,218 @@
+//! Tests running the `multirender-archive` binary on archives written by `multirender_serialize`.
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::time::Duration;
+
+use kurbo::{Affine, Rect};
+use multirender::PaintScene;
+use multirender::recording::Scene;
+use multirender_serialize::{FrameArchiveWriter, SceneArchive, SerializeConfig};
+use peniko::{Color, Fill, Mix};
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../multirender_serialize/tests/fixtures")
+        .join(name)
+}
+
+/// A directory for the outputs of a single test
+fn test_dir(name: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("multirender-archive-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn run(args: &[&dyn AsRef<std::ffi::OsStr>]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_multirender-archive"))
+        .args(args.iter().map(|arg| arg.as_ref()))
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn rect_scene(color: Color) -> Scene {
+    let mut scene = Scene::new();
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        color,
+        None,
+        &Rect::new(10.0, 10.0, 30.0, 30.0),
+    );
+    scene
+}
+
+fn write_scene(path: &Path, scene: &Scene) {
+    SceneArchive::from_scene(scene, &SerializeConfig::new())
+        .unwrap()
+        .serialize(File::create(path).unwrap())
+        .unwrap();
+}
+
+fn write_frames(path: &Path, scenes: &[Scene]) {
+    let mut writer = FrameArchiveWriter::new(File::create(path).unwrap(), &SerializeConfig::new());
+    for (index, scene) in scenes.iter().enumerate() {
+        writer
+            .add_frame(scene, Duration::from_millis(index as u64 * 16), 40, 40)
+            .unwrap();
+    }
+    writer.finish().unwrap();
+}
+
+#[test]
+fn info_and_validate_fixture() {
+    let archive = fixture("v1_shapes.multirender.zip");
+
+    let output = run(&[&"info", &archive]);
+    assert!(output.status.success());
+    let info = stdout(&output);
+    assert!(info.contains("box_shadow"), "{info}");
+    assert!(info.contains("Images: 1"), "{info}");
+
+    let output = run(&[&"validate", &archive]);
+    assert!(output.status.success(), "{}", stdout(&output));
+}
+
+#[test]
+fn validate_reports_unbalanced_layers() {
+    let dir = test_dir("validate");
+    let archive = dir.join("unbalanced.multirender.zip");
+    let mut scene = rect_scene(Color::WHITE);
+    scene.push_layer(
+        Mix::Normal,
+        1.0,
+        Affine::IDENTITY,
+        &Rect::new(0.0, 0.0, 10.0, 10.0),
+    );
+    write_scene(&archive, &scene);
+
+    let output = run(&[&"validate", &archive]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).contains("1 layer(s) are never popped"));
+}
+
+#[test]
+fn render_scales_output() {
+    let dir = test_dir("render");
+    let archive = dir.join("scene.multirender.zip");
+    let png = dir.join("scene.png");
+    write_scene(&archive, &rect_scene(Color::WHITE));
+
+    // Scene archives do not record a viewport size
+    let output = run(&[&"render", &archive, &"-o", &png]);
+    assert_eq!(output.status.code(), Some(2));
+
+    let output = run(&[
+        &"render",
+        &archive,
+        &"-o",
+        &png,
+        &"--width",
+        &"40",
+        &"--height",
+        &"20",
+        &"--scale",
+        &"2",
+    ]);
+    assert!(output.status.success());
+    let image = image::open(&png).unwrap().into_rgba8();
+    assert_eq!(image.dimensions(), (80, 40));
+    assert_eq!(image.get_pixel(40, 30).0, [255, 255, 255, 255]);
+    assert_eq!(image.get_pixel(70, 30).0[3], 0);
+
+    for scale in ["0", "-1", "inf", "NaN"] {
+        let output = run(&[
+            &"render",
+            &archive,
+            &"-o",
+            &png,
+            &"--width",
+            &"40",
+            &"--height",
+            &"20",
+            &format!("--scale={scale}"),
+        ]);
+        assert_eq!(output.status.code(), Some(2), "--scale {scale}");
+    }
+}
+
+#[test]
+fn render_unpremultiplies_alpha() {
+    let dir = test_dir("render-alpha");
+    let archive = dir.join("scene.multirender.zip");
+    let png = dir.join("scene.png");
+    write_scene(&archive, &rect_scene(Color::from_rgba8(255, 255, 255, 128)));
+
+    let output = run(&[
+        &"render",
+        &archive,
+        &"-o",
+        &png,
+        &"--width",
+        &"40",
+        &"--height",
+        &"40",
+    ]);
+    assert!(output.status.success());
+    let image = image::open(&png).unwrap().into_rgba8();
+    let [r, g, b, a] = image.get_pixel(20, 20).0;
+    assert!(a.abs_diff(128) <= 1, "alpha {a}");
+    // Premultiplied output would store the color channels at half intensity
+    for channel in [r, g, b] {
+        assert!(channel >= 254, "pixel {:?}", [r, g, b, a]);
+    }
+}
+
+#[test]
+fn diff_compares_frames() {
+    let dir = test_dir("diff");
+    let a = dir.join("a.multirender.zip");
+    let b = dir.join("b.multirender.zip");
+    write_frames(&a, &[rect_scene(Color::WHITE), rect_scene(Color::WHITE)]);
+    write_frames(&b, &[rect_scene(Color::WHITE), rect_scene(Color::BLACK)]);
+
+    let output = run(&[&"diff", &a, &b, &"--frame", &"0"]);
+    assert!(output.status.success(), "{}", stdout(&output));
+
+    let diff_png = dir.join("diff.png");
+    let output = run(&[&"diff", &a, &b, &"--frame", &"1", &"-o", &diff_png]);
+    assert_eq!(output.status.code(), Some(1));
+    let report = stdout(&output);
+    assert!(report.contains("#0: fill differs"), "{report}");
+    assert!(report.contains("400 of 1600 pixels differ"), "{report}");
+    assert_eq!(
+        image::open(&diff_png)
+            .unwrap()
+            .into_rgba8()
+            .get_pixel(20, 20)
+            .0,
+        [255, 0, 0, 255]
+    );
+}
+
+#[test]
+fn extract_writes_resources() {
+    let dir = test_dir("extract");
+    let output = run(&[
+        &"extract",
+        &fixture("v1_glyphs_woff2.multirender.zip"),
+        &dir,
+        &"--decompress-fonts",
+    ]);
+    assert!(output.status.success());
+
+    let fonts: Vec<_> = std::fs::read_dir(dir.join("fonts"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    assert_eq!(fonts.len(), 1);
+    assert_eq!(fonts[0].extension().unwrap(), "ttf");
+    assert!(std::fs::read(&fonts[0]).unwrap().starts_with(&[0, 1, 0, 0]));
+}
@@ -0,0 +1,22 @@
+//! Reads arbitrary bytes as a scene archive, both in full and lazily.
+
+#![no_main]
+
+use std::io::Cursor;
+
+use libfuzzer_sys::fuzz_target;
+use multirender_serialize::{FrameArchiveReader, SceneArchive, SceneArchiveReader};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(archive) = SceneArchive::deserialize(Cursor::new(data)) {
+        let _ = archive.to_scene();
+    }
+
+    if let Ok(mut reader) = SceneArchiveReader::new(Cursor::new(data)) {
+        let _ = reader.to_scene();
+    }
+
+    if let Ok(mut reader) = FrameArchiveReader::new(Cursor::new(data)) {
+        for _ in reader.frames() {}
+    }
+});
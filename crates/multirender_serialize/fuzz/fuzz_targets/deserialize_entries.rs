@@ -0,0 +1,55 @@
+//! Reads scene archives built from arbitrary manifests, draw commands and resource files.
+//!
+//! Packing the input into a valid zip archive lets the fuzzer reach the manifest, command and resource decoding
+//! without having to produce a zip container first.
+
+#![no_main]
+
+use std::io::{Cursor, Write};
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use multirender_serialize::{CommandEncoding, SceneArchive, SceneArchiveReader};
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+#[derive(Arbitrary, Debug)]
+struct Input<'a> {
+    manifest: &'a [u8],
+    commands: &'a [u8],
+    binary_commands: bool,
+    files: Vec<(&'a str, &'a [u8])>,
+}
+
+fn build_archive(input: &Input) -> zip::result::ZipResult<Vec<u8>> {
+    let encoding = if input.binary_commands {
+        CommandEncoding::Binary
+    } else {
+        CommandEncoding::Json
+    };
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default();
+    zip.start_file("resources.json", options)?;
+    zip.write_all(input.manifest)?;
+    zip.start_file(encoding.path(), options)?;
+    zip.write_all(input.commands)?;
+    for (path, contents) in &input.files {
+        zip.start_file(*path, options)?;
+        zip.write_all(contents)?;
+    }
+    Ok(zip.finish()?.into_inner())
+}
+
+fuzz_target!(|input: Input| {
+    let Ok(data) = build_archive(&input) else {
+        return;
+    };
+
+    if let Ok(archive) = SceneArchive::deserialize(Cursor::new(&data)) {
+        let _ = archive.to_scene();
+    }
+
+    if let Ok(mut reader) = SceneArchiveReader::new(Cursor::new(&data)) {
+        let _ = reader.to_scene();
+    }
+});
@@ -0,0 +1,197 @@
+//! A window renderer wrapper that captures rendered frames to archives.
+
+use std::collections::VecDeque;
+use std::io::{Seek, Write};
+use std::sync::Arc;
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+use peniko::kurbo::Affine;
+
+use multirender::recording::Scene;
+use multirender::{FrameStats, PaintScene, WindowHandle, WindowRenderer};
+
+use crate::{ArchiveError, Frame, FrameArchiveWriter, SceneArchive, SerializeConfig};
+
+/// Wraps a [`WindowRenderer`] to capture frames to archives for reproducing bugs.
+///
+/// Frames are drawn into a [`Scene`] which is then replayed through the inner renderer, so any backend can be
+/// captured without changing the draw function. When a capture is requested with
+/// [`capture_next_frame`](Self::capture_next_frame) the next rendered frame is kept and can be taken as a
+/// [`SceneArchive`] with [`take_capture`](Self::take_capture). With [`with_history`](Self::with_history) the last
+/// frames are kept in a ring buffer, which [`write_history`](Self::write_history) writes to a frame archive to capture
+/// what just happened.
+///
+/// Captured scenes are in physical pixels: the scale factor is applied to them. Frames are timestamped relative to the
+/// creation of the renderer, except on wasm where timing is unavailable and every timestamp is zero.
+pub struct CapturingWindowRenderer<W: WindowRenderer> {
+    inner: W,
+    config: SerializeConfig,
+    scale_factor: f64,
+    width: u32,
+    height: u32,
+    #[cfg(not(target_arch = "wasm32"))]
+    start: Instant,
+    capture_requested: bool,
+    capture: Option<Frame>,
+    /// The most recently rendered frames, oldest first
+    history: VecDeque<Frame>,
+    history_len: usize,
+}
+
+impl<W: WindowRenderer> CapturingWindowRenderer<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            config: SerializeConfig::default(),
+            scale_factor: 1.0,
+            width: 0,
+            height: 0,
+            #[cfg(not(target_arch = "wasm32"))]
+            start: Instant::now(),
+            capture_requested: false,
+            capture: None,
+            history: VecDeque::new(),
+            history_len: 0,
+        }
+    }
+
+    /// Set the configuration used to write captured frames to archives.
+    pub fn with_config(mut self, config: SerializeConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Keep the last `frames` rendered frames. Defaults to 0, which only keeps requested captures.
+    pub fn with_history(mut self, frames: usize) -> Self {
+        self.history_len = frames;
+        self.history.truncate(frames);
+        self
+    }
+
+    pub fn inner(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn inner_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// The time since the renderer was created
+    #[cfg(not(target_arch = "wasm32"))]
+    fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn elapsed(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    /// Capture the next frame that is rendered.
+    pub fn capture_next_frame(&mut self) {
+        self.capture_requested = true;
+    }
+
+    /// Take the most recently captured frame as an archive. Returns `None` if no frame has been captured since the
+    /// last call.
+    pub fn take_capture(&mut self) -> Option<Result<SceneArchive, ArchiveError>> {
+        let frame = self.capture.take()?;
+        Some(SceneArchive::from_scene(&frame.scene, &self.config))
+    }
+
+    /// The frames in the history, oldest first
+    pub fn history(&self) -> impl ExactSizeIterator<Item = &Frame> {
+        self.history.iter()
+    }
+
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+    }
+
+    /// Write the frames in the history to a frame archive, oldest first.
+    pub fn write_history<Wr: Write + Seek>(&self, writer: Wr) -> Result<Wr, ArchiveError> {
+        let mut archive = FrameArchiveWriter::new(writer, &self.config);
+        for frame in &self.history {
+            archive.add_frame(&frame.scene, frame.timestamp, frame.width, frame.height)?;
+        }
+        archive.finish()
+    }
+}
+
+impl<W: WindowRenderer> WindowRenderer for CapturingWindowRenderer<W> {
+    type ScenePainter<'a>
+        = Scene
+    where
+        Self: 'a;
+
+    fn resume(
+        &mut self,
+        window: Arc<dyn WindowHandle>,
+        width: u32,
+        height: u32,
+    ) -> Result<(), String> {
+        self.width = width;
+        self.height = height;
+        self.inner.resume(window, width, height)
+    }
+
+    fn suspend(&mut self) {
+        self.inner.suspend();
+    }
+
+    fn is_active(&self) -> bool {
+        self.inner.is_active()
+    }
+
+    fn set_size(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        self.inner.set_size(width, height);
+    }
+
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+        self.inner.set_scale_factor(scale_factor);
+    }
+
+    fn render<F: FnOnce(&mut Scene)>(&mut self, draw_fn: F) -> Option<FrameStats> {
+        let mut scene = Scene::new();
+        draw_fn(&mut scene);
+
+        let keep = self.inner.is_active() && (self.capture_requested || self.history_len > 0);
+        let frame = keep.then(|| {
+            let mut captured = Scene::with_tolerance(scene.tolerance);
+            captured.append_scene(scene.clone(), Affine::scale(self.scale_factor));
+            Frame {
+                timestamp: self.elapsed(),
+                width: self.width,
+                height: self.height,
+                scene: captured,
+            }
+        });
+
+        let stats = self
+            .inner
+            .render(|painter| painter.append_scene(scene, Affine::IDENTITY));
+
+        if let Some(frame) = frame {
+            if self.capture_requested {
+                self.capture_requested = false;
+                self.capture = Some(frame.clone());
+            }
+            if self.history_len > 0 {
+                if self.history.len() == self.history_len {
+                    self.history.pop_front();
+                }
+                self.history.push_back(frame);
+            }
+        }
+        stats
+    }
+}
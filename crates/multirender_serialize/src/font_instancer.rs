@@ -0,0 +1,200 @@
+//! Instancing of variable TrueType fonts: producing a static font for a single location in the design space.
+
+use multirender::NormalizedCoord;
+use peniko::kurbo::BezPath;
+use read_fonts::types::{F2Dot14, GlyphId, Tag};
+use read_fonts::{FontRef, TableProvider};
+use skrifa::MetadataProvider;
+use skrifa::instance::{LocationRef, Size};
+use skrifa::outline::{DrawSettings, OutlinePen};
+use write_fonts::FontBuilder;
+use write_fonts::from_obj::ToOwnedTable;
+use write_fonts::tables::glyf::{Bbox, GlyfLocaBuilder, Glyph, SimpleGlyph};
+use write_fonts::tables::head::Head;
+use write_fonts::tables::hhea::Hhea;
+use write_fonts::tables::hmtx::{Hmtx, LongMetric};
+use write_fonts::tables::maxp::Maxp;
+
+use crate::ArchiveError;
+
+/// Tables that are dropped from instanced fonts: variation tables, which no longer apply, and hinting tables, which
+/// are not valid for the instanced outlines.
+const DROPPED_TABLES: &[&[u8; 4]] = &[
+    b"fvar", b"gvar", b"avar", b"cvar", b"HVAR", b"VVAR", b"MVAR", b"STAT", b"cvt ", b"fpgm",
+    b"prep", b"hdmx", b"LTSH", b"VDMX",
+];
+
+/// Tables that are rebuilt for instanced fonts.
+const REBUILT_TABLES: &[&[u8; 4]] = &[b"glyf", b"loca", b"head", b"hhea", b"hmtx", b"maxp"];
+
+/// Whether `font` is a variable font that can be instanced. Only fonts with TrueType (`glyf`) outlines are supported.
+pub(crate) fn is_instanceable(font: &FontRef) -> bool {
+    font.table_data(Tag::new(b"fvar")).is_some() && font.table_data(Tag::new(b"glyf")).is_some()
+}
+
+/// Create a static instance of the variable font `data` at `coords`.
+///
+/// Outlines are drawn at the location and stored as simple glyphs (composite glyphs are flattened), and advance widths
+/// are taken from the location. Hinting instructions are dropped.
+pub(crate) fn instance_font(
+    data: &[u8],
+    coords: &[NormalizedCoord],
+) -> Result<Vec<u8>, ArchiveError> {
+    let font = FontRef::new(data).map_err(font_error)?;
+    let coords: Vec<F2Dot14> = coords.iter().copied().map(F2Dot14::from_bits).collect();
+    let location = LocationRef::new(&coords);
+
+    let outlines = font.outline_glyphs();
+    let glyph_metrics = font.glyph_metrics(Size::unscaled(), location);
+    let num_glyphs = font.maxp().map_err(font_error)?.num_glyphs();
+
+    let mut glyf_loca = GlyfLocaBuilder::new();
+    let mut metrics = Vec::with_capacity(num_glyphs as usize);
+    let mut font_bbox: Option<Bbox> = None;
+    let mut max_points = 0;
+    let mut max_contours = 0;
+    for gid in 0..num_glyphs {
+        let gid = GlyphId::from(gid);
+
+        let mut pen = BezPathPen(BezPath::new());
+        if let Some(outline) = outlines.get(gid) {
+            outline
+                .draw(DrawSettings::unhinted(Size::unscaled(), location), &mut pen)
+                .map_err(|e| {
+                    ArchiveError::FontProcessing(format!("Failed to draw glyph {gid}: {e}"))
+                })?;
+        }
+        let glyph = if pen.0.elements().is_empty() {
+            Glyph::Empty
+        } else {
+            let glyph = SimpleGlyph::from_bezpath(&pen.0).map_err(|e| {
+                ArchiveError::FontProcessing(format!("Failed to convert glyph {gid}: {e:?}"))
+            })?;
+            max_points = max_points.max(glyph.contours.iter().map(|c| c.len()).sum::<usize>());
+            max_contours = max_contours.max(glyph.contours.len());
+            Glyph::Simple(glyph)
+        };
+
+        let advance = glyph_metrics.advance_width(gid).unwrap_or_default().round();
+        let bbox = glyph.bbox();
+        if let Some(bbox) = bbox {
+            font_bbox = Some(font_bbox.map_or(bbox, |font_bbox| font_bbox.union(bbox)));
+        }
+        metrics.push((advance.clamp(0.0, u16::MAX as f32) as u16, bbox));
+
+        glyf_loca.add_glyph(&glyph).map_err(|e| {
+            ArchiveError::FontProcessing(format!("Failed to write glyph {gid}: {e}"))
+        })?;
+    }
+    let (glyf, loca, loca_format) = glyf_loca.build();
+
+    let font_bbox = font_bbox.unwrap_or_default();
+    let mut head: Head = font.head().map_err(font_error)?.to_owned_table();
+    head.index_to_loc_format = loca_format as i16;
+    head.x_min = font_bbox.x_min;
+    head.y_min = font_bbox.y_min;
+    head.x_max = font_bbox.x_max;
+    head.y_max = font_bbox.y_max;
+
+    let mut hhea: Hhea = font.hhea().map_err(font_error)?.to_owned_table();
+    hhea.number_of_h_metrics = num_glyphs;
+    hhea.advance_width_max = metrics
+        .iter()
+        .map(|(advance, _)| *advance)
+        .max()
+        .unwrap_or(0)
+        .into();
+    let inked = metrics
+        .iter()
+        .filter_map(|(advance, bbox)| Some((*advance, (*bbox)?)));
+    hhea.min_left_side_bearing = inked
+        .clone()
+        .map(|(_, bbox)| bbox.x_min)
+        .min()
+        .unwrap_or(0)
+        .into();
+    hhea.min_right_side_bearing = inked
+        .clone()
+        .map(|(advance, bbox)| (advance as i32 - bbox.x_max as i32) as i16)
+        .min()
+        .unwrap_or(0)
+        .into();
+    hhea.x_max_extent = inked.map(|(_, bbox)| bbox.x_max).max().unwrap_or(0).into();
+
+    let hmtx = Hmtx::new(
+        metrics
+            .iter()
+            .map(|(advance, bbox)| LongMetric::new(*advance, bbox.map_or(0, |bbox| bbox.x_min)))
+            .collect(),
+        Vec::new(),
+    );
+
+    let mut maxp: Maxp = font.maxp().map_err(font_error)?.to_owned_table();
+    if maxp.max_points.is_some() {
+        maxp.max_points = Some(max_points.min(u16::MAX as usize) as u16);
+        maxp.max_contours = Some(max_contours.min(u16::MAX as usize) as u16);
+        maxp.max_composite_points = Some(0);
+        maxp.max_composite_contours = Some(0);
+        maxp.max_component_elements = Some(0);
+        maxp.max_component_depth = Some(0);
+        maxp.max_size_of_instructions = Some(0);
+    }
+
+    let mut builder = FontBuilder::new();
+    builder
+        .add_table(&glyf)
+        .and_then(|b| b.add_table(&loca))
+        .and_then(|b| b.add_table(&head))
+        .and_then(|b| b.add_table(&hhea))
+        .and_then(|b| b.add_table(&hmtx))
+        .and_then(|b| b.add_table(&maxp))
+        .map_err(|e| ArchiveError::FontProcessing(format!("Failed to write font tables: {e}")))?;
+    for record in font.table_directory().table_records() {
+        let tag = record.tag();
+        if DROPPED_TABLES
+            .iter()
+            .chain(REBUILT_TABLES)
+            .any(|t| tag == Tag::new(t))
+        {
+            continue;
+        }
+        if let Some(data) = font.table_data(tag) {
+            builder.add_raw(tag, data.as_bytes());
+        }
+    }
+    Ok(builder.build())
+}
+
+fn font_error(e: read_fonts::ReadError) -> ArchiveError {
+    ArchiveError::FontProcessing(format!("Failed to parse font: {e}"))
+}
+
+/// Collects an outline into a [`BezPath`].
+struct BezPathPen(BezPath);
+
+impl OutlinePen for BezPathPen {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.0.move_to((x as f64, y as f64));
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.0.line_to((x as f64, y as f64));
+    }
+
+    fn quad_to(&mut self, cx0: f32, cy0: f32, x: f32, y: f32) {
+        self.0
+            .quad_to((cx0 as f64, cy0 as f64), (x as f64, y as f64));
+    }
+
+    fn curve_to(&mut self, cx0: f32, cy0: f32, cx1: f32, cy1: f32, x: f32, y: f32) {
+        self.0.curve_to(
+            (cx0 as f64, cy0 as f64),
+            (cx1 as f64, cy1 as f64),
+            (x as f64, y as f64),
+        );
+    }
+
+    fn close(&mut self) {
+        self.0.close_path();
+    }
+}
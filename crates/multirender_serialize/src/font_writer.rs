@@ -2,12 +2,14 @@
 
 use std::collections::{HashMap, HashSet};
 
+use multirender::NormalizedCoord;
 use peniko::FontData;
 use read_fonts::FontRef;
 use read_fonts::collections::int_set::IntSet;
 use read_fonts::types::GlyphId;
 use skera::{Plan, SubsetFlags};
 
+use crate::font_instancer::{instance_font, is_instanceable};
 use crate::{ArchiveError, ResourceId, SerializeConfig, sha256_hex};
 
 /// A font that has been processed (optionally subsetted and/or WOFF2-encoded) and is
@@ -23,6 +25,16 @@ pub(crate) struct ProcessedFont {
     pub path: String,
 }
 
+/// Identifies the content of a processed font: the font it was created from, and the glyphs it was subsetted to. Fonts
+/// with the same key are processed into the same data.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct FontKey {
+    /// `(Blob ID, face index, instance location)`, as used to deduplicate fonts within a scene
+    font: (u64, u32, Option<Vec<NormalizedCoord>>),
+    /// The sorted glyph IDs the font is subsetted to, empty when subsetting is disabled
+    glyph_ids: Vec<u32>,
+}
+
 /// Collects, deduplicates, and processes fonts for writing into a scene archive.
 ///
 /// When subsetting is enabled, each `(blob, face index)` pair is treated as a distinct
@@ -30,14 +42,21 @@ pub(crate) struct ProcessedFont {
 ///
 /// When disabled, fonts are deduplicated by blob alone. Multiple faces sharing the same TTC
 /// are stored together.
+///
+/// When variable font instancing is enabled as well, each location a variable font is used at
+/// is a distinct resource, which is instanced into a static font.
 pub(crate) struct FontWriter {
     config: SerializeConfig,
-    /// Map `(Blob ID, face index)` to [`ResourceId`].
+    /// Map `(Blob ID, face index, instance location)` to [`ResourceId`].
     /// When subsetting is disabled, the face in the `(Blob ID, face index)` tuple is always 0.
     /// This is because multiple faces sharing the same TTC should be keyed together.
-    id_map: HashMap<(u64, u32), ResourceId>,
+    id_map: HashMap<(u64, u32, Option<Vec<NormalizedCoord>>), ResourceId>,
     fonts: Vec<FontData>,
     glyph_ids: Vec<HashSet<u32>>,
+    /// The location each font is instanced at, or `None` if the font is stored with its variations.
+    instances: Vec<Option<Vec<NormalizedCoord>>>,
+    /// Whether each `(Blob ID, face index)` is a variable font that can be instanced.
+    instanceable: HashMap<(u64, u32), bool>,
 }
 
 impl FontWriter {
@@ -47,17 +66,24 @@ impl FontWriter {
             id_map: HashMap::new(),
             fonts: Vec::new(),
             glyph_ids: Vec::new(),
+            instances: Vec::new(),
+            instanceable: HashMap::new(),
         }
     }
 
-    /// Register a font and return its [`ResourceId`].
-    pub fn register(&mut self, font: &FontData) -> ResourceId {
+    /// Register a font used at `normalized_coords` and return its [`ResourceId`].
+    pub fn register(
+        &mut self,
+        font: &FontData,
+        normalized_coords: &[NormalizedCoord],
+    ) -> ResourceId {
         let key = if self.config.subset_fonts {
-            (font.data.id(), font.index)
+            let instance = self.instance_location(font, normalized_coords);
+            (font.data.id(), font.index, instance)
         } else {
             // When subsetting is disabled, the face index is always 0 so that
             // multiple faces sharing the same TTC are keyed together.
-            (font.data.id(), 0)
+            (font.data.id(), 0, None)
         };
 
         if let Some(&id) = self.id_map.get(&key) {
@@ -65,12 +91,47 @@ impl FontWriter {
         }
 
         let id = ResourceId(self.fonts.len());
+        self.instances.push(key.2.clone());
         self.id_map.insert(key, id);
         self.fonts.push(font.clone());
         self.glyph_ids.push(HashSet::new());
         id
     }
 
+    /// The location to instance `font` at, or `None` if it is not instanced.
+    fn instance_location(
+        &mut self,
+        font: &FontData,
+        normalized_coords: &[NormalizedCoord],
+    ) -> Option<Vec<NormalizedCoord>> {
+        if !self.config.instance_variable_fonts {
+            return None;
+        }
+        let instanceable = *self
+            .instanceable
+            .entry((font.data.id(), font.index))
+            .or_insert_with(|| {
+                FontRef::from_index(font.data.data(), font.index)
+                    .is_ok_and(|font_ref| is_instanceable(&font_ref))
+            });
+        if !instanceable {
+            return None;
+        }
+
+        // Missing coordinates are at the default location, so trailing zeros don't change the location
+        let len = normalized_coords
+            .iter()
+            .rposition(|coord| *coord != 0)
+            .map_or(0, |index| index + 1);
+        Some(normalized_coords[..len].to_vec())
+    }
+
+    /// Whether a font resource is a static instance of a variable font. Glyph runs using it must not
+    /// specify normalized coordinates.
+    pub fn is_instanced(&self, id: ResourceId) -> bool {
+        self.instances[id.0].is_some()
+    }
+
     /// Record glyph IDs used for a font resource (used for subsetting).
     pub fn record_glyphs(&mut self, id: ResourceId, glyphs: &[multirender::Glyph]) {
         if self.config.subset_fonts {
@@ -93,67 +154,95 @@ impl FontWriter {
         }
     }
 
+    /// The number of registered font resources.
+    pub fn len(&self) -> usize {
+        self.fonts.len()
+    }
+
+    /// The key identifying the processed data of a font resource, which allows fonts that have already been
+    /// processed to be reused.
+    pub fn key(&self, id: ResourceId) -> FontKey {
+        let font = &self.fonts[id.0];
+        let face_index = if self.config.subset_fonts {
+            font.index
+        } else {
+            0
+        };
+        let mut glyph_ids: Vec<u32> = self.glyph_ids[id.0].iter().copied().collect();
+        glyph_ids.sort_unstable();
+        FontKey {
+            font: (font.data.id(), face_index, self.instances[id.0].clone()),
+            glyph_ids,
+        }
+    }
+
     /// Consume the writer, returning an iterator of processed fonts ready for the archive.
     pub fn into_processed(self) -> impl Iterator<Item = Result<ProcessedFont, ArchiveError>> {
-        self.fonts.into_iter().enumerate().map(move |(idx, font)| {
-            let raw_data = if self.config.subset_fonts {
-                let glyph_ids = &self.glyph_ids[idx];
-
-                let font_ref = FontRef::from_index(font.data.data(), font.index).map_err(|e| {
-                    ArchiveError::FontProcessing(format!("Failed to parse font: {e}"))
-                })?;
-
-                let mut input_gids: IntSet<GlyphId> = IntSet::empty();
-                for &gid in glyph_ids {
-                    input_gids.insert(GlyphId::new(gid));
-                }
-
-                let plan = Plan::new(
-                    &input_gids,
-                    &IntSet::empty(),
-                    &font_ref,
-                    // Keep original glyph IDs so we don't need to remap them in draw commands.
-                    SubsetFlags::SUBSET_FLAGS_RETAIN_GIDS,
-                    &IntSet::empty(),
-                    &IntSet::empty(),
-                    &IntSet::empty(),
-                    &IntSet::empty(),
-                    &IntSet::empty(),
-                );
-
-                skera::subset_font(&font_ref, &plan).map_err(|e| {
-                    ArchiveError::FontProcessing(format!("Font subsetting failed: {e}"))
-                })?
-            } else {
-                font.data.data().to_vec()
-            };
-
-            let raw_size = raw_data.len();
-
-            // Conditionally WOFF2 compress.
-            let stored_data = if self.config.woff2_fonts {
-                ttf2woff2::encode_no_transform(&raw_data, ttf2woff2::BrotliQuality::default())
-                    .map_err(|e| {
-                        ArchiveError::FontProcessing(format!("WOFF2 encoding failed: {e}"))
-                    })?
-            } else {
-                raw_data
-            };
-
-            let hash = sha256_hex(&stored_data);
-            let extension = if self.config.woff2_fonts {
-                "woff2"
-            } else {
-                "ttf"
-            };
-            let path = format!("fonts/{}.{}", hash, extension);
-
-            Ok(ProcessedFont {
-                raw_size,
-                stored_data,
-                hash,
-                path,
-            })
+        (0..self.fonts.len()).map(move |idx| self.process(ResourceId(idx)))
+    }
+
+    /// Subset, instance and encode a font resource according to the config.
+    pub fn process(&self, id: ResourceId) -> Result<ProcessedFont, ArchiveError> {
+        let idx = id.0;
+        let font = &self.fonts[idx];
+        let raw_data = if self.config.subset_fonts {
+            let glyph_ids = &self.glyph_ids[idx];
+
+            let font_ref = FontRef::from_index(font.data.data(), font.index)
+                .map_err(|e| ArchiveError::FontProcessing(format!("Failed to parse font: {e}")))?;
+
+            let mut input_gids: IntSet<GlyphId> = IntSet::empty();
+            for &gid in glyph_ids {
+                input_gids.insert(GlyphId::new(gid));
+            }
+
+            let plan = Plan::new(
+                &input_gids,
+                &IntSet::empty(),
+                &font_ref,
+                // Keep original glyph IDs so we don't need to remap them in draw commands.
+                SubsetFlags::SUBSET_FLAGS_RETAIN_GIDS,
+                &IntSet::empty(),
+                &IntSet::empty(),
+                &IntSet::empty(),
+                &IntSet::empty(),
+                &IntSet::empty(),
+            );
+
+            skera::subset_font(&font_ref, &plan)
+                .map_err(|e| ArchiveError::FontProcessing(format!("Font subsetting failed: {e}")))?
+        } else {
+            font.data.data().to_vec()
+        };
+
+        let raw_data = match &self.instances[idx] {
+            Some(coords) => instance_font(&raw_data, coords)?,
+            None => raw_data,
+        };
+
+        let raw_size = raw_data.len();
+
+        // Conditionally WOFF2 compress.
+        let stored_data = if self.config.woff2_fonts {
+            ttf2woff2::encode_no_transform(&raw_data, ttf2woff2::BrotliQuality::default())
+                .map_err(|e| ArchiveError::FontProcessing(format!("WOFF2 encoding failed: {e}")))?
+        } else {
+            raw_data
+        };
+
+        let hash = sha256_hex(&stored_data);
+        let extension = if self.config.woff2_fonts {
+            "woff2"
+        } else {
+            "ttf"
+        };
+        let path = format!("fonts/{}.{}", hash, extension);
+
+        Ok(ProcessedFont {
+            raw_size,
+            stored_data,
+            hash,
+            path,
         })
     }
 }
@@ -0,0 +1,459 @@
+//! Multi-frame archives storing a sequence of scenes that share resources.
+//!
+//! # Archive Format
+//!
+//! A frame archive is a zip archive containing:
+//!
+//! - `frames.json` - A [`FrameArchiveManifest`] listing every frame and the resources shared between them
+//! - `frames/<index>.{json,bin}` - The draw commands of each frame, encoded as described by [`CommandEncoding`]
+//! - `images/<hash>-<width>x<height>.{png,jpg,webp}` and `fonts/<sha256_hash>.{woff2,ttf}` - Resources, stored once
+//!   for all frames
+
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Seek, Write};
+use std::time::Duration;
+
+use peniko::ImageData;
+use serde::{Deserialize, Serialize};
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+use multirender::recording::Scene;
+
+use crate::font_writer::{FontKey, FontWriter};
+use crate::{
+    ArchiveError, ArchiveLimits, CommandEncoding, FontMetadata, ImageMetadata, ResourceCollector,
+    ResourceId, ResourceKind, ResourceReconstructor, SerializableRenderCommand, SerializeConfig,
+    file_options, font_metadata, image_key, image_metadata, process_image, read_commands,
+    scale_image_brushes, visit_resources, write_commands,
+};
+
+const MANIFEST_PATH: &str = "frames.json";
+
+/// The manifest of a multi-frame archive.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FrameArchiveManifest {
+    /// Version of the archive format
+    pub version: u32,
+    /// The encoding of the draw commands of every frame
+    pub command_encoding: CommandEncoding,
+    pub frames: Vec<FrameMetadata>,
+    /// Images shared by all frames
+    pub images: Vec<ImageMetadata>,
+    /// Fonts shared by all frames
+    pub fonts: Vec<FontMetadata>,
+}
+
+impl FrameArchiveManifest {
+    /// Current frame archive format version. Bump this when the format changes.
+    pub const CURRENT_VERSION: u32 = 1;
+}
+
+/// Metadata for a single frame.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FrameMetadata {
+    /// The time of the frame relative to the start of the recording
+    pub timestamp: Duration,
+    /// Width of the viewport the frame was rendered to, in physical pixels
+    pub width: u32,
+    /// Height of the viewport the frame was rendered to, in physical pixels
+    pub height: u32,
+    /// Scene tolerance (used for path flattening)
+    pub tolerance: f64,
+    /// Path to the frame's draw commands within the archive
+    pub path: String,
+}
+
+/// A frame read from a multi-frame archive.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    pub timestamp: Duration,
+    pub width: u32,
+    pub height: u32,
+    pub scene: Scene,
+}
+
+/// Writes a multi-frame archive one frame at a time.
+///
+/// Frames are written to the underlying zip file as they are added, so that recording does not need to keep every
+/// frame in memory. Images and fonts are deduplicated across all frames by their SHA-256 hash and stored once.
+/// Resources are only processed (encoded, subsetted and hashed) the first time they are used: later frames that use
+/// the same [`Blob`](peniko::Blob) reuse the result.
+///
+/// When font subsetting is enabled each frame's fonts are subsetted separately, so fonts are only shared between
+/// frames that use the same glyphs.
+///
+/// The archive is incomplete until [`finish`](Self::finish) is called.
+pub struct FrameArchiveWriter<W: Write + Seek> {
+    zip: ZipWriter<W>,
+    config: SerializeConfig,
+    manifest: FrameArchiveManifest,
+    /// Maps image keys (content hashes and dimensions) to the images (which may differ in format) with that content
+    image_ids: HashMap<String, Vec<ResourceId>>,
+    font_ids: HashMap<String, ResourceId>,
+    /// Images that have been processed, keyed by Blob ID, layout and whether they may be downsampled, with their
+    /// scale after downsampling
+    processed_images: HashMap<ImageDataKey, (ResourceId, (f64, f64))>,
+    /// Fonts that have been processed
+    processed_fonts: HashMap<FontKey, ResourceId>,
+    /// Paths of resource files that have been written
+    written: HashSet<String>,
+    options: SimpleFileOptions,
+}
+
+/// Identifies the pixels of an [`ImageData`]: its blob along with how the blob is interpreted. The format and alpha
+/// type are stored as their discriminants, as peniko's types don't implement `Hash`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct ImageDataKey {
+    blob_id: u64,
+    width: u32,
+    height: u32,
+    format: u8,
+    alpha_type: u8,
+    /// Whether the image may be downsampled
+    downsample: bool,
+}
+
+impl ImageDataKey {
+    fn new(image: &ImageData, downsample: bool) -> Self {
+        Self {
+            blob_id: image.data.id(),
+            width: image.width,
+            height: image.height,
+            format: image.format as u8,
+            alpha_type: image.alpha_type as u8,
+            downsample,
+        }
+    }
+}
+
+impl<W: Write + Seek> FrameArchiveWriter<W> {
+    pub fn new(writer: W, config: &SerializeConfig) -> Self {
+        Self::with_file_options(writer, config, file_options(false))
+    }
+
+    /// Create a writer that produces the same bytes every time the same frames are added.
+    ///
+    /// Entries are written in the order frames and resources are added, with a fixed modification time and
+    /// compression level, as with [`SceneArchive::serialize_deterministic`].
+    pub fn new_deterministic(writer: W, config: &SerializeConfig) -> Self {
+        Self::with_file_options(writer, config, file_options(true))
+    }
+
+    fn with_file_options(writer: W, config: &SerializeConfig, options: SimpleFileOptions) -> Self {
+        Self {
+            zip: ZipWriter::new(writer),
+            config: config.clone(),
+            manifest: FrameArchiveManifest {
+                version: FrameArchiveManifest::CURRENT_VERSION,
+                command_encoding: config.command_encoding,
+                frames: Vec::new(),
+                images: Vec::new(),
+                fonts: Vec::new(),
+            },
+            image_ids: HashMap::new(),
+            font_ids: HashMap::new(),
+            processed_images: HashMap::new(),
+            processed_fonts: HashMap::new(),
+            written: HashSet::new(),
+            options,
+        }
+    }
+
+    /// The number of frames written so far
+    pub fn len(&self) -> usize {
+        self.manifest.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.manifest.frames.is_empty()
+    }
+
+    /// Append a frame to the archive.
+    ///
+    /// `timestamp` is the time of the frame relative to the start of the recording, and `width` and `height` are the
+    /// size of the viewport that the frame was rendered to.
+    pub fn add_frame(
+        &mut self,
+        scene: &Scene,
+        timestamp: Duration,
+        width: u32,
+        height: u32,
+    ) -> Result<(), ArchiveError> {
+        let mut collector = ResourceCollector::new(self.config.clone());
+        let mut commands: Vec<_> = scene
+            .commands
+            .iter()
+            .map(|cmd| collector.convert_command(cmd))
+            .collect();
+
+        let mut image_map = Vec::with_capacity(collector.images.len());
+        let mut image_scales = Vec::with_capacity(collector.images.len());
+        for (idx, image) in collector.images.iter().enumerate() {
+            let downsample = !collector.glyph_run_images.contains(&ResourceId(idx));
+            let (id, scale) = self.add_image(image, downsample)?;
+            image_map.push(id);
+            image_scales.push(scale);
+        }
+        scale_image_brushes(&mut commands, &image_scales);
+
+        let font_map = (0..collector.fonts.len())
+            .map(|idx| self.add_font(&collector.fonts, ResourceId(idx)))
+            .collect::<Result<Vec<_>, ArchiveError>>()?;
+
+        for cmd in &mut commands {
+            visit_resources(cmd, |kind, id| {
+                *id = match kind {
+                    ResourceKind::Image => image_map[id.0],
+                    ResourceKind::Font => font_map[id.0],
+                };
+            });
+        }
+
+        let encoding = self.manifest.command_encoding;
+        let extension = match encoding {
+            CommandEncoding::Json => "json",
+            CommandEncoding::Binary => "bin",
+        };
+        let path = format!("frames/{:05}.{extension}", self.manifest.frames.len());
+        write_commands(&mut self.zip, &path, encoding, &commands, self.options)?;
+
+        self.manifest.frames.push(FrameMetadata {
+            timestamp,
+            width,
+            height,
+            tolerance: scene.tolerance,
+            path,
+        });
+        Ok(())
+    }
+
+    /// Process an image unless it has already been processed, returning its ID and its scale after downsampling
+    fn add_image(
+        &mut self,
+        image: &ImageData,
+        downsample: bool,
+    ) -> Result<(ResourceId, (f64, f64)), ArchiveError> {
+        let data_key = ImageDataKey::new(image, downsample);
+        if let Some(&processed) = self.processed_images.get(&data_key) {
+            return Ok(processed);
+        }
+
+        let processed = process_image(image, &self.config, downsample)?;
+        let meta = image_metadata(ResourceId(0), image, &processed);
+        let id = self.add_image_file(&processed.stored_data, meta)?;
+        self.processed_images
+            .insert(data_key, (id, processed.scale));
+        Ok((id, processed.scale))
+    }
+
+    /// Add a processed image unless an image with the same content has already been added
+    fn add_image_file(
+        &mut self,
+        data: &[u8],
+        mut meta: ImageMetadata,
+    ) -> Result<ResourceId, ArchiveError> {
+        let key = image_key(&meta.entry.sha256_hash, meta.width, meta.height);
+        let same_content = self.image_ids.entry(key).or_default();
+        if let Some(&id) = same_content.iter().find(|id| {
+            let existing = &self.manifest.images[id.0];
+            existing.format == meta.format && existing.alpha_type == meta.alpha_type
+        }) {
+            return Ok(id);
+        }
+
+        let id = ResourceId(self.manifest.images.len());
+        same_content.push(id);
+        self.write_file(&meta.entry.path, data)?;
+
+        meta.entry.id = id;
+        self.manifest.images.push(meta);
+        Ok(id)
+    }
+
+    /// Process a font registered with `fonts` unless it has already been processed, returning its ID
+    fn add_font(
+        &mut self,
+        fonts: &FontWriter,
+        font: ResourceId,
+    ) -> Result<ResourceId, ArchiveError> {
+        let font_key = fonts.key(font);
+        if let Some(&id) = self.processed_fonts.get(&font_key) {
+            return Ok(id);
+        }
+
+        let processed = fonts.process(font)?;
+        let id = match self.font_ids.get(&processed.hash) {
+            Some(&id) => id,
+            None => {
+                let id = ResourceId(self.manifest.fonts.len());
+                self.font_ids.insert(processed.hash.clone(), id);
+                self.write_file(&processed.path, &processed.stored_data)?;
+                self.manifest.fonts.push(font_metadata(id, &processed));
+                id
+            }
+        };
+        self.processed_fonts.insert(font_key, id);
+        Ok(id)
+    }
+
+    /// Write a resource file unless a file with the same path has already been written
+    fn write_file(&mut self, path: &str, data: &[u8]) -> Result<(), ArchiveError> {
+        if self.written.insert(path.to_string()) {
+            self.zip.start_file(path, self.options)?;
+            self.zip.write_all(data)?;
+        }
+        Ok(())
+    }
+
+    /// Write the manifest and finish the zip file, returning the underlying writer.
+    pub fn finish(mut self) -> Result<W, ArchiveError> {
+        self.zip.start_file(MANIFEST_PATH, self.options)?;
+        let manifest_json = serde_json::to_string_pretty(&self.manifest)?;
+        self.zip.write_all(manifest_json.as_bytes())?;
+        Ok(self.zip.finish()?)
+    }
+}
+
+/// Reads frames from a multi-frame archive.
+///
+/// Only the manifest is read when the archive is opened. Frames are decoded when they are requested, and resources
+/// are decoded the first time a frame uses them and cached for later frames.
+///
+/// The archive is checked against [`ArchiveLimits`] as it is read.
+pub struct FrameArchiveReader<R: Read + Seek> {
+    zip: ZipArchive<R>,
+    limits: ArchiveLimits,
+    manifest: FrameArchiveManifest,
+    resources: ResourceReconstructor,
+}
+
+impl<R: Read + Seek> FrameArchiveReader<R> {
+    /// Open an archive with the default [`ArchiveLimits`].
+    pub fn new(reader: R) -> Result<Self, ArchiveError> {
+        Self::with_limits(reader, ArchiveLimits::default())
+    }
+
+    /// Open an archive, rejecting archives that exceed `limits`.
+    pub fn with_limits(reader: R, limits: ArchiveLimits) -> Result<Self, ArchiveError> {
+        let mut zip = ZipArchive::new(reader)?;
+        limits.check_archive(&mut zip)?;
+
+        let manifest: FrameArchiveManifest =
+            serde_json::from_slice(&limits.read_file(&mut zip, MANIFEST_PATH)?)?;
+
+        if manifest.version != FrameArchiveManifest::CURRENT_VERSION {
+            return Err(ArchiveError::UnsupportedVersion(manifest.version));
+        }
+        limits.check_resources(&manifest.images, manifest.fonts.len())?;
+
+        let resources = ResourceReconstructor::new(
+            vec![None; manifest.fonts.len()],
+            vec![None; manifest.images.len()],
+        );
+        Ok(Self {
+            zip,
+            limits,
+            manifest,
+            resources,
+        })
+    }
+
+    pub fn manifest(&self) -> &FrameArchiveManifest {
+        &self.manifest
+    }
+
+    /// The number of frames in the archive
+    pub fn len(&self) -> usize {
+        self.manifest.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.manifest.frames.is_empty()
+    }
+
+    /// Read the frame at `index`. Returns `None` if the index is out of bounds.
+    pub fn frame(&mut self, index: usize) -> Option<Result<Frame, ArchiveError>> {
+        let meta = self.manifest.frames.get(index)?.clone();
+        Some(self.read_frame(meta))
+    }
+
+    /// Iterate over the frames in the archive, decoding each frame as it is reached.
+    pub fn frames(&mut self) -> impl Iterator<Item = Result<Frame, ArchiveError>> + '_ {
+        (0..self.len()).map_while(|index| self.frame(index))
+    }
+
+    /// Decode every resource in the archive, verifying their hashes. Resources are cached for later frames.
+    pub fn load_resources(&mut self) -> Result<(), ArchiveError> {
+        for index in 0..self.manifest.images.len() {
+            self.load(ResourceKind::Image, ResourceId(index))?;
+        }
+        for index in 0..self.manifest.fonts.len() {
+            self.load(ResourceKind::Font, ResourceId(index))?;
+        }
+        Ok(())
+    }
+
+    /// Read the draw commands of the frame at `index` without decoding the resources they reference. Returns `None`
+    /// if the index is out of bounds.
+    pub fn frame_commands(
+        &mut self,
+        index: usize,
+    ) -> Option<Result<Vec<SerializableRenderCommand>, ArchiveError>> {
+        let path = self.manifest.frames.get(index)?.path.clone();
+        Some(self.read_commands(&path))
+    }
+
+    fn read_frame(&mut self, meta: FrameMetadata) -> Result<Frame, ArchiveError> {
+        let mut commands = self.read_commands(&meta.path)?;
+
+        let commands = self.resources.load_and_convert(
+            &mut self.zip,
+            &self.manifest.images,
+            &self.manifest.fonts,
+            &self.limits,
+            &mut commands,
+        )?;
+
+        Ok(Frame {
+            timestamp: meta.timestamp,
+            width: meta.width,
+            height: meta.height,
+            scene: Scene {
+                tolerance: meta.tolerance,
+                commands,
+            },
+        })
+    }
+
+    /// Read the draw commands of a frame, checking them against the limits
+    fn read_commands(
+        &mut self,
+        path: &str,
+    ) -> Result<Vec<SerializableRenderCommand>, ArchiveError> {
+        let commands = read_commands(
+            &mut self.zip,
+            path,
+            self.manifest.command_encoding,
+            &self.limits,
+        )?;
+        self.limits.check_commands(
+            &commands,
+            self.manifest.images.len(),
+            self.manifest.fonts.len(),
+        )?;
+        Ok(commands)
+    }
+
+    /// Decode a resource if it has not been decoded yet
+    fn load(&mut self, kind: ResourceKind, id: ResourceId) -> Result<(), ArchiveError> {
+        self.resources.load(
+            &mut self.zip,
+            &self.manifest.images,
+            &self.manifest.fonts,
+            &self.limits,
+            kind,
+            id,
+        )
+    }
+}
@@ -0,0 +1,297 @@
+//! Write-side image processing: normalization, downsampling, and encoding.
+
+use std::io::Cursor;
+
+use image::imageops::FilterType;
+use image::{ExtendedColorType, ImageEncoder, ImageReader, Limits, RgbaImage};
+use peniko::{Blob, ImageAlphaType, ImageData, ImageFormat};
+use serde::{Deserialize, Serialize};
+
+use crate::{ArchiveError, SerializeConfig, sha256_hex};
+
+/// How images are encoded when they are written to an archive.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum ImagePolicy {
+    /// Lossless PNG
+    #[default]
+    Png,
+    /// Lossy JPEG with a quality between 1 and 100. JPEG has no alpha channel, so images with
+    /// transparency are stored as PNG.
+    Jpeg { quality: u8 },
+    /// Lossy WebP with a quality between 0 and 100. Other qualities are rejected when the archive is written.
+    WebP { quality: f32 },
+    /// The original encoded bytes supplied with [`SerializeConfig::with_encoded_image`]. The image is restored as
+    /// the straight-alpha pixels decoded from those bytes.
+    /// Images without original bytes, and images that are downsampled, are stored as PNG.
+    Original,
+}
+
+/// The encoding of an image file in an archive.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageEncoding {
+    #[default]
+    Png,
+    Jpeg,
+    #[serde(rename = "webp")]
+    WebP,
+}
+
+impl ImageEncoding {
+    /// The file extension used for images with this encoding
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageEncoding::Png => "png",
+            ImageEncoding::Jpeg => "jpg",
+            ImageEncoding::WebP => "webp",
+        }
+    }
+
+    fn image_format(self) -> image::ImageFormat {
+        match self {
+            ImageEncoding::Png => image::ImageFormat::Png,
+            ImageEncoding::Jpeg => image::ImageFormat::Jpeg,
+            ImageEncoding::WebP => image::ImageFormat::WebP,
+        }
+    }
+}
+
+/// An image in its original encoding, supplied by the caller for [`ImagePolicy::Original`].
+#[derive(Clone, Debug)]
+pub(crate) struct EncodedImage {
+    pub data: Blob<u8>,
+    pub encoding: ImageEncoding,
+}
+
+/// An image that has been normalized to RGBA8, optionally downsampled, and encoded, ready to be
+/// written into the archive.
+pub(crate) struct ProcessedImage {
+    /// The RGBA8 pixels of the image as it is stored (after downsampling, or as decoded from its original bytes).
+    pub image: ImageData,
+    /// The encoded image file.
+    pub stored_data: Vec<u8>,
+    pub encoding: ImageEncoding,
+    /// The hash identifying the image (see [`image_hash`]).
+    pub hash: String,
+    /// Archive-relative path (e.g. `images/<hash>-<width>x<height>.png`, see [`image_key`]).
+    pub path: String,
+    /// The size of the original image divided by the size of the stored image.
+    pub scale: (f64, f64),
+}
+
+/// Normalize, downsample, and encode an image according to `config`. Images are only downsampled if `downsample` is
+/// true, which requires every brush that uses the image to be rescaled.
+pub(crate) fn process_image(
+    image: &ImageData,
+    config: &SerializeConfig,
+    downsample: bool,
+) -> Result<ProcessedImage, ArchiveError> {
+    let rgba = ImageData {
+        data: convert_to_rgba(image)?,
+        format: ImageFormat::Rgba8,
+        alpha_type: image.alpha_type,
+        width: image.width,
+        height: image.height,
+    };
+    let mut stored = match config.max_image_size {
+        Some(max_size) if downsample => downsample_image(&rgba, max_size)?,
+        _ => rgba,
+    };
+    let downsampled = stored.width != image.width || stored.height != image.height;
+    let (width, height) = (stored.width, stored.height);
+
+    let (stored_data, encoding) = match config.image_policy {
+        ImagePolicy::Jpeg { quality } if is_opaque(stored.data.data()) => (
+            encode_jpeg(stored.data.data(), width, height, quality)?,
+            ImageEncoding::Jpeg,
+        ),
+        ImagePolicy::WebP { quality } => {
+            if !(0.0..=100.0).contains(&quality) {
+                return Err(ArchiveError::InvalidFormat(format!(
+                    "WebP quality must be between 0 and 100, got {quality}"
+                )));
+            }
+            (
+                webp::Encoder::from_rgba(stored.data.data(), width, height)
+                    .encode(quality)
+                    .to_vec(),
+                ImageEncoding::WebP,
+            )
+        }
+        ImagePolicy::Original if !downsampled => {
+            match config.encoded_images.get(&image.data.id()) {
+                Some(original) => {
+                    // The file is stored as it is, so the image is what decoding the file produces, which can differ
+                    // from the supplied pixels (for example if those are premultiplied)
+                    let file = original.data.data().to_vec();
+                    let pixels = decode_image(&file, original.encoding, width, height)?;
+                    stored.data = Blob::from(pixels);
+                    stored.alpha_type = ImageAlphaType::Alpha;
+                    (file, original.encoding)
+                }
+                None => (
+                    encode_png(stored.data.data(), width, height)?,
+                    ImageEncoding::Png,
+                ),
+            }
+        }
+        _ => (
+            encode_png(stored.data.data(), width, height)?,
+            ImageEncoding::Png,
+        ),
+    };
+
+    let hash = image_hash(encoding, stored.data.data(), &stored_data);
+    let path = format!(
+        "images/{}.{}",
+        image_key(&hash, stored.width, stored.height),
+        encoding.extension()
+    );
+    let scale = (
+        image.width as f64 / stored.width as f64,
+        image.height as f64 / stored.height as f64,
+    );
+    Ok(ProcessedImage {
+        image: stored,
+        stored_data,
+        encoding,
+        hash,
+        path,
+        scale,
+    })
+}
+
+/// The hash identifying an image. PNG images are identified by the hash of their RGBA8 pixels, while images with
+/// other encodings are identified by the hash of the encoded file, as decoding them is not guaranteed to produce the
+/// same pixels everywhere.
+pub(crate) fn image_hash(encoding: ImageEncoding, pixels: &[u8], file: &[u8]) -> String {
+    match encoding {
+        ImageEncoding::Png => sha256_hex(pixels),
+        ImageEncoding::Jpeg | ImageEncoding::WebP => sha256_hex(file),
+    }
+}
+
+/// The key identifying a stored image: its hash along with its dimensions, as the hash of a PNG image only covers its
+/// pixels and images of different sizes can have the same pixels. Used as the image's file name and to deduplicate
+/// images.
+pub(crate) fn image_key(hash: &str, width: u32, height: u32) -> String {
+    format!("{hash}-{width}x{height}")
+}
+
+/// Decode an image file to RGBA8 pixels, checking that it has the expected dimensions. Images that are larger than
+/// expected are rejected before they are decoded.
+pub(crate) fn decode_image(
+    data: &[u8],
+    encoding: ImageEncoding,
+    width: u32,
+    height: u32,
+) -> Result<Vec<u8>, ArchiveError> {
+    let mut limits = Limits::no_limits();
+    limits.max_image_width = Some(width);
+    limits.max_image_height = Some(height);
+    let mut reader = ImageReader::with_format(Cursor::new(data), encoding.image_format());
+    reader.limits(limits);
+    let img = reader.decode()?;
+    if img.width() != width || img.height() != height {
+        return Err(ArchiveError::InvalidFormat(format!(
+            "Image is {}x{} but the manifest says {width}x{height}",
+            img.width(),
+            img.height()
+        )));
+    }
+    Ok(img.into_rgba8().into_raw())
+}
+
+/// Downsample an RGBA8 image so that neither side exceeds `max_size`, preserving its aspect ratio.
+fn downsample_image(image: &ImageData, max_size: u32) -> Result<ImageData, ArchiveError> {
+    let max_size = max_size.max(1);
+    let largest = image.width.max(image.height);
+    if largest <= max_size {
+        return Ok(image.clone());
+    }
+
+    let scale = max_size as f64 / largest as f64;
+    let width = ((image.width as f64 * scale).round() as u32).clamp(1, max_size);
+    let height = ((image.height as f64 * scale).round() as u32).clamp(1, max_size);
+
+    let img: RgbaImage = RgbaImage::from_raw(image.width, image.height, image.data.data().to_vec())
+        .ok_or_else(|| ArchiveError::InvalidFormat("Failed to create image buffer".to_string()))?;
+    let resized = image::imageops::resize(&img, width, height, FilterType::Triangle);
+
+    Ok(ImageData {
+        data: Blob::from(resized.into_raw()),
+        format: ImageFormat::Rgba8,
+        alpha_type: image.alpha_type,
+        width,
+        height,
+    })
+}
+
+fn is_opaque(rgba_data: &[u8]) -> bool {
+    rgba_data.chunks_exact(4).all(|pixel| pixel[3] == 255)
+}
+
+fn encode_png(rgba_data: &[u8], width: u32, height: u32) -> Result<Vec<u8>, ArchiveError> {
+    let mut png_data = Vec::new();
+    let encoder = image::codecs::png::PngEncoder::new(&mut png_data);
+    encoder.write_image(rgba_data, width, height, ExtendedColorType::Rgba8)?;
+    Ok(png_data)
+}
+
+fn encode_jpeg(
+    rgba_data: &[u8],
+    width: u32,
+    height: u32,
+    quality: u8,
+) -> Result<Vec<u8>, ArchiveError> {
+    let rgb_data: Vec<u8> = rgba_data
+        .chunks_exact(4)
+        .flat_map(|pixel| [pixel[0], pixel[1], pixel[2]])
+        .collect();
+
+    let mut jpeg_data = Vec::new();
+    let encoder =
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg_data, quality.clamp(1, 100));
+    encoder.write_image(&rgb_data, width, height, ExtendedColorType::Rgb8)?;
+    Ok(jpeg_data)
+}
+
+/// Convert RGBA8 data to the target [`ImageFormat`].
+pub(crate) fn convert_from_rgba(
+    rgba_blob: &Blob<u8>,
+    target: ImageFormat,
+) -> Result<Blob<u8>, ArchiveError> {
+    match target {
+        ImageFormat::Rgba8 => Ok(rgba_blob.clone()),
+        ImageFormat::Bgra8 => {
+            // Swap R and B channels
+            let mut bgra = rgba_blob.data().to_vec();
+            for chunk in bgra.chunks_exact_mut(4) {
+                chunk.swap(0, 2);
+            }
+            Ok(Blob::from(bgra))
+        }
+        other => Err(ArchiveError::InvalidFormat(format!(
+            "Unsupported image format: {other:?}"
+        ))),
+    }
+}
+
+/// Convert [`ImageData`] to RGBA8 format.
+fn convert_to_rgba(image: &ImageData) -> Result<Blob<u8>, ArchiveError> {
+    match image.format {
+        ImageFormat::Rgba8 => Ok(image.data.clone()),
+        ImageFormat::Bgra8 => {
+            // Swap B and R channels
+            let mut rgba = image.data.data().to_vec();
+            for chunk in rgba.chunks_exact_mut(4) {
+                chunk.swap(0, 2);
+            }
+            Ok(Blob::from(rgba))
+        }
+        // ImageFormat is non_exhaustive, so simply error out if we encounter an unknown format
+        other => Err(ArchiveError::InvalidFormat(format!(
+            "Unsupported image format: {other:?}"
+        ))),
+    }
+}
@@ -5,26 +5,56 @@
 //! The serialized scene is a zip archive containing:
 //!
 //! - `resources.json` - Metadata mapping resource files to IDs
-//! - `draw_commands.json` - Serialized draw commands referencing resources by ID
-//! - `images/<sha256_hash>.png` - Image files (PNG format)
-//! - `fonts/<sha256_hash>.{woff2,ttf}` - Font data files (optionally WOFF2-compressed and subsetted)
+//! - `draw_commands.json` or `draw_commands.bin` - Serialized draw commands referencing resources by ID,
+//!   encoded as JSON or with [postcard](https://docs.rs/postcard) depending on [`CommandEncoding`]
+//! - `images/<hash>-<width>x<height>.{png,jpg,webp}` - Image files, encoded according to the [`ImagePolicy`]
+//! - `fonts/<sha256_hash>.{woff2,ttf}` - Font data files (optionally WOFF2-compressed, subsetted, and instanced)
+//!
+//! Archives can be read in full with [`SceneArchive::deserialize`], or lazily with [`SceneArchiveReader`], which only
+//! decodes the resources that are requested. Both check archives against [`ArchiveLimits`], as archives may come from
+//! untrusted sources.
+//!
+//! Sequences of frames that share resources can be stored with [`FrameArchiveWriter`] and read back with
+//! [`FrameArchiveReader`].
+//!
+//! Frames rendered to a window can be captured with [`CapturingWindowRenderer`].
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::{Read, Seek, Write};
 
-use image::{ImageBuffer, ImageEncoder, RgbaImage};
+use peniko::kurbo::Affine;
 use peniko::{Blob, Brush, FontData, ImageAlphaType, ImageBrush, ImageData, ImageFormat};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, DateTime};
 use zip::{ZipArchive, ZipWriter};
 
 use multirender::recording::{FillCommand, GlyphRunCommand, RenderCommand, Scene, StrokeCommand};
 
+mod capture;
+mod font_instancer;
 mod font_writer;
+mod frames;
+mod image_writer;
 mod json_formatter;
-
-use font_writer::FontWriter;
+mod limits;
+mod migrate;
+mod reader;
+
+pub use capture::CapturingWindowRenderer;
+pub use frames::{
+    Frame, FrameArchiveManifest, FrameArchiveReader, FrameArchiveWriter, FrameMetadata,
+};
+pub use limits::ArchiveLimits;
+pub use reader::SceneArchiveReader;
+
+use font_writer::{FontWriter, ProcessedFont};
+use image_writer::{
+    EncodedImage, ProcessedImage, convert_from_rgba, decode_image, image_hash, image_key,
+    process_image,
+};
+pub use image_writer::{ImageEncoding, ImagePolicy};
 
 /// A render command with resources replaced by IDs.
 pub type SerializableRenderCommand = RenderCommand<FontResourceId, ResourceId>;
@@ -54,7 +84,10 @@ pub struct SceneArchive {
     pub commands: Vec<SerializableRenderCommand>,
     /// Font data (one per font resource, optionally WOFF2-compressed and/or subsetted).
     pub fonts: Vec<Blob<u8>>,
+    /// Image pixels, normalized to RGBA8.
     pub images: Vec<ImageData>,
+    /// Encoded image files (one per image), as stored in the archive.
+    pub image_files: Vec<Blob<u8>>,
 }
 
 /// The resources manifest stored in the archive.
@@ -64,29 +97,56 @@ pub struct ResourceManifest {
     pub version: u32,
     /// Scene tolerance (used for path flattening)
     pub tolerance: f64,
+    /// The encoding of the draw commands file
+    pub command_encoding: CommandEncoding,
     pub images: Vec<ImageMetadata>,
     pub fonts: Vec<FontMetadata>,
 }
 
 impl ResourceManifest {
-    /// Current archive format version. Bump this when the format changes.
-    pub const CURRENT_VERSION: u32 = 1;
+    /// Current archive format version. Bump this when the format changes, and add a migration from the
+    /// previous version so that existing archives can still be read.
+    pub const CURRENT_VERSION: u32 = 3;
 
     pub fn new(tolerance: f64) -> Self {
         Self {
             version: Self::CURRENT_VERSION,
             tolerance,
+            command_encoding: CommandEncoding::default(),
             images: Vec::new(),
             fonts: Vec::new(),
         }
     }
 }
 
+/// How draw commands are encoded in the archive.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandEncoding {
+    /// Human-readable JSON, with paths stored as SVG path strings
+    #[default]
+    Json,
+    /// A compact binary encoding, with paths stored as raw verbs and points so that coordinates round-trip exactly
+    Binary,
+}
+
+impl CommandEncoding {
+    /// Path to the draw commands file within the archive
+    pub fn path(self) -> &'static str {
+        match self {
+            CommandEncoding::Json => "draw_commands.json",
+            CommandEncoding::Binary => "draw_commands.bin",
+        }
+    }
+}
+
 /// Metadata for an image resource.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ImageMetadata {
     #[serde(flatten)]
     pub entry: ResourceEntry,
+    /// How the image file is encoded
+    pub encoding: ImageEncoding,
     /// The original image format. Images are stored as RGBA8 in the archive
     /// and converted back to the original format on reconstruction.
     pub format: ImageFormat,
@@ -113,20 +173,66 @@ pub struct ResourceEntry {
     pub kind: ResourceKind,
     /// The size of the raw decompressed resource data in bytes.
     pub size: usize,
-    /// SHA-256 hash of the resource's raw content
+    /// SHA-256 hash of the resource's raw content. For images that are not stored as PNG, this is the hash of the
+    /// encoded file instead.
     pub sha256_hash: String,
     /// Path to the resource file within the archive
     pub path: String,
 }
 
 /// The type of resource stored in the archive.
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ResourceKind {
     Image,
     Font,
 }
 
+/// Call `f` with the kind and ID of every resource referenced by `cmd`, allowing the ID to be replaced.
+pub fn visit_resources(
+    cmd: &mut SerializableRenderCommand,
+    mut f: impl FnMut(ResourceKind, &mut ResourceId),
+) {
+    let brush = match cmd {
+        RenderCommand::Fill(fill) => &mut fill.brush,
+        RenderCommand::Stroke(stroke) => &mut stroke.brush,
+        RenderCommand::GlyphRun(glyph_run) => {
+            f(ResourceKind::Font, &mut glyph_run.font_data.resource_id);
+            &mut glyph_run.brush
+        }
+        RenderCommand::PushLayer(_)
+        | RenderCommand::PushClipLayer(_)
+        | RenderCommand::PopLayer
+        | RenderCommand::BoxShadow(_) => return,
+    };
+    if let Brush::Image(image_brush) = brush {
+        f(ResourceKind::Image, &mut image_brush.image);
+    }
+}
+
+/// The kind and ID of every resource referenced by `cmd`.
+fn resource_references(
+    cmd: &SerializableRenderCommand,
+) -> impl Iterator<Item = (ResourceKind, ResourceId)> {
+    let (font, brush) = match cmd {
+        RenderCommand::Fill(fill) => (None, Some(&fill.brush)),
+        RenderCommand::Stroke(stroke) => (None, Some(&stroke.brush)),
+        RenderCommand::GlyphRun(glyph_run) => (
+            Some((ResourceKind::Font, glyph_run.font_data.resource_id)),
+            Some(&glyph_run.brush),
+        ),
+        RenderCommand::PushLayer(_)
+        | RenderCommand::PushClipLayer(_)
+        | RenderCommand::PopLayer
+        | RenderCommand::BoxShadow(_) => (None, None),
+    };
+    let image = match brush {
+        Some(Brush::Image(image_brush)) => Some((ResourceKind::Image, image_brush.image)),
+        _ => None,
+    };
+    font.into_iter().chain(image)
+}
+
 /// Collects and deduplicates resources from a scene.
 struct ResourceCollector {
     fonts: FontWriter,
@@ -134,6 +240,9 @@ struct ResourceCollector {
     image_id_map: HashMap<u64, ResourceId>,
     /// Collected images
     images: Vec<ImageData>,
+    /// Images used as the brush of a glyph run. Glyph runs have no brush transform, so these images can't be rescaled
+    /// to cover the same area if they are downsampled.
+    glyph_run_images: HashSet<ResourceId>,
 }
 
 impl ResourceCollector {
@@ -142,6 +251,7 @@ impl ResourceCollector {
             fonts: FontWriter::new(config),
             image_id_map: HashMap::new(),
             images: Vec::new(),
+            glyph_run_images: HashSet::new(),
         }
     }
 
@@ -196,9 +306,14 @@ impl ResourceCollector {
                 shape: fill.shape.clone(),
             }),
             RenderCommand::GlyphRun(glyph_run) => {
-                let resource_id = self.fonts.register(&glyph_run.font_data);
+                let resource_id = self
+                    .fonts
+                    .register(&glyph_run.font_data, &glyph_run.normalized_coords);
                 self.fonts.record_glyphs(resource_id, &glyph_run.glyphs);
                 let brush = self.convert_brush(&glyph_run.brush);
+                if let Brush::Image(image_brush) = &brush {
+                    self.glyph_run_images.insert(image_brush.image);
+                }
                 SerializableRenderCommand::GlyphRun(GlyphRunCommand {
                     font_data: FontResourceId {
                         resource_id,
@@ -206,7 +321,12 @@ impl ResourceCollector {
                     },
                     font_size: glyph_run.font_size,
                     hint: glyph_run.hint,
-                    normalized_coords: glyph_run.normalized_coords.clone(),
+                    // Instanced fonts are static, the location is baked into the font
+                    normalized_coords: if self.fonts.is_instanced(resource_id) {
+                        Vec::new()
+                    } else {
+                        glyph_run.normalized_coords.clone()
+                    },
                     style: glyph_run.style.clone(),
                     brush,
                     brush_alpha: glyph_run.brush_alpha,
@@ -223,28 +343,90 @@ impl ResourceCollector {
 }
 
 /// Reconstructs resources from deserialized data.
+///
+/// Resources that have not been loaded are `None`, which allows resources to be decoded lazily.
 struct ResourceReconstructor {
-    fonts: Vec<FontData>,
-    images: Vec<ImageData>,
+    fonts: Vec<Option<FontData>>,
+    images: Vec<Option<ImageData>>,
 }
 
 impl ResourceReconstructor {
-    fn new(fonts: Vec<FontData>, images: Vec<ImageData>) -> Self {
+    fn new(fonts: Vec<Option<FontData>>, images: Vec<Option<ImageData>>) -> Self {
         Self { fonts, images }
     }
 
     fn get_font(&self, id: ResourceId) -> Result<&FontData, ArchiveError> {
         self.fonts
             .get(id.0)
+            .and_then(Option::as_ref)
             .ok_or(ArchiveError::ResourceNotFound(id))
     }
 
     fn get_image(&self, id: ResourceId) -> Result<&ImageData, ArchiveError> {
         self.images
             .get(id.0)
+            .and_then(Option::as_ref)
             .ok_or(ArchiveError::ResourceNotFound(id))
     }
 
+    /// Decode a resource if it has not been decoded yet, verifying its hash.
+    fn load<R: Read + Seek>(
+        &mut self,
+        zip: &mut ZipArchive<R>,
+        images: &[ImageMetadata],
+        fonts: &[FontMetadata],
+        limits: &ArchiveLimits,
+        kind: ResourceKind,
+        id: ResourceId,
+    ) -> Result<(), ArchiveError> {
+        match kind {
+            ResourceKind::Image => {
+                let (Some(slot), Some(meta)) = (self.images.get(id.0), images.get(id.0)) else {
+                    return Err(ArchiveError::ResourceNotFound(id));
+                };
+                if slot.is_none() {
+                    let (image, _) = read_image(zip, meta, limits)?;
+                    self.images[id.0] = Some(restore_image(&image, meta)?);
+                }
+            }
+            ResourceKind::Font => {
+                let (Some(slot), Some(meta)) = (self.fonts.get(id.0), fonts.get(id.0)) else {
+                    return Err(ArchiveError::ResourceNotFound(id));
+                };
+                if slot.is_none() {
+                    let font = read_font(zip, meta, limits)?;
+                    self.fonts[id.0] = Some(decode_font(&font, &meta.entry.path, limits)?);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Convert draw commands, first decoding the resources they reference that have not been decoded yet.
+    fn load_and_convert<R: Read + Seek>(
+        &mut self,
+        zip: &mut ZipArchive<R>,
+        images: &[ImageMetadata],
+        fonts: &[FontMetadata],
+        limits: &ArchiveLimits,
+        commands: &mut [SerializableRenderCommand],
+    ) -> Result<Vec<RenderCommand>, ArchiveError> {
+        let mut result = Ok(());
+        for cmd in commands.iter_mut() {
+            visit_resources(cmd, |kind, id| {
+                if result.is_ok() {
+                    result = self.load(zip, images, fonts, limits, kind, *id);
+                }
+            });
+        }
+        result?;
+
+        commands
+            .iter()
+            .map(|cmd| self.convert_command(cmd))
+            .collect()
+    }
+
     /// Convert a [`SerializableBrush`] back to a [`Brush`].
     fn convert_brush(&self, brush: &SerializableBrush) -> Result<Brush, ArchiveError> {
         Ok(match brush {
@@ -326,56 +508,238 @@ fn hex_encode(bytes: &[u8]) -> String {
     hex
 }
 
-fn encode_rgba_to_png(rgba_data: &[u8], width: u32, height: u32) -> Result<Vec<u8>, ArchiveError> {
-    let img: RgbaImage = ImageBuffer::from_raw(width, height, rgba_data.to_vec())
-        .ok_or_else(|| ArchiveError::InvalidFormat("Failed to create image buffer".to_string()))?;
+/// Convert an RGBA8 image read from an archive back to its original format.
+fn restore_image(image: &ImageData, meta: &ImageMetadata) -> Result<ImageData, ArchiveError> {
+    Ok(ImageData {
+        data: convert_from_rgba(&image.data, meta.format)?,
+        format: meta.format,
+        alpha_type: image.alpha_type,
+        width: image.width,
+        height: image.height,
+    })
+}
 
-    let mut png_data = Vec::new();
-    let encoder = image::codecs::png::PngEncoder::new(&mut png_data);
-    encoder.write_image(&img, width, height, image::ExtendedColorType::Rgba8)?;
+/// Decode font data read from an archive, decompressing WOFF2 fonts.
+fn decode_font(
+    font_blob: &Blob<u8>,
+    path: &str,
+    limits: &ArchiveLimits,
+) -> Result<FontData, ArchiveError> {
+    let data = font_blob.data();
+    let ttf_data = if data.starts_with(b"wOF2") {
+        // Check the decompressed size recorded in the WOFF2 header before decompressing
+        let size = data
+            .get(16..20)
+            .map(|size| u32::from_be_bytes(size.try_into().unwrap()))
+            .ok_or_else(|| {
+                ArchiveError::FontProcessing(format!("{path} has a truncated WOFF2 header"))
+            })?;
+        limits.check_file_size(path, size.into())?;
+        wuff::decompress_woff2(data)
+            .map_err(|e| ArchiveError::FontProcessing(format!("WOFF2 decoding failed: {e}")))?
+    } else {
+        data.to_vec()
+    };
+    Ok(FontData::new(Blob::from(ttf_data), 0))
+}
 
-    Ok(png_data)
+fn verify_hash(entry: &ResourceEntry, data: &[u8]) -> Result<(), ArchiveError> {
+    let hash = sha256_hex(data);
+    if hash != entry.sha256_hash {
+        return Err(ArchiveError::InvalidFormat(format!(
+            "Hash mismatch for {}: expected {}, got {}",
+            entry.path, entry.sha256_hash, hash
+        )));
+    }
+    Ok(())
 }
 
-fn decode_png_to_rgba(png_data: &[u8]) -> Result<Vec<u8>, ArchiveError> {
-    let img = image::load_from_memory_with_format(png_data, image::ImageFormat::Png)?;
-    Ok(img.into_rgba8().into_raw())
+/// The compression level used for deterministic archives.
+const DETERMINISTIC_COMPRESSION_LEVEL: i64 = 6;
+
+/// Zip options for the entries of an archive. Deterministic archives use fixed options rather than defaults that
+/// depend on the enabled features of the `zip` crate (such as the current time as the modification time).
+fn file_options(deterministic: bool) -> SimpleFileOptions {
+    if deterministic {
+        SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .compression_level(Some(DETERMINISTIC_COMPRESSION_LEVEL))
+            .last_modified_time(DateTime::default())
+            .unix_permissions(0o644)
+    } else {
+        SimpleFileOptions::default()
+    }
 }
 
-/// Convert RGBA8 data to the target [`ImageFormat`].
-fn convert_from_rgba(rgba_blob: &Blob<u8>, target: ImageFormat) -> Result<Blob<u8>, ArchiveError> {
-    match target {
-        ImageFormat::Rgba8 => Ok(rgba_blob.clone()),
-        ImageFormat::Bgra8 => {
-            // Swap R and B channels
-            let mut bgra = rgba_blob.data().to_vec();
-            for chunk in bgra.chunks_exact_mut(4) {
-                chunk.swap(0, 2);
-            }
-            Ok(Blob::from(bgra))
+fn write_commands<W: Write + Seek>(
+    zip: &mut ZipWriter<W>,
+    path: &str,
+    encoding: CommandEncoding,
+    commands: &[SerializableRenderCommand],
+    options: SimpleFileOptions,
+) -> Result<(), ArchiveError> {
+    zip.start_file(path, options)?;
+    match encoding {
+        CommandEncoding::Json => {
+            let commands_json = json_formatter::to_json_depth_limited(&commands, 3)?;
+            zip.write_all(commands_json.as_bytes())?;
+        }
+        CommandEncoding::Binary => {
+            let commands_bin = postcard::to_stdvec(commands)?;
+            zip.write_all(&commands_bin)?;
         }
-        other => Err(ArchiveError::InvalidFormat(format!(
-            "Unsupported image format: {other:?}"
-        ))),
     }
+    Ok(())
+}
+
+fn read_commands<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    path: &str,
+    encoding: CommandEncoding,
+    limits: &ArchiveLimits,
+) -> Result<Vec<SerializableRenderCommand>, ArchiveError> {
+    let contents = limits.read_file(zip, path)?;
+    Ok(match encoding {
+        CommandEncoding::Json => serde_json::from_slice(&contents)?,
+        CommandEncoding::Binary => postcard::from_bytes(&contents)?,
+    })
+}
+
+/// Read the manifest of a scene archive, upgrading archives written with older versions of the format.
+///
+/// The draw commands of archives with JSON-encoded commands written with older versions are upgraded along with the
+/// manifest, so they are returned as well. Otherwise only the manifest is read.
+///
+/// The archive size and the resources listed in the manifest are checked against `limits`.
+fn read_manifest<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    limits: &ArchiveLimits,
+) -> Result<(ResourceManifest, Option<Vec<SerializableRenderCommand>>), ArchiveError> {
+    limits.check_archive(zip)?;
+    let mut manifest_value: serde_json::Value =
+        serde_json::from_slice(&limits.read_file(zip, "resources.json")?)?;
+
+    let version = migrate::manifest_version(&manifest_value)?;
+    let (manifest, commands): (ResourceManifest, _) = match version {
+        ResourceManifest::CURRENT_VERSION => (serde_json::from_value(manifest_value)?, None),
+        1..ResourceManifest::CURRENT_VERSION => match migrate::command_encoding(&manifest_value)? {
+            CommandEncoding::Json => {
+                let mut commands_value: serde_json::Value =
+                    serde_json::from_slice(&limits.read_file(zip, CommandEncoding::Json.path())?)?;
+                migrate::migrate(&mut manifest_value, Some(&mut commands_value))?;
+                (
+                    serde_json::from_value(manifest_value)?,
+                    Some(serde_json::from_value::<Vec<_>>(commands_value)?),
+                )
+            }
+            CommandEncoding::Binary => {
+                migrate::migrate(&mut manifest_value, None)?;
+                (serde_json::from_value(manifest_value)?, None)
+            }
+        },
+        _ => return Err(ArchiveError::UnsupportedVersion(version)),
+    };
+
+    limits.check_resources(&manifest.images, manifest.fonts.len())?;
+    if let Some(commands) = &commands {
+        limits.check_commands(commands, manifest.images.len(), manifest.fonts.len())?;
+    }
+    Ok((manifest, commands))
 }
 
-/// Convert [`ImageData`] to RGBA8 format.
-fn convert_to_rgba(image: &ImageData) -> Result<Blob<u8>, ArchiveError> {
-    match image.format {
-        ImageFormat::Rgba8 => Ok(image.data.clone()),
-        ImageFormat::Bgra8 => {
-            // Swap B and R channels
-            let mut rgba = image.data.data().to_vec();
-            for chunk in rgba.chunks_exact_mut(4) {
-                chunk.swap(0, 2);
+/// Read an image file, decoding it to RGBA8 and verifying its hash.
+/// Returns the decoded image along with the encoded file.
+fn read_image<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    meta: &ImageMetadata,
+    limits: &ArchiveLimits,
+) -> Result<(ImageData, Blob<u8>), ArchiveError> {
+    limits.check_image(meta.width, meta.height)?;
+    let file_data = limits.read_file(zip, &meta.entry.path)?;
+    let rgba_data = decode_image(&file_data, meta.encoding, meta.width, meta.height)?;
+    let hash = image_hash(meta.encoding, &rgba_data, &file_data);
+    if hash != meta.entry.sha256_hash {
+        return Err(ArchiveError::InvalidFormat(format!(
+            "Hash mismatch for {}: expected {}, got {}",
+            meta.entry.path, meta.entry.sha256_hash, hash
+        )));
+    }
+
+    let image = ImageData {
+        data: Blob::from(rgba_data),
+        format: ImageFormat::Rgba8,
+        alpha_type: meta.alpha_type,
+        width: meta.width,
+        height: meta.height,
+    };
+    Ok((image, Blob::from(file_data)))
+}
+
+/// Read stored font data, verifying its hash.
+fn read_font<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    meta: &FontMetadata,
+    limits: &ArchiveLimits,
+) -> Result<Blob<u8>, ArchiveError> {
+    let raw_data = limits.read_file(zip, &meta.entry.path)?;
+    verify_hash(&meta.entry, &raw_data)?;
+    Ok(Blob::from(raw_data))
+}
+
+fn image_metadata(
+    id: ResourceId,
+    original: &ImageData,
+    processed: &ProcessedImage,
+) -> ImageMetadata {
+    ImageMetadata {
+        entry: ResourceEntry {
+            id,
+            kind: ResourceKind::Image,
+            size: processed.image.data.len(),
+            sha256_hash: processed.hash.clone(),
+            path: processed.path.clone(),
+        },
+        encoding: processed.encoding,
+        format: original.format,
+        alpha_type: processed.image.alpha_type,
+        width: processed.image.width,
+        height: processed.image.height,
+    }
+}
+
+fn font_metadata(id: ResourceId, processed: &ProcessedFont) -> FontMetadata {
+    FontMetadata {
+        entry: ResourceEntry {
+            id,
+            kind: ResourceKind::Font,
+            size: processed.raw_size,
+            sha256_hash: processed.hash.clone(),
+            path: processed.path.clone(),
+        },
+    }
+}
+
+/// Scale the brushes of downsampled images so that they cover the same area. `image_scales` is the scale of each
+/// image referenced by `commands`, indexed by its [`ResourceId`].
+///
+/// Glyph runs have no brush transform, so images used by glyph runs must not be downsampled.
+fn scale_image_brushes(commands: &mut [SerializableRenderCommand], image_scales: &[(f64, f64)]) {
+    for cmd in commands {
+        let (brush, brush_transform) = match cmd {
+            SerializableRenderCommand::Fill(fill) => (&fill.brush, &mut fill.brush_transform),
+            SerializableRenderCommand::Stroke(stroke) => {
+                (&stroke.brush, &mut stroke.brush_transform)
+            }
+            _ => continue,
+        };
+        if let Brush::Image(image_brush) = brush {
+            let (sx, sy) = image_scales[image_brush.image.0];
+            if sx != 1.0 || sy != 1.0 {
+                *brush_transform = Some(
+                    brush_transform.unwrap_or(Affine::IDENTITY) * Affine::scale_non_uniform(sx, sy),
+                );
             }
-            Ok(Blob::from(rgba))
         }
-        // ImageFormat is non_exhaustive, so simply error out if we encounter an unknown format
-        other => Err(ArchiveError::InvalidFormat(format!(
-            "Unsupported image format: {other:?}"
-        ))),
     }
 }
 
@@ -383,65 +747,36 @@ impl SceneArchive {
     /// Create a new SceneArchive from a recorded Scene.
     pub fn from_scene(scene: &Scene, config: &SerializeConfig) -> Result<Self, ArchiveError> {
         let mut manifest = ResourceManifest::new(scene.tolerance);
+        manifest.command_encoding = config.command_encoding;
         let mut collector = ResourceCollector::new(config.clone());
 
-        let commands: Vec<_> = scene
+        let mut commands: Vec<_> = scene
             .commands
             .iter()
             .map(|cmd| collector.convert_command(cmd))
             .collect();
 
-        // Normalize all images to RGBA8
-        let images: Vec<ImageData> = collector
-            .images
-            .iter()
-            .map(|image| {
-                let data = convert_to_rgba(image)?;
-                Ok(ImageData {
-                    data,
-                    format: ImageFormat::Rgba8,
-                    alpha_type: image.alpha_type,
-                    width: image.width,
-                    height: image.height,
-                })
-            })
-            .collect::<Result<Vec<_>, ArchiveError>>()?;
-
-        // Add image metadata
-        for (idx, (original, normalized)) in collector.images.iter().zip(images.iter()).enumerate()
-        {
-            let data = normalized.data.data();
-            let hash = sha256_hex(data);
-            let path = format!("images/{}.png", hash);
-
-            manifest.images.push(ImageMetadata {
-                entry: ResourceEntry {
-                    id: ResourceId(idx),
-                    kind: ResourceKind::Image,
-                    size: data.len(),
-                    sha256_hash: hash,
-                    path,
-                },
-                format: original.format,
-                alpha_type: original.alpha_type,
-                width: original.width,
-                height: original.height,
-            });
+        // Normalize all images to RGBA8, downsample and encode them
+        let mut images = Vec::with_capacity(collector.images.len());
+        let mut image_files = Vec::with_capacity(collector.images.len());
+        let mut image_scales = Vec::with_capacity(collector.images.len());
+        for (idx, original) in collector.images.iter().enumerate() {
+            let downsample = !collector.glyph_run_images.contains(&ResourceId(idx));
+            let processed = process_image(original, config, downsample)?;
+            manifest
+                .images
+                .push(image_metadata(ResourceId(idx), original, &processed));
+            images.push(processed.image);
+            image_files.push(Blob::from(processed.stored_data));
+            image_scales.push(processed.scale);
         }
+        scale_image_brushes(&mut commands, &image_scales);
 
         // Add font metadata.
         let mut fonts = Vec::new();
         for (idx, result) in collector.fonts.into_processed().enumerate() {
             let font = result?;
-            manifest.fonts.push(FontMetadata {
-                entry: ResourceEntry {
-                    id: ResourceId(idx),
-                    kind: ResourceKind::Font,
-                    size: font.raw_size,
-                    sha256_hash: font.hash,
-                    path: font.path,
-                },
-            });
+            manifest.fonts.push(font_metadata(ResourceId(idx), &font));
             fonts.push(Blob::from(font.stored_data));
         }
 
@@ -450,43 +785,34 @@ impl SceneArchive {
             commands,
             fonts,
             images,
+            image_files,
         })
     }
 
-    /// Convert this archive back to a Scene.
+    /// Convert this archive back to a Scene, with the default [`ArchiveLimits`].
     pub fn to_scene(&self) -> Result<Scene, ArchiveError> {
+        self.to_scene_with_limits(&ArchiveLimits::default())
+    }
+
+    /// Convert this archive back to a Scene, rejecting archives that exceed `limits`.
+    pub fn to_scene_with_limits(&self, limits: &ArchiveLimits) -> Result<Scene, ArchiveError> {
+        limits.check_resources(&self.manifest.images, self.manifest.fonts.len())?;
+        limits.check_commands(&self.commands, self.images.len(), self.fonts.len())?;
+
         // Convert images back to their original format
-        let images: Vec<ImageData> = self
+        let images: Vec<Option<ImageData>> = self
             .images
             .iter()
             .zip(self.manifest.images.iter())
-            .map(|(image, meta)| {
-                let data = convert_from_rgba(&image.data, meta.format)?;
-                Ok(ImageData {
-                    data,
-                    format: meta.format,
-                    alpha_type: image.alpha_type,
-                    width: image.width,
-                    height: image.height,
-                })
-            })
+            .map(|(image, meta)| restore_image(image, meta).map(Some))
             .collect::<Result<Vec<_>, ArchiveError>>()?;
 
         // Decode fonts.
-        let fonts_ttf: Vec<FontData> = self
+        let fonts_ttf: Vec<Option<FontData>> = self
             .fonts
             .iter()
-            .map(|font_blob| {
-                let data = font_blob.data();
-                let ttf_data = if data.starts_with(b"wOF2") {
-                    wuff::decompress_woff2(data).map_err(|e| {
-                        ArchiveError::FontProcessing(format!("WOFF2 decoding failed: {e}"))
-                    })?
-                } else {
-                    data.to_vec()
-                };
-                Ok(FontData::new(Blob::from(ttf_data), 0))
-            })
+            .zip(self.manifest.fonts.iter())
+            .map(|(font_blob, meta)| decode_font(font_blob, &meta.entry.path, limits).map(Some))
             .collect::<Result<Vec<_>, ArchiveError>>()?;
 
         let reconstructor = ResourceReconstructor::new(fonts_ttf, images);
@@ -505,8 +831,25 @@ impl SceneArchive {
 
     /// Serialize the archive to a zip file.
     pub fn serialize<W: Write + Seek>(&self, writer: W) -> Result<(), ArchiveError> {
+        self.write_zip(writer, false)
+    }
+
+    /// Serialize the archive to a zip file, producing the same bytes every time the same archive is serialized.
+    ///
+    /// Entries are written in a fixed order (the manifest, the draw commands, then resources sorted by path) with a
+    /// fixed modification time and compression level, so that archives can be content-addressed and compared byte
+    /// for byte. The output is only reproducible with the same version of the deflate implementation.
+    pub fn serialize_deterministic<W: Write + Seek>(&self, writer: W) -> Result<(), ArchiveError> {
+        self.write_zip(writer, true)
+    }
+
+    fn write_zip<W: Write + Seek>(
+        &self,
+        writer: W,
+        deterministic: bool,
+    ) -> Result<(), ArchiveError> {
         let mut zip = ZipWriter::new(writer);
-        let options = SimpleFileOptions::default();
+        let options = file_options(deterministic);
 
         // Write resources.json
         {
@@ -515,106 +858,86 @@ impl SceneArchive {
             zip.write_all(manifest_json.as_bytes())?;
         }
 
-        // Write draw_commands.json
-        {
-            zip.start_file("draw_commands.json", options)?;
-            let commands_json = json_formatter::to_json_depth_limited(&self.commands, 3)?;
-            zip.write_all(commands_json.as_bytes())?;
-        }
-
-        // Write image files as PNG
-        for (idx, image) in self.images.iter().enumerate() {
-            let path = &self.manifest.images[idx].entry.path;
-            let png_data = encode_rgba_to_png(image.data.data(), image.width, image.height)?;
-            zip.start_file(path, options)?;
-            zip.write_all(&png_data)?;
+        // Write draw commands
+        write_commands(
+            &mut zip,
+            self.manifest.command_encoding.path(),
+            self.manifest.command_encoding,
+            &self.commands,
+            options,
+        )?;
+
+        // Write image and font files
+        let image_files = self
+            .image_files
+            .iter()
+            .zip(&self.manifest.images)
+            .map(|(data, meta)| (&meta.entry.path, data));
+        let font_files = self
+            .fonts
+            .iter()
+            .zip(&self.manifest.fonts)
+            .map(|(data, meta)| (&meta.entry.path, data));
+        let mut resource_files: Vec<_> = image_files.chain(font_files).collect();
+        if deterministic {
+            resource_files.sort_by_key(|(path, _)| *path);
         }
-
-        // Write font files
-        for (idx, font_data) in self.fonts.iter().enumerate() {
-            let path = &self.manifest.fonts[idx].entry.path;
+        for (path, data) in resource_files {
             zip.start_file(path, options)?;
-            zip.write_all(font_data.data())?;
+            zip.write_all(data.data())?;
         }
 
         zip.finish()?;
         Ok(())
     }
 
-    /// Deserialize an archive from a zip file.
+    /// Deserialize an archive from a zip file, with the default [`ArchiveLimits`].
     pub fn deserialize<R: Read + Seek>(reader: R) -> Result<Self, ArchiveError> {
-        let mut zip = ZipArchive::new(reader)?;
-
-        // Read resources.json
-        let manifest: ResourceManifest = {
-            let mut file = zip.by_name("resources.json")?;
-            let mut contents = String::new();
-            file.read_to_string(&mut contents)?;
-            serde_json::from_str(&contents)?
-        };
+        Self::deserialize_with_limits(reader, &ArchiveLimits::default())
+    }
 
-        // Check version
-        if manifest.version != ResourceManifest::CURRENT_VERSION {
-            return Err(ArchiveError::UnsupportedVersion(manifest.version));
-        }
+    /// Deserialize an archive from a zip file, rejecting archives that exceed `limits`.
+    pub fn deserialize_with_limits<R: Read + Seek>(
+        reader: R,
+        limits: &ArchiveLimits,
+    ) -> Result<Self, ArchiveError> {
+        let mut zip = ZipArchive::new(reader)?;
 
-        // Read draw_commands.json
-        let commands: Vec<SerializableRenderCommand> = {
-            let mut file = zip.by_name("draw_commands.json")?;
-            let mut contents = String::new();
-            file.read_to_string(&mut contents)?;
-            serde_json::from_str(&contents)?
+        let (manifest, commands) = read_manifest(&mut zip, limits)?;
+        let commands = match commands {
+            Some(commands) => commands,
+            None => {
+                let commands = read_commands(
+                    &mut zip,
+                    manifest.command_encoding.path(),
+                    manifest.command_encoding,
+                    limits,
+                )?;
+                limits.check_commands(&commands, manifest.images.len(), manifest.fonts.len())?;
+                commands
+            }
         };
 
         // Read images
-        let mut images = Vec::with_capacity(manifest.images.len());
-        for meta in &manifest.images {
-            let mut file = zip.by_name(&meta.entry.path)?;
-            let mut png_data = Vec::new();
-            file.read_to_end(&mut png_data)?;
-            let rgba_data = decode_png_to_rgba(&png_data)?;
-
-            // Verify hash
-            let hash = sha256_hex(&rgba_data);
-            if hash != meta.entry.sha256_hash {
-                return Err(ArchiveError::InvalidFormat(format!(
-                    "Hash mismatch for {}: expected {}, got {}",
-                    meta.entry.path, meta.entry.sha256_hash, hash
-                )));
-            }
-
-            images.push(ImageData {
-                data: Blob::from(rgba_data),
-                format: ImageFormat::Rgba8,
-                alpha_type: meta.alpha_type,
-                width: meta.width,
-                height: meta.height,
-            });
-        }
+        let (images, image_files) = manifest
+            .images
+            .iter()
+            .map(|meta| read_image(&mut zip, meta, limits))
+            .collect::<Result<(Vec<_>, Vec<_>), ArchiveError>>()?;
 
         // Read fonts (may be WOFF2-compressed or raw TTF/OTF)
-        let mut fonts: Vec<Blob<u8>> = Vec::with_capacity(manifest.fonts.len());
-        for meta in &manifest.fonts {
-            let mut file = zip.by_name(&meta.entry.path)?;
-            let mut raw_data = Vec::new();
-            file.read_to_end(&mut raw_data)?;
-
-            // Verify hash
-            let hash = sha256_hex(&raw_data);
-            if hash != meta.entry.sha256_hash {
-                return Err(ArchiveError::InvalidFormat(format!(
-                    "Hash mismatch for {}: expected {}, got {}",
-                    meta.entry.path, meta.entry.sha256_hash, hash
-                )));
-            }
-            fonts.push(Blob::from(raw_data));
-        }
+        let fonts = manifest
+            .fonts
+            .iter()
+            .map(|meta| read_font(&mut zip, meta, limits))
+            .collect::<Result<Vec<_>, ArchiveError>>()?;
 
         Ok(Self {
             manifest,
             commands,
             fonts,
             images,
+            image_files,
         })
     }
 }
@@ -622,7 +945,13 @@ impl SceneArchive {
 #[derive(Clone, Debug, Default)]
 pub struct SerializeConfig {
     subset_fonts: bool,
+    instance_variable_fonts: bool,
     woff2_fonts: bool,
+    command_encoding: CommandEncoding,
+    image_policy: ImagePolicy,
+    max_image_size: Option<u32>,
+    /// Original encoded bytes of images, keyed by Blob ID
+    encoded_images: HashMap<u64, EncodedImage>,
 }
 
 impl SerializeConfig {
@@ -636,23 +965,99 @@ impl SerializeConfig {
         self
     }
 
+    /// Instance variable fonts at the locations they are used at, storing a static font for each
+    /// location and removing the normalized coordinates from glyph runs. Only TrueType-flavored
+    /// variable fonts are instanced, and hinting instructions are not preserved.
+    ///
+    /// Has no effect unless font subsetting is enabled.
+    pub fn with_instance_variable_fonts(mut self, instance_variable_fonts: bool) -> Self {
+        self.instance_variable_fonts = instance_variable_fonts;
+        self
+    }
+
     /// WOFF2-compress font data.
     pub fn with_woff2_fonts(mut self, woff2_fonts: bool) -> Self {
         self.woff2_fonts = woff2_fonts;
         self
     }
+
+    /// Set how draw commands are encoded. Defaults to [`CommandEncoding::Json`].
+    pub fn with_command_encoding(mut self, command_encoding: CommandEncoding) -> Self {
+        self.command_encoding = command_encoding;
+        self
+    }
+
+    /// Set how images are encoded. Defaults to [`ImagePolicy::Png`].
+    pub fn with_image_policy(mut self, image_policy: ImagePolicy) -> Self {
+        self.image_policy = image_policy;
+        self
+    }
+
+    /// Downsample images whose width or height exceeds `max_image_size` pixels.
+    ///
+    /// Images used as the brush of a glyph run are stored at full size, as glyph runs have no brush transform that
+    /// could scale a smaller image to cover the same area.
+    pub fn with_max_image_size(mut self, max_image_size: Option<u32>) -> Self {
+        self.max_image_size = max_image_size;
+        self
+    }
+
+    /// Supply the original encoded bytes of `image` (for example the JPEG file it was loaded from),
+    /// which are stored instead of re-encoding the image when using [`ImagePolicy::Original`].
+    pub fn with_encoded_image(
+        mut self,
+        image: &ImageData,
+        data: impl Into<Blob<u8>>,
+        encoding: ImageEncoding,
+    ) -> Self {
+        self.encoded_images.insert(
+            image.data.id(),
+            EncodedImage {
+                data: data.into(),
+                encoding,
+            },
+        );
+        self
+    }
 }
 
 #[derive(Debug)]
 pub enum ArchiveError {
     Io(std::io::Error),
     Json(serde_json::Error),
+    Binary(postcard::Error),
     Zip(zip::result::ZipError),
     Image(image::ImageError),
     FontProcessing(String),
     InvalidFormat(String),
     ResourceNotFound(ResourceId),
     UnsupportedVersion(u32),
+    /// The total decompressed size of the archive exceeds [`ArchiveLimits`].
+    ArchiveTooLarge {
+        size: u64,
+        limit: u64,
+    },
+    /// The decompressed size of a file in the archive exceeds [`ArchiveLimits`].
+    FileTooLarge {
+        path: String,
+        size: u64,
+        limit: u64,
+    },
+    /// The dimensions of an image exceed [`ArchiveLimits`].
+    ImageTooLarge {
+        width: u32,
+        height: u32,
+    },
+    /// The number of draw commands exceeds [`ArchiveLimits`].
+    TooManyCommands {
+        count: usize,
+        limit: usize,
+    },
+    /// The number of resources exceeds [`ArchiveLimits`].
+    TooManyResources {
+        count: usize,
+        limit: usize,
+    },
 }
 
 impl std::fmt::Display for ArchiveError {
@@ -660,12 +1065,32 @@ impl std::fmt::Display for ArchiveError {
         match self {
             ArchiveError::Io(e) => write!(f, "IO error: {}", e),
             ArchiveError::Json(e) => write!(f, "JSON error: {}", e),
+            ArchiveError::Binary(e) => write!(f, "Binary encoding error: {}", e),
             ArchiveError::Zip(e) => write!(f, "Zip error: {}", e),
             ArchiveError::Image(e) => write!(f, "Image error: {}", e),
             ArchiveError::FontProcessing(msg) => write!(f, "Font processing error: {}", msg),
             ArchiveError::InvalidFormat(msg) => write!(f, "Invalid format: {}", msg),
             ArchiveError::ResourceNotFound(id) => write!(f, "Resource not found: {:?}", id),
             ArchiveError::UnsupportedVersion(v) => write!(f, "Unsupported version: {}", v),
+            ArchiveError::ArchiveTooLarge { size, limit } => write!(
+                f,
+                "Archive is too large: {} bytes decompressed, limit is {}",
+                size, limit
+            ),
+            ArchiveError::FileTooLarge { path, size, limit } => write!(
+                f,
+                "File is too large: {} is {} bytes decompressed, limit is {}",
+                path, size, limit
+            ),
+            ArchiveError::ImageTooLarge { width, height } => {
+                write!(f, "Image is too large: {}x{}", width, height)
+            }
+            ArchiveError::TooManyCommands { count, limit } => {
+                write!(f, "Too many draw commands: {}, limit is {}", count, limit)
+            }
+            ArchiveError::TooManyResources { count, limit } => {
+                write!(f, "Too many resources: {}, limit is {}", count, limit)
+            }
         }
     }
 }
@@ -675,6 +1100,7 @@ impl std::error::Error for ArchiveError {
         match self {
             ArchiveError::Io(e) => Some(e),
             ArchiveError::Json(e) => Some(e),
+            ArchiveError::Binary(e) => Some(e),
             ArchiveError::Zip(e) => Some(e),
             ArchiveError::Image(e) => Some(e),
             _ => None,
@@ -694,6 +1120,12 @@ impl From<serde_json::Error> for ArchiveError {
     }
 }
 
+impl From<postcard::Error> for ArchiveError {
+    fn from(e: postcard::Error) -> Self {
+        ArchiveError::Binary(e)
+    }
+}
+
 impl From<zip::result::ZipError> for ArchiveError {
     fn from(e: zip::result::ZipError) -> Self {
         ArchiveError::Zip(e)
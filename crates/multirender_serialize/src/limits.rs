@@ -0,0 +1,199 @@
+//! Limits for reading archives from untrusted sources.
+
+use std::io::{Read, Seek};
+
+use zip::ZipArchive;
+
+use crate::{
+    ArchiveError, ImageMetadata, ResourceKind, SerializableRenderCommand, resource_references,
+};
+
+/// Limits applied when reading archives, protecting against archives that are malformed or crafted to exhaust
+/// memory.
+///
+/// Archives that exceed a limit are rejected with a dedicated [`ArchiveError`] before the offending data is decoded.
+/// The defaults allow large captures; use [`ArchiveLimits::unlimited`] for trusted archives that exceed them.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ArchiveLimits {
+    max_archive_size: u64,
+    max_file_size: u64,
+    max_image_dimension: u32,
+    max_image_pixels: u64,
+    max_commands: usize,
+    max_resources: usize,
+}
+
+impl Default for ArchiveLimits {
+    fn default() -> Self {
+        Self {
+            max_archive_size: 1 << 30,
+            max_file_size: 256 << 20,
+            max_image_dimension: 16384,
+            max_image_pixels: 8192 * 8192,
+            max_commands: 10_000_000,
+            max_resources: 65536,
+        }
+    }
+}
+
+impl ArchiveLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits that accept any archive.
+    pub fn unlimited() -> Self {
+        Self {
+            max_archive_size: u64::MAX,
+            max_file_size: u64::MAX,
+            max_image_dimension: u32::MAX,
+            max_image_pixels: u64::MAX,
+            max_commands: usize::MAX,
+            max_resources: usize::MAX,
+        }
+    }
+
+    /// Set the maximum total decompressed size of the files in an archive, in bytes. Defaults to 1 GiB.
+    pub fn with_max_archive_size(mut self, max_archive_size: u64) -> Self {
+        self.max_archive_size = max_archive_size;
+        self
+    }
+
+    /// Set the maximum decompressed size of a single file in an archive, in bytes. This includes fonts after WOFF2
+    /// decompression. Defaults to 256 MiB.
+    pub fn with_max_file_size(mut self, max_file_size: u64) -> Self {
+        self.max_file_size = max_file_size;
+        self
+    }
+
+    /// Set the maximum width and height of an image, in pixels. Defaults to 16384.
+    pub fn with_max_image_dimension(mut self, max_image_dimension: u32) -> Self {
+        self.max_image_dimension = max_image_dimension;
+        self
+    }
+
+    /// Set the maximum number of pixels in an image. Defaults to 8192 × 8192.
+    pub fn with_max_image_pixels(mut self, max_image_pixels: u64) -> Self {
+        self.max_image_pixels = max_image_pixels;
+        self
+    }
+
+    /// Set the maximum number of draw commands in a scene or frame. Defaults to 10 million.
+    pub fn with_max_commands(mut self, max_commands: usize) -> Self {
+        self.max_commands = max_commands;
+        self
+    }
+
+    /// Set the maximum number of images and fonts in an archive. Defaults to 65536.
+    pub fn with_max_resources(mut self, max_resources: usize) -> Self {
+        self.max_resources = max_resources;
+        self
+    }
+
+    /// Check the total decompressed size of an archive, as recorded in its central directory.
+    pub(crate) fn check_archive<R: Read + Seek>(
+        &self,
+        zip: &mut ZipArchive<R>,
+    ) -> Result<(), ArchiveError> {
+        let mut size: u64 = 0;
+        for index in 0..zip.len() {
+            size = size.saturating_add(zip.by_index_raw(index)?.size());
+        }
+        if size > self.max_archive_size {
+            return Err(ArchiveError::ArchiveTooLarge {
+                size,
+                limit: self.max_archive_size,
+            });
+        }
+        Ok(())
+    }
+
+    /// Read a file from an archive. Files are never read past the size recorded in the archive, which is checked
+    /// against the file size limit before reading.
+    pub(crate) fn read_file<R: Read + Seek>(
+        &self,
+        zip: &mut ZipArchive<R>,
+        path: &str,
+    ) -> Result<Vec<u8>, ArchiveError> {
+        let file = zip.by_name(path)?;
+        let size = file.size();
+        self.check_file_size(path, size)?;
+
+        let mut contents = Vec::new();
+        file.take(size.saturating_add(1))
+            .read_to_end(&mut contents)?;
+        if contents.len() as u64 > size {
+            return Err(ArchiveError::InvalidFormat(format!(
+                "{path} is larger than recorded in the archive"
+            )));
+        }
+        Ok(contents)
+    }
+
+    pub(crate) fn check_file_size(&self, path: &str, size: u64) -> Result<(), ArchiveError> {
+        if size > self.max_file_size {
+            return Err(ArchiveError::FileTooLarge {
+                path: path.to_string(),
+                size,
+                limit: self.max_file_size,
+            });
+        }
+        Ok(())
+    }
+
+    /// Check the number of resources in an archive and the size of its images.
+    pub(crate) fn check_resources(
+        &self,
+        images: &[ImageMetadata],
+        font_count: usize,
+    ) -> Result<(), ArchiveError> {
+        let count = images.len().saturating_add(font_count);
+        if count > self.max_resources {
+            return Err(ArchiveError::TooManyResources {
+                count,
+                limit: self.max_resources,
+            });
+        }
+        for meta in images {
+            self.check_image(meta.width, meta.height)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_image(&self, width: u32, height: u32) -> Result<(), ArchiveError> {
+        if width > self.max_image_dimension
+            || height > self.max_image_dimension
+            || width as u64 * height as u64 > self.max_image_pixels
+        {
+            return Err(ArchiveError::ImageTooLarge { width, height });
+        }
+        Ok(())
+    }
+
+    /// Check the number of draw commands, and that every resource they reference exists.
+    pub(crate) fn check_commands(
+        &self,
+        commands: &[SerializableRenderCommand],
+        image_count: usize,
+        font_count: usize,
+    ) -> Result<(), ArchiveError> {
+        if commands.len() > self.max_commands {
+            return Err(ArchiveError::TooManyCommands {
+                count: commands.len(),
+                limit: self.max_commands,
+            });
+        }
+        for cmd in commands {
+            for (kind, id) in resource_references(cmd) {
+                let count = match kind {
+                    ResourceKind::Image => image_count,
+                    ResourceKind::Font => font_count,
+                };
+                if id.0 >= count {
+                    return Err(ArchiveError::ResourceNotFound(id));
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+//! Upgrading archives written with older versions of the archive format.
+//!
+//! Each [`Migration`] upgrades the JSON representation of an archive's manifest and draw commands by a single
+//! version. Archives are upgraded by applying every migration from their version up to
+//! [`ResourceManifest::CURRENT_VERSION`] in order, so a migration only ever needs to know about the version directly
+//! before it.
+//!
+//! When the format changes, bump [`ResourceManifest::CURRENT_VERSION`], add a migration from the previous version to
+//! [`MIGRATIONS`] and add a fixture archive written with the previous version to the tests.
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{ArchiveError, CommandEncoding, ResourceManifest};
+
+/// Upgrades an archive from `from_version` to `from_version + 1`.
+///
+/// `commands` is `None` for archives with binary-encoded draw commands, which can only be upgraded by migrations
+/// that leave the command layout unchanged.
+struct Migration {
+    from_version: u32,
+    migrate: fn(manifest: &mut Value, commands: Option<&mut Value>) -> Result<(), ArchiveError>,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from_version: 1,
+        migrate: v1_to_v2,
+    },
+    Migration {
+        from_version: 2,
+        migrate: v2_to_v3,
+    },
+];
+
+/// Version 2 records the encoding of the draw commands. Version 1 archives always use JSON.
+fn v1_to_v2(manifest: &mut Value, _commands: Option<&mut Value>) -> Result<(), ArchiveError> {
+    manifest_object(manifest)?.insert("command_encoding".into(), "json".into());
+    Ok(())
+}
+
+/// Version 3 records the encoding of each image. Version 2 archives always store images as PNG.
+fn v2_to_v3(manifest: &mut Value, _commands: Option<&mut Value>) -> Result<(), ArchiveError> {
+    let images = manifest_object(manifest)?
+        .get_mut("images")
+        .and_then(Value::as_array_mut)
+        .ok_or_else(|| ArchiveError::InvalidFormat("Manifest has no images".to_string()))?;
+    for image in images {
+        let image = image.as_object_mut().ok_or_else(|| {
+            ArchiveError::InvalidFormat("Image metadata is not an object".to_string())
+        })?;
+        image.insert("encoding".into(), "png".into());
+    }
+    Ok(())
+}
+
+fn manifest_object(
+    manifest: &mut Value,
+) -> Result<&mut serde_json::Map<String, Value>, ArchiveError> {
+    manifest
+        .as_object_mut()
+        .ok_or_else(|| ArchiveError::InvalidFormat("Manifest is not an object".to_string()))
+}
+
+/// Read the encoding of the draw commands from a manifest. Archives written before version 2 always use JSON.
+pub(crate) fn command_encoding(manifest: &Value) -> Result<CommandEncoding, ArchiveError> {
+    match manifest.get("command_encoding") {
+        Some(encoding) => Ok(CommandEncoding::deserialize(encoding)?),
+        None => Ok(CommandEncoding::Json),
+    }
+}
+
+/// Read the format version of a manifest.
+pub(crate) fn manifest_version(manifest: &Value) -> Result<u32, ArchiveError> {
+    manifest
+        .get("version")
+        .and_then(Value::as_u64)
+        .and_then(|version| u32::try_from(version).ok())
+        .ok_or_else(|| ArchiveError::InvalidFormat("Manifest has no valid version".to_string()))
+}
+
+/// Upgrade a manifest and its draw commands to the current version.
+pub(crate) fn migrate(
+    manifest: &mut Value,
+    mut commands: Option<&mut Value>,
+) -> Result<(), ArchiveError> {
+    loop {
+        let version = manifest_version(manifest)?;
+        if version == ResourceManifest::CURRENT_VERSION {
+            return Ok(());
+        }
+
+        let migration = MIGRATIONS
+            .iter()
+            .find(|migration| migration.from_version == version)
+            .ok_or(ArchiveError::UnsupportedVersion(version))?;
+        (migration.migrate)(manifest, commands.as_deref_mut())?;
+        manifest_object(manifest)?.insert("version".into(), (version + 1).into());
+    }
+}
@@ -0,0 +1,136 @@
+//! Lazy reading of scene archives.
+
+use std::io::{Read, Seek};
+
+use peniko::{FontData, ImageData};
+use zip::ZipArchive;
+
+use multirender::recording::Scene;
+
+use crate::{
+    ArchiveError, ArchiveLimits, ResourceId, ResourceKind, ResourceManifest, ResourceReconstructor,
+    SerializableRenderCommand, read_commands, read_manifest,
+};
+
+/// Reads a scene archive on demand.
+///
+/// Unlike [`SceneArchive::deserialize`](crate::SceneArchive::deserialize), which decodes every resource up front,
+/// only the manifest is read when the archive is opened. Draw commands are read the first time they are requested,
+/// and images and fonts are decoded when they are requested (or when [`to_scene`](Self::to_scene) needs them),
+/// verifying their hashes, and cached.
+///
+/// The archive is checked against [`ArchiveLimits`] as it is read.
+pub struct SceneArchiveReader<R: Read + Seek> {
+    zip: ZipArchive<R>,
+    limits: ArchiveLimits,
+    manifest: ResourceManifest,
+    commands: Option<Vec<SerializableRenderCommand>>,
+    resources: ResourceReconstructor,
+}
+
+impl<R: Read + Seek> SceneArchiveReader<R> {
+    /// Open an archive with the default [`ArchiveLimits`].
+    pub fn new(reader: R) -> Result<Self, ArchiveError> {
+        Self::with_limits(reader, ArchiveLimits::default())
+    }
+
+    /// Open an archive, rejecting archives that exceed `limits`.
+    pub fn with_limits(reader: R, limits: ArchiveLimits) -> Result<Self, ArchiveError> {
+        let mut zip = ZipArchive::new(reader)?;
+        // Archives with JSON-encoded commands written with older versions of the format have their commands
+        // upgraded along with the manifest, so those are read here as well.
+        let (manifest, commands) = read_manifest(&mut zip, &limits)?;
+
+        let resources = ResourceReconstructor::new(
+            vec![None; manifest.fonts.len()],
+            vec![None; manifest.images.len()],
+        );
+        Ok(Self {
+            zip,
+            limits,
+            manifest,
+            commands,
+            resources,
+        })
+    }
+
+    pub fn manifest(&self) -> &ResourceManifest {
+        &self.manifest
+    }
+
+    /// The draw commands of the archive, which reference resources by ID.
+    pub fn commands(&mut self) -> Result<&[SerializableRenderCommand], ArchiveError> {
+        let commands = match self.commands.take() {
+            Some(commands) => commands,
+            None => {
+                let commands = read_commands(
+                    &mut self.zip,
+                    self.manifest.command_encoding.path(),
+                    self.manifest.command_encoding,
+                    &self.limits,
+                )?;
+                self.limits.check_commands(
+                    &commands,
+                    self.manifest.images.len(),
+                    self.manifest.fonts.len(),
+                )?;
+                commands
+            }
+        };
+        Ok(self.commands.insert(commands))
+    }
+
+    /// The image with the given ID, in the format it was recorded in.
+    pub fn image(&mut self, id: ResourceId) -> Result<ImageData, ArchiveError> {
+        self.load(ResourceKind::Image, id)?;
+        self.resources.get_image(id).cloned()
+    }
+
+    /// The font with the given ID, decompressed if it is stored as WOFF2.
+    pub fn font(&mut self, id: ResourceId) -> Result<FontData, ArchiveError> {
+        self.load(ResourceKind::Font, id)?;
+        self.resources.get_font(id).cloned()
+    }
+
+    /// Decode every resource in the archive, verifying their hashes.
+    pub fn load_resources(&mut self) -> Result<(), ArchiveError> {
+        for index in 0..self.manifest.images.len() {
+            self.load(ResourceKind::Image, ResourceId(index))?;
+        }
+        for index in 0..self.manifest.fonts.len() {
+            self.load(ResourceKind::Font, ResourceId(index))?;
+        }
+        Ok(())
+    }
+
+    /// Convert the archive to a Scene, decoding only the resources that are referenced by its draw commands.
+    pub fn to_scene(&mut self) -> Result<Scene, ArchiveError> {
+        self.commands()?;
+        let mut commands = self.commands.take().unwrap_or_default();
+        let result = self.resources.load_and_convert(
+            &mut self.zip,
+            &self.manifest.images,
+            &self.manifest.fonts,
+            &self.limits,
+            &mut commands,
+        );
+        self.commands = Some(commands);
+
+        Ok(Scene {
+            tolerance: self.manifest.tolerance,
+            commands: result?,
+        })
+    }
+
+    /// Decode a resource if it has not been decoded yet
+    fn load(&mut self, kind: ResourceKind, id: ResourceId) -> Result<(), ArchiveError> {
+        self.resources.load(
+            &mut self.zip,
+            &self.manifest.images,
+            &self.manifest.fonts,
+            &self.limits,
+            kind,
+            id,
+        )
+    }
+}
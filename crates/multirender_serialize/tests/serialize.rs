@@ -1,19 +1,28 @@
 //! Integration tests for scene serialization.
 
-use std::io::{Cursor, Read};
+use std::io::{Cursor, Read, Write};
+use std::sync::Arc;
+use std::time::Duration;
 
-use kurbo::{Affine, Rect, Stroke};
+use kurbo::{Affine, BezPath, Circle, Rect, Shape, Stroke};
 use multirender::recording::{RenderCommand, Scene};
-use multirender::{Glyph, PaintScene};
+use multirender::{FrameStats, Glyph, PaintScene, WindowHandle, WindowRenderer};
 use multirender_serialize::{
-    ArchiveError, ResourceManifest, SceneArchive, SerializableRenderCommand, SerializeConfig,
+    ArchiveError, ArchiveLimits, CapturingWindowRenderer, CommandEncoding, FrameArchiveReader,
+    FrameArchiveWriter, ImageEncoding, ImagePolicy, ResourceId, ResourceManifest, SceneArchive,
+    SceneArchiveReader, SerializableRenderCommand, SerializeConfig,
 };
 use peniko::{
-    Blob, Brush, Color, Compose, Fill, FontData, ImageAlphaType, ImageBrush, ImageData,
+    Blob, Brush, Color, Compose, Fill, FontData, Gradient, ImageAlphaType, ImageBrush, ImageData,
     ImageFormat, Mix,
 };
 use read_fonts::TableProvider;
-use zip::ZipArchive;
+use read_fonts::types::{F2Dot14, GlyphId};
+use skrifa::MetadataProvider;
+use skrifa::instance::{LocationRef, Size};
+use skrifa::outline::{DrawSettings, OutlinePen};
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
 
 #[test]
 fn test_empty_scene_roundtrip() {
@@ -23,6 +32,178 @@ fn test_empty_scene_roundtrip() {
 /// Tests that all non-image and non-font command types survive a roundtrip.
 #[test]
 fn test_all_command_types_roundtrip() {
+    assert_scene_roundtrip(&build_all_commands_scene());
+}
+
+#[test]
+fn test_binary_encoding_roundtrip() {
+    let mut scene = build_all_commands_scene();
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        &Gradient::new_linear((0.0, 0.0), (100.0, 0.0)).with_stops([Color::BLACK, Color::WHITE]),
+        None,
+        &Rect::new(0.0, 0.0, 100.0, 100.0),
+    );
+    let image_brush = ImageBrush::new(make_1x1_image(10, 20, 30, 255));
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        image_brush.as_ref(),
+        None,
+        &Rect::new(0.0, 0.0, 1.0, 1.0),
+    );
+    scene.append_scene(build_glyph_scene(&roboto_font()), Affine::IDENTITY);
+
+    let data = serialize_to_vec(&scene, &binary_config()).unwrap();
+    let restored = deserialize_from_slice(&data).unwrap();
+
+    // Image and font blobs get new IDs when they are reconstructed, so those commands are compared separately
+    let count = scene.commands.len();
+    assert_eq!(restored.commands.len(), count);
+    assert_eq!(scene.commands[..count - 2], restored.commands[..count - 2]);
+    assert_eq!(
+        extract_image_pixels(&restored, count - 2),
+        vec![10, 20, 30, 255]
+    );
+    assert_glyph_run_preserved(&Scene {
+        tolerance: restored.tolerance,
+        commands: restored.commands[count - 1..].to_vec(),
+    });
+}
+
+/// Path coordinates that have no short decimal representation must survive the binary encoding exactly.
+#[test]
+fn test_binary_encoding_preserves_path_coordinates() {
+    let mut scene = Scene::new();
+    let circle = Circle::new((1.0 / 3.0, std::f64::consts::PI), 1e-7 + 0.1);
+    scene.fill(
+        Fill::NonZero,
+        Affine::rotate(0.1),
+        Color::BLACK,
+        None,
+        &circle,
+    );
+
+    let data = serialize_to_vec(&scene, &binary_config()).unwrap();
+    let restored = deserialize_from_slice(&data).unwrap();
+    let (RenderCommand::Fill(original), RenderCommand::Fill(restored)) =
+        (&scene.commands[0], &restored.commands[0])
+    else {
+        panic!("Expected Fill commands");
+    };
+    assert_eq!(original.shape.elements(), restored.shape.elements());
+}
+
+/// A binary path whose first verb isn't a move must be rejected rather than panic when it is rebuilt.
+#[test]
+fn test_binary_path_must_begin_with_move() {
+    let mut path = BezPath::new();
+    path.move_to((1234.5, 0.0));
+    path.line_to((0.0, 1234.5));
+    let mut scene = Scene::new();
+    scene.fill(Fill::NonZero, Affine::IDENTITY, Color::BLACK, None, &path);
+    let data = serialize_to_vec(&scene, &binary_config()).unwrap();
+
+    // Replace the move with a line in the verbs [MOVE_TO, LINE_TO] followed by the four coordinates
+    let mut zip = ZipArchive::new(Cursor::new(&data)).unwrap();
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    for index in 0..zip.len() {
+        let mut file = zip.by_index(index).unwrap();
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents).unwrap();
+        if file.name() == "draw_commands.bin" {
+            let encoded = [&[2, 0, 1, 4][..], &1234.5f64.to_le_bytes()].concat();
+            let start = contents
+                .windows(encoded.len())
+                .position(|window| window == encoded)
+                .expect("encoded path not found");
+            contents[start + 1] = 1;
+        }
+        writer
+            .start_file(file.name(), SimpleFileOptions::default())
+            .unwrap();
+        writer.write_all(&contents).unwrap();
+    }
+    let tampered = writer.finish().unwrap().into_inner();
+
+    assert!(matches!(
+        archive_deserialize_from_slice(&tampered),
+        Err(ArchiveError::Binary(_))
+    ));
+}
+
+#[test]
+fn test_deterministic_serialization_is_byte_identical() {
+    for config in [default_config(), binary_config(), subset_and_woff2_config()] {
+        // Build the scene from scratch for every run, so that its resources have new blob IDs
+        let serialize = || {
+            let mut scene = build_glyph_scene(&roboto_font());
+            scene
+                .commands
+                .extend(build_image_scene(&make_gradient_image(8, 4, 255)).commands);
+            scene
+                .commands
+                .extend(build_image_scene(&make_1x1_image(0, 0, 255, 255)).commands);
+            scene.commands.extend(build_all_commands_scene().commands);
+
+            let mut buf = Cursor::new(Vec::new());
+            SceneArchive::from_scene(&scene, &config)
+                .unwrap()
+                .serialize_deterministic(&mut buf)
+                .unwrap();
+            buf.into_inner()
+        };
+
+        let first = serialize();
+        assert_eq!(first, serialize());
+        assert_eq!(deserialize_from_slice(&first).unwrap().commands.len(), 15);
+    }
+}
+
+#[test]
+fn test_deterministic_serialization_entries() {
+    let mut scene = build_glyph_scene(&roboto_font());
+    scene
+        .commands
+        .extend(build_image_scene(&make_gradient_image(8, 4, 255)).commands);
+    scene
+        .commands
+        .extend(build_image_scene(&make_1x1_image(0, 0, 255, 255)).commands);
+
+    let mut buf = Cursor::new(Vec::new());
+    SceneArchive::from_scene(&scene, &default_config())
+        .unwrap()
+        .serialize_deterministic(&mut buf)
+        .unwrap();
+    let mut zip = ZipArchive::new(Cursor::new(buf.into_inner())).unwrap();
+
+    // The manifest and draw commands come first, followed by resources sorted by path
+    let names: Vec<String> = zip.file_names().map(str::to_string).collect();
+    let (head, resources) = names.split_at(2);
+    assert_eq!(head, ["resources.json", "draw_commands.json"]);
+    assert_eq!(resources.len(), 3);
+    assert!(resources.is_sorted());
+
+    for index in 0..zip.len() {
+        let file = zip.by_index(index).unwrap();
+        assert_eq!(file.last_modified(), Some(zip::DateTime::default()));
+        assert_eq!(file.compression(), zip::CompressionMethod::Deflated);
+    }
+}
+
+#[test]
+fn test_binary_encoding_is_recorded_in_manifest() {
+    let data = serialize_to_vec(&build_all_commands_scene(), &binary_config()).unwrap();
+    let archive = archive_deserialize_from_slice(&data).unwrap();
+    assert_eq!(archive.manifest.command_encoding, CommandEncoding::Binary);
+
+    let mut zip = ZipArchive::new(Cursor::new(&data)).unwrap();
+    assert!(zip.by_name("draw_commands.bin").is_ok());
+    assert!(zip.by_name("draw_commands.json").is_err());
+}
+
+fn build_all_commands_scene() -> Scene {
     let mut scene = Scene::new();
 
     // Layer with blend mode
@@ -98,7 +279,7 @@ fn test_all_command_types_roundtrip() {
 
     scene.pop_layer();
 
-    assert_scene_roundtrip(&scene);
+    scene
 }
 
 #[test]
@@ -271,6 +452,97 @@ fn test_glyph_run_roundtrip_with_subsetting_and_woff2() {
     assert_glyph_run_preserved(&restored);
 }
 
+/// Normalized coordinates of Roboto Flex at its maximum weight
+const ROBOTO_FLEX_BOLD: &[i16] = &[1 << 14];
+
+#[test]
+fn test_variable_font_instancing() {
+    let font = roboto_flex_font();
+    let scene = build_variable_glyph_scene(&font, &[&[], ROBOTO_FLEX_BOLD, &[1 << 14, 0, 0], &[0]]);
+    let config = subset_and_woff2_config().with_instance_variable_fonts(true);
+    let archive =
+        archive_deserialize_from_slice(&serialize_to_vec(&scene, &config).unwrap()).unwrap();
+
+    // Locations that only differ by trailing default coordinates share an instance
+    assert_eq!(archive.manifest.fonts.len(), 2);
+    let font_ids: Vec<usize> = archive
+        .commands
+        .iter()
+        .map(|cmd| match cmd {
+            SerializableRenderCommand::GlyphRun(glyph_run) => {
+                assert!(glyph_run.normalized_coords.is_empty());
+                glyph_run.font_data.resource_id.0
+            }
+            other => panic!("Expected GlyphRun command, got {other:?}"),
+        })
+        .collect();
+    assert_eq!(font_ids, vec![0, 1, 1, 0]);
+
+    // The instances are static fonts with the outlines of the variable font at each location
+    let original = read_fonts::FontRef::new(font.data.data()).unwrap();
+    for (font_blob, coords) in archive.fonts.iter().zip([&[][..], ROBOTO_FLEX_BOLD]) {
+        let ttf_data = wuff::decompress_woff2(font_blob.data()).unwrap();
+        let instance = read_fonts::FontRef::new(&ttf_data).unwrap();
+        for tag in [b"fvar", b"gvar", b"HVAR"] {
+            assert!(
+                instance
+                    .table_data(read_fonts::types::Tag::new(tag))
+                    .is_none()
+            );
+        }
+        for glyph in hello_glyphs(&font) {
+            let expected = outline_points(&original, glyph.id, coords);
+            let actual = outline_points(&instance, glyph.id, &[]);
+            assert_eq!(actual.len(), expected.len(), "glyph {}", glyph.id);
+            for (a, e) in actual.iter().zip(&expected) {
+                assert!(
+                    (a.0 - e.0).abs() <= 1.0 && (a.1 - e.1).abs() <= 1.0,
+                    "glyph {}: {a:?} != {e:?}",
+                    glyph.id
+                );
+            }
+        }
+    }
+    assert_ne!(
+        outline_points(&original, hello_glyphs(&font)[0].id, &[]),
+        outline_points(&original, hello_glyphs(&font)[0].id, ROBOTO_FLEX_BOLD),
+    );
+
+    // Dropping the variation data makes the fonts a lot smaller
+    let variable = archive_deserialize_from_slice(
+        &serialize_to_vec(&scene, &subset_and_woff2_config()).unwrap(),
+    )
+    .unwrap();
+    assert!(
+        archive.manifest.fonts[1].entry.size * 2 < variable.manifest.fonts[0].entry.size,
+        "Instanced font ({} bytes) should be much smaller than the variable font ({} bytes)",
+        archive.manifest.fonts[1].entry.size,
+        variable.manifest.fonts[0].entry.size
+    );
+
+    let restored = archive.to_scene().unwrap();
+    assert_eq!(restored.commands.len(), 4);
+}
+
+#[test]
+fn test_variable_fonts_are_not_instanced_by_default() {
+    let font = roboto_flex_font();
+    let scene = build_variable_glyph_scene(&font, &[&[], ROBOTO_FLEX_BOLD]);
+    let archive = archive_deserialize_from_slice(
+        &serialize_to_vec(&scene, &subset_and_woff2_config()).unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(archive.manifest.fonts.len(), 1);
+    let restored = archive.to_scene().unwrap();
+    let RenderCommand::GlyphRun(glyph_run) = &restored.commands[1] else {
+        panic!("Expected GlyphRun command");
+    };
+    assert_eq!(glyph_run.normalized_coords, ROBOTO_FLEX_BOLD);
+    let font_ref = read_fonts::FontRef::new(glyph_run.font_data.data.data()).unwrap();
+    assert!(font_ref.fvar().is_ok());
+}
+
 #[test]
 fn test_font_deduplication() {
     let font = roboto_font();
@@ -304,7 +576,7 @@ fn test_font_deduplication() {
 
 #[test]
 fn test_resource_manifest_version() {
-    assert_eq!(ResourceManifest::CURRENT_VERSION, 1);
+    assert_eq!(ResourceManifest::CURRENT_VERSION, 3);
 }
 
 #[test]
@@ -328,7 +600,8 @@ fn test_archive_contains_expected_files() {
         .read_to_string(&mut resources_json)
         .unwrap();
     let manifest: ResourceManifest = serde_json::from_str(&resources_json).unwrap();
-    assert_eq!(manifest.version, 1);
+    assert_eq!(manifest.version, ResourceManifest::CURRENT_VERSION);
+    assert_eq!(manifest.command_encoding, CommandEncoding::Json);
     assert!(manifest.images.is_empty());
     assert!(manifest.fonts.is_empty());
 
@@ -342,12 +615,784 @@ fn test_archive_contains_expected_files() {
     assert_eq!(commands.len(), 1);
 }
 
+#[test]
+fn test_jpeg_image_policy() {
+    let image = make_gradient_image(16, 8, 255);
+    let scene = build_image_scene(&image);
+    let config = SerializeConfig::new().with_image_policy(ImagePolicy::Jpeg { quality: 90 });
+
+    let data = serialize_to_vec(&scene, &config).unwrap();
+    let archive = archive_deserialize_from_slice(&data).unwrap();
+    assert_eq!(archive.manifest.images[0].encoding, ImageEncoding::Jpeg);
+    assert!(archive.manifest.images[0].entry.path.ends_with(".jpg"));
+
+    let restored = archive.to_scene().unwrap();
+    assert_pixels_close(&extract_image_pixels(&restored, 0), image.data.data(), 16);
+}
+
+#[test]
+fn test_jpeg_image_policy_keeps_transparent_images_lossless() {
+    let image = make_gradient_image(4, 4, 128);
+    let scene = build_image_scene(&image);
+    let config = SerializeConfig::new().with_image_policy(ImagePolicy::Jpeg { quality: 90 });
+
+    let data = serialize_to_vec(&scene, &config).unwrap();
+    let archive = archive_deserialize_from_slice(&data).unwrap();
+    assert_eq!(archive.manifest.images[0].encoding, ImageEncoding::Png);
+    let restored = archive.to_scene().unwrap();
+    assert_eq!(extract_image_pixels(&restored, 0), image.data.data());
+}
+
+#[test]
+fn test_webp_image_policy() {
+    let image = make_gradient_image(16, 8, 200);
+    let scene = build_image_scene(&image);
+    let config = SerializeConfig::new().with_image_policy(ImagePolicy::WebP { quality: 90.0 });
+
+    let data = serialize_to_vec(&scene, &config).unwrap();
+    let archive = archive_deserialize_from_slice(&data).unwrap();
+    assert_eq!(archive.manifest.images[0].encoding, ImageEncoding::WebP);
+    assert!(archive.manifest.images[0].entry.path.ends_with(".webp"));
+
+    let restored = archive.to_scene().unwrap();
+    // WebP subsamples chroma, so sharp color changes in small images are blurred
+    assert_pixels_close(&extract_image_pixels(&restored, 0), image.data.data(), 48);
+}
+
+#[test]
+fn test_original_image_policy_stores_supplied_bytes() {
+    // An image that the app decoded from a JPEG file
+    let source =
+        image::RgbImage::from_fn(8, 8, |x, y| image::Rgb([x as u8 * 30, y as u8 * 30, 90]));
+    let mut jpeg = Vec::new();
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, 80)
+        .encode_image(&source)
+        .unwrap();
+    let decoded = image::load_from_memory(&jpeg).unwrap().into_rgba8();
+    let image = ImageData {
+        data: Blob::from(decoded.into_raw()),
+        format: ImageFormat::Rgba8,
+        alpha_type: ImageAlphaType::Alpha,
+        width: 8,
+        height: 8,
+    };
+    let other_image = make_1x1_image(1, 2, 3, 4);
+
+    let mut scene = build_image_scene(&image);
+    scene.append_scene(build_image_scene(&other_image), Affine::IDENTITY);
+    let config = SerializeConfig::new()
+        .with_image_policy(ImagePolicy::Original)
+        .with_encoded_image(&image, jpeg.clone(), ImageEncoding::Jpeg);
+
+    let data = serialize_to_vec(&scene, &config).unwrap();
+    let archive = archive_deserialize_from_slice(&data).unwrap();
+    assert_eq!(archive.manifest.images[0].encoding, ImageEncoding::Jpeg);
+    assert_eq!(archive.image_files[0].data(), jpeg.as_slice());
+    // Images without original bytes fall back to PNG
+    assert_eq!(archive.manifest.images[1].encoding, ImageEncoding::Png);
+
+    let restored = archive.to_scene().unwrap();
+    assert_eq!(extract_image_pixels(&restored, 0), image.data.data());
+    assert_eq!(extract_image_pixels(&restored, 1), vec![1, 2, 3, 4]);
+}
+
+/// A stored PNG file is restored as the straight-alpha pixels it decodes to, even if the app's pixels are premultiplied
+#[test]
+fn test_original_image_policy_stores_png_with_partial_alpha() {
+    let source = image::RgbaImage::from_fn(4, 4, |x, y| {
+        image::Rgba([200, x as u8 * 60, 40, y as u8 * 80])
+    });
+    let mut png = Vec::new();
+    source
+        .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+        .unwrap();
+    let premultiplied: Vec<u8> = source
+        .pixels()
+        .flat_map(|&image::Rgba([r, g, b, a])| {
+            let premultiply = |c: u8| (c as u16 * a as u16 / 255) as u8;
+            [premultiply(r), premultiply(g), premultiply(b), a]
+        })
+        .collect();
+    let image = ImageData {
+        data: Blob::from(premultiplied),
+        format: ImageFormat::Rgba8,
+        alpha_type: ImageAlphaType::AlphaPremultiplied,
+        width: 4,
+        height: 4,
+    };
+
+    let config = SerializeConfig::new()
+        .with_image_policy(ImagePolicy::Original)
+        .with_encoded_image(&image, png.clone(), ImageEncoding::Png);
+    let data = serialize_to_vec(&build_image_scene(&image), &config).unwrap();
+    let archive = archive_deserialize_from_slice(&data).unwrap();
+    assert_eq!(archive.image_files[0].data(), png.as_slice());
+    assert_eq!(archive.manifest.images[0].alpha_type, ImageAlphaType::Alpha);
+
+    let restored = archive.to_scene().unwrap();
+    assert_eq!(extract_image_pixels(&restored, 0), source.into_raw());
+}
+
+#[test]
+fn test_webp_image_policy_rejects_invalid_quality() {
+    let scene = build_image_scene(&make_gradient_image(4, 4, 255));
+    for quality in [-1.0, 100.5, f32::NAN] {
+        let config = SerializeConfig::new().with_image_policy(ImagePolicy::WebP { quality });
+        assert!(matches!(
+            serialize_to_vec(&scene, &config),
+            Err(ArchiveError::InvalidFormat(_))
+        ));
+    }
+}
+
+#[test]
+fn test_max_image_size_downsamples_images() {
+    let image = make_gradient_image(16, 8, 255);
+    let scene = build_image_scene(&image);
+    let config = SerializeConfig::new().with_max_image_size(Some(4));
+
+    let data = serialize_to_vec(&scene, &config).unwrap();
+    let restored = deserialize_from_slice(&data).unwrap();
+    let RenderCommand::Fill(fill) = &restored.commands[0] else {
+        panic!("Expected Fill command");
+    };
+    let Brush::Image(image_brush) = &fill.brush else {
+        panic!("Expected image brush");
+    };
+    assert_eq!((image_brush.image.width, image_brush.image.height), (4, 2));
+    // The brush is scaled up so that the image still covers the original area
+    assert_eq!(fill.brush_transform, Some(Affine::scale(4.0)));
+    assert_eq!(fill.shape, Rect::new(0.0, 0.0, 16.0, 8.0).to_path(0.1));
+}
+
+#[test]
+fn test_max_image_size_keeps_glyph_run_images() {
+    let font = roboto_font();
+    let image = make_gradient_image(16, 8, 255);
+    let mut scene = Scene::new();
+    scene.draw_glyphs(
+        &font,
+        16.0,
+        false,
+        &[],
+        Fill::NonZero,
+        ImageBrush::new(image.clone()).as_ref(),
+        1.0,
+        Affine::IDENTITY,
+        None,
+        hello_glyphs(&font).into_iter(),
+    );
+    let config = SerializeConfig::new().with_max_image_size(Some(4));
+
+    let data = serialize_to_vec(&scene, &config).unwrap();
+    let restored = deserialize_from_slice(&data).unwrap();
+    let RenderCommand::GlyphRun(glyph_run) = &restored.commands[0] else {
+        panic!("Expected GlyphRun command");
+    };
+    let Brush::Image(image_brush) = &glyph_run.brush else {
+        panic!("Expected image brush");
+    };
+    // Glyph runs can't rescale their brush, so the image is stored at full size
+    assert_eq!((image_brush.image.width, image_brush.image.height), (16, 8));
+}
+
+/// Archives written with version 1 of the format, before the command encoding was recorded in the manifest.
+#[test]
+fn test_v1_archive_migration() {
+    let data = include_bytes!("fixtures/v1_shapes.multirender.zip");
+    let archive = archive_deserialize_from_slice(data).unwrap();
+    assert_eq!(archive.manifest.version, ResourceManifest::CURRENT_VERSION);
+    assert_eq!(archive.manifest.command_encoding, CommandEncoding::Json);
+    assert_eq!(archive.manifest.images[0].encoding, ImageEncoding::Png);
+
+    let scene = archive.to_scene().unwrap();
+    assert_eq!(scene.commands.len(), 6);
+    assert!(matches!(scene.commands[0], RenderCommand::PushLayer(_)));
+    assert!(matches!(scene.commands[2], RenderCommand::Stroke(_)));
+    assert!(matches!(scene.commands[3], RenderCommand::PopLayer));
+    assert!(matches!(scene.commands[5], RenderCommand::BoxShadow(_)));
+    let RenderCommand::Fill(fill) = &scene.commands[1] else {
+        panic!("Expected Fill command");
+    };
+    assert_eq!(fill.transform, Affine::translate((10.0, 20.0)));
+    assert_eq!(fill.brush, Brush::Solid(Color::from_rgb8(255, 0, 0)));
+
+    // The image is converted back to its original BGRA format
+    let RenderCommand::Fill(image_fill) = &scene.commands[4] else {
+        panic!("Expected Fill command");
+    };
+    let Brush::Image(image_brush) = &image_fill.brush else {
+        panic!("Expected image brush");
+    };
+    assert_eq!(image_brush.image.format, ImageFormat::Bgra8);
+    assert_eq!(
+        image_brush.image.data.data(),
+        &[
+            255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255, 255, 255, 255, 128
+        ]
+    );
+
+    // Upgraded archives are written with the current version
+    let restored = archive_deserialize_from_slice(&archive_serialize_to_vec(&archive).unwrap())
+        .unwrap()
+        .to_scene()
+        .unwrap();
+    assert_eq!(restored.commands[..4], scene.commands[..4]);
+}
+
+#[test]
+fn test_v2_binary_archive_migration() {
+    let data = include_bytes!("fixtures/v2_binary_image.multirender.zip");
+    let archive = archive_deserialize_from_slice(data).unwrap();
+    assert_eq!(archive.manifest.version, ResourceManifest::CURRENT_VERSION);
+    assert_eq!(archive.manifest.command_encoding, CommandEncoding::Binary);
+    assert_eq!(archive.manifest.images[0].encoding, ImageEncoding::Png);
+
+    let scene = archive.to_scene().unwrap();
+    assert_eq!(scene.commands.len(), 2);
+    let RenderCommand::Fill(fill) = &scene.commands[0] else {
+        panic!("Expected Fill command");
+    };
+    assert_eq!(fill.transform, Affine::translate((10.0, 20.0)));
+
+    let RenderCommand::Fill(image_fill) = &scene.commands[1] else {
+        panic!("Expected Fill command");
+    };
+    let Brush::Image(image_brush) = &image_fill.brush else {
+        panic!("Expected image brush");
+    };
+    assert_eq!(image_brush.image.width, 2);
+    assert_eq!(
+        image_brush.image.data.data(),
+        &[
+            255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255, 255, 255, 255, 128
+        ]
+    );
+}
+
+#[test]
+fn test_v1_archive_with_woff2_fonts_migration() {
+    let data = include_bytes!("fixtures/v1_glyphs_woff2.multirender.zip");
+    let restored = deserialize_from_slice(data).unwrap();
+    assert_glyph_run_preserved(&restored);
+}
+
+#[test]
+fn test_unknown_version_is_rejected() {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.start_file("resources.json", SimpleFileOptions::default())
+        .unwrap();
+    zip.write_all(br#"{"version": 99}"#).unwrap();
+    let data = zip.finish().unwrap().into_inner();
+
+    assert!(matches!(
+        archive_deserialize_from_slice(&data),
+        Err(ArchiveError::UnsupportedVersion(99))
+    ));
+}
+
+#[test]
+fn test_frame_archive_roundtrip() {
+    let image = make_1x1_image(255, 0, 0, 255);
+    let font = roboto_font();
+    let frames: Vec<Scene> = (0..3)
+        .map(|i| {
+            let mut scene = build_glyph_scene(&font);
+            scene.fill(
+                Fill::NonZero,
+                Affine::translate((i as f64, 0.0)),
+                ImageBrush::new(image.clone()).as_ref(),
+                None,
+                &Rect::new(0.0, 0.0, 1.0, 1.0),
+            );
+            scene
+        })
+        .collect();
+
+    for config in [default_config(), binary_config()] {
+        let mut writer = FrameArchiveWriter::new(Cursor::new(Vec::new()), &config);
+        for (i, scene) in frames.iter().enumerate() {
+            let timestamp = Duration::from_millis(16 * i as u64);
+            writer
+                .add_frame(scene, timestamp, 800, 600 + i as u32)
+                .unwrap();
+        }
+        assert_eq!(writer.len(), 3);
+        let data = writer.finish().unwrap().into_inner();
+
+        let mut reader = FrameArchiveReader::new(Cursor::new(&data)).unwrap();
+        assert_eq!(reader.len(), 3);
+        // Resources used by every frame are stored once
+        assert_eq!(reader.manifest().images.len(), 1);
+        assert_eq!(reader.manifest().fonts.len(), 1);
+
+        for (i, frame) in reader.frames().enumerate() {
+            let frame = frame.unwrap();
+            assert_eq!(frame.timestamp, Duration::from_millis(16 * i as u64));
+            assert_eq!((frame.width, frame.height), (800, 600 + i as u32));
+            assert_eq!(frame.scene.commands.len(), 2);
+            assert_eq!(extract_image_pixels(&frame.scene, 1), vec![255, 0, 0, 255]);
+            assert_glyph_run_preserved(&Scene {
+                tolerance: frame.scene.tolerance,
+                commands: frame.scene.commands[..1].to_vec(),
+            });
+        }
+
+        // Frames can be read out of order
+        let last = reader.frame(2).unwrap().unwrap();
+        let RenderCommand::Fill(fill) = &last.scene.commands[1] else {
+            panic!("Expected Fill command");
+        };
+        assert_eq!(fill.transform, Affine::translate((2.0, 0.0)));
+        assert!(reader.frame(3).is_none());
+    }
+}
+
+#[test]
+fn test_deterministic_frame_archive_is_byte_identical() {
+    for config in [default_config(), subset_and_woff2_config()] {
+        // Build the frames from scratch for every run, so that their resources have new blob IDs
+        let write = || {
+            let font = roboto_font();
+            let image = make_gradient_image(8, 4, 255);
+            let mut writer =
+                FrameArchiveWriter::new_deterministic(Cursor::new(Vec::new()), &config);
+            for i in 0..3 {
+                let mut scene = build_glyph_scene(&font);
+                scene.commands.extend(build_image_scene(&image).commands);
+                writer
+                    .add_frame(&scene, Duration::from_millis(16 * i), 8, 4)
+                    .unwrap();
+            }
+            writer.finish().unwrap().into_inner()
+        };
+
+        let first = write();
+        assert_eq!(first, write());
+
+        let mut zip = ZipArchive::new(Cursor::new(&first)).unwrap();
+        for index in 0..zip.len() {
+            let file = zip.by_index(index).unwrap();
+            assert_eq!(file.last_modified(), Some(zip::DateTime::default()));
+        }
+
+        // Frames that use the same glyphs share the subsetted font
+        let reader = FrameArchiveReader::new(Cursor::new(&first)).unwrap();
+        assert_eq!(reader.manifest().images.len(), 1);
+        assert_eq!(reader.manifest().fonts.len(), 1);
+    }
+}
+
+#[test]
+fn test_frame_archive_keeps_distinct_resources() {
+    let mut writer = FrameArchiveWriter::new(Cursor::new(Vec::new()), &default_config());
+    for color in [[255, 0, 0, 255], [0, 255, 0, 255]] {
+        let mut scene = Scene::new();
+        let image = make_1x1_image(color[0], color[1], color[2], color[3]);
+        scene.fill(
+            Fill::NonZero,
+            Affine::IDENTITY,
+            ImageBrush::new(image).as_ref(),
+            None,
+            &Rect::new(0.0, 0.0, 1.0, 1.0),
+        );
+        writer.add_frame(&scene, Duration::ZERO, 1, 1).unwrap();
+    }
+    let data = writer.finish().unwrap().into_inner();
+
+    let mut reader = FrameArchiveReader::new(Cursor::new(&data)).unwrap();
+    assert_eq!(reader.manifest().images.len(), 2);
+    let pixels: Vec<Vec<u8>> = reader
+        .frames()
+        .map(|frame| extract_image_pixels(&frame.unwrap().scene, 0))
+        .collect();
+    assert_eq!(pixels, vec![vec![255, 0, 0, 255], vec![0, 255, 0, 255]]);
+}
+
+#[test]
+fn test_frame_archive_keeps_images_with_same_pixels_but_different_sizes() {
+    let solid = |width: u32, height: u32| ImageData {
+        data: Blob::from([255, 0, 0, 255].repeat((width * height) as usize)),
+        format: ImageFormat::Rgba8,
+        alpha_type: ImageAlphaType::Alpha,
+        width,
+        height,
+    };
+
+    let mut writer = FrameArchiveWriter::new(Cursor::new(Vec::new()), &default_config());
+    for image in [solid(2, 2), solid(4, 1)] {
+        writer
+            .add_frame(&build_image_scene(&image), Duration::ZERO, 4, 2)
+            .unwrap();
+    }
+    let data = writer.finish().unwrap().into_inner();
+
+    let mut reader = FrameArchiveReader::new(Cursor::new(&data)).unwrap();
+    let images = &reader.manifest().images;
+    assert_eq!(images.len(), 2);
+    assert_ne!(images[0].entry.path, images[1].entry.path);
+
+    let sizes: Vec<(u32, u32)> = reader
+        .frames()
+        .map(|frame| match &frame.unwrap().scene.commands[0] {
+            RenderCommand::Fill(fill) => match &fill.brush {
+                Brush::Image(image) => (image.image.width, image.image.height),
+                other => panic!("Expected image brush, got {other:?}"),
+            },
+            other => panic!("Expected Fill command, got {other:?}"),
+        })
+        .collect();
+    assert_eq!(sizes, vec![(2, 2), (4, 1)]);
+}
+
+#[test]
+fn test_scene_archive_reader() {
+    let font = roboto_font();
+    let mut scene = build_glyph_scene(&font);
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        ImageBrush::new(make_1x1_image(255, 0, 0, 255)).as_ref(),
+        None,
+        &Rect::new(0.0, 0.0, 1.0, 1.0),
+    );
+
+    for config in [default_config(), binary_config(), subset_and_woff2_config()] {
+        let data = serialize_to_vec(&scene, &config).unwrap();
+        let mut reader = SceneArchiveReader::new(Cursor::new(&data)).unwrap();
+        assert_eq!(reader.manifest().images.len(), 1);
+        assert_eq!(reader.manifest().fonts.len(), 1);
+        assert_eq!(reader.commands().unwrap().len(), 2);
+
+        let image = reader.image(ResourceId(0)).unwrap();
+        assert_eq!(image.data.data(), &[255, 0, 0, 255]);
+        let font = reader.font(ResourceId(0)).unwrap();
+        assert!(!font.data.data().starts_with(b"wOF2"));
+        assert!(matches!(
+            reader.image(ResourceId(1)),
+            Err(ArchiveError::ResourceNotFound(ResourceId(1)))
+        ));
+
+        // Resources are decoded once and shared with the scene
+        let restored = reader.to_scene().unwrap();
+        assert_glyph_run_preserved(&Scene {
+            tolerance: restored.tolerance,
+            commands: restored.commands[..1].to_vec(),
+        });
+        let RenderCommand::GlyphRun(glyph_run) = &restored.commands[0] else {
+            panic!("Expected GlyphRun command");
+        };
+        assert_eq!(glyph_run.font_data.data.id(), font.data.id());
+        let RenderCommand::Fill(fill) = &restored.commands[1] else {
+            panic!("Expected Fill command");
+        };
+        let Brush::Image(image_brush) = &fill.brush else {
+            panic!("Expected image brush");
+        };
+        assert_eq!(image_brush.image.data.id(), image.data.id());
+    }
+}
+
+#[test]
+fn test_scene_archive_reader_migrates_archives() {
+    let data = include_bytes!("fixtures/v1_shapes.multirender.zip");
+    let mut reader = SceneArchiveReader::new(Cursor::new(data)).unwrap();
+    assert_eq!(reader.manifest().version, ResourceManifest::CURRENT_VERSION);
+    let scene = reader.to_scene().unwrap();
+    let expected = archive_deserialize_from_slice(data)
+        .unwrap()
+        .to_scene()
+        .unwrap();
+    assert_eq!(scene.commands.len(), 6);
+    assert_eq!(scene.commands[..4], expected.commands[..4]);
+    assert_eq!(
+        extract_image_pixels(&scene, 4),
+        extract_image_pixels(&expected, 4)
+    );
+
+    let data = include_bytes!("fixtures/v2_binary_image.multirender.zip");
+    let mut reader = SceneArchiveReader::new(Cursor::new(data)).unwrap();
+    assert_eq!(reader.manifest().command_encoding, CommandEncoding::Binary);
+    assert_eq!(reader.commands().unwrap().len(), 2);
+}
+
+#[test]
+fn test_scene_archive_reader_verifies_resources_when_loaded() {
+    let mut scene = Scene::new();
+    for image in [
+        make_1x1_image(255, 0, 0, 255),
+        make_1x1_image(0, 0, 255, 255),
+    ] {
+        scene.fill(
+            Fill::NonZero,
+            Affine::IDENTITY,
+            ImageBrush::new(image).as_ref(),
+            None,
+            &Rect::new(0.0, 0.0, 1.0, 1.0),
+        );
+    }
+    let data = serialize_to_vec(&scene, &default_config()).unwrap();
+
+    // Replace the second image with a copy of the first
+    let mut zip = ZipArchive::new(Cursor::new(&data)).unwrap();
+    let manifest: ResourceManifest =
+        serde_json::from_reader(zip.by_name("resources.json").unwrap()).unwrap();
+    let (first, second) = (
+        &manifest.images[0].entry.path,
+        &manifest.images[1].entry.path,
+    );
+    let mut first_file = Vec::new();
+    zip.by_name(first)
+        .unwrap()
+        .read_to_end(&mut first_file)
+        .unwrap();
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    for index in 0..zip.len() {
+        let mut file = zip.by_index(index).unwrap();
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents).unwrap();
+        if file.name() == second {
+            contents = first_file.clone();
+        }
+        writer
+            .start_file(file.name(), SimpleFileOptions::default())
+            .unwrap();
+        writer.write_all(&contents).unwrap();
+    }
+    let tampered = writer.finish().unwrap().into_inner();
+
+    let mut reader = SceneArchiveReader::new(Cursor::new(&tampered)).unwrap();
+    assert_eq!(reader.commands().unwrap().len(), 2);
+    assert!(reader.image(ResourceId(0)).is_ok());
+    assert!(matches!(
+        reader.image(ResourceId(1)),
+        Err(ArchiveError::InvalidFormat(_))
+    ));
+    assert!(matches!(
+        reader.to_scene(),
+        Err(ArchiveError::InvalidFormat(_))
+    ));
+}
+
+#[test]
+fn test_archive_limits() {
+    let mut scene = build_glyph_scene(&roboto_font());
+    scene.draw_image(
+        ImageBrush::new(make_gradient_image(64, 32, 255)).as_ref(),
+        Affine::IDENTITY,
+    );
+    let data = serialize_to_vec(&scene, &subset_and_woff2_config()).unwrap();
+    let deserialize =
+        |limits: ArchiveLimits| SceneArchive::deserialize_with_limits(Cursor::new(&data), &limits);
+
+    assert!(deserialize(ArchiveLimits::default()).is_ok());
+    assert!(deserialize(ArchiveLimits::unlimited()).is_ok());
+    assert!(matches!(
+        deserialize(ArchiveLimits::new().with_max_archive_size(1024)),
+        Err(ArchiveError::ArchiveTooLarge { limit: 1024, .. })
+    ));
+    assert!(matches!(
+        deserialize(ArchiveLimits::new().with_max_file_size(64)),
+        Err(ArchiveError::FileTooLarge { limit: 64, .. })
+    ));
+    assert!(matches!(
+        deserialize(ArchiveLimits::new().with_max_image_dimension(63)),
+        Err(ArchiveError::ImageTooLarge {
+            width: 64,
+            height: 32
+        })
+    ));
+    assert!(matches!(
+        deserialize(ArchiveLimits::new().with_max_image_pixels(64 * 32 - 1)),
+        Err(ArchiveError::ImageTooLarge { .. })
+    ));
+    assert!(matches!(
+        deserialize(ArchiveLimits::new().with_max_commands(1)),
+        Err(ArchiveError::TooManyCommands { count: 2, limit: 1 })
+    ));
+    assert!(matches!(
+        deserialize(ArchiveLimits::new().with_max_resources(1)),
+        Err(ArchiveError::TooManyResources { count: 2, limit: 1 })
+    ));
+
+    // The decompressed size of WOFF2 fonts is checked before decompressing them
+    let archive = deserialize(ArchiveLimits::default()).unwrap();
+    let font_size = archive.fonts[0].data().len() as u64;
+    let limits = ArchiveLimits::new().with_max_file_size(font_size);
+    assert!(matches!(
+        archive.to_scene_with_limits(&limits),
+        Err(ArchiveError::FileTooLarge { .. })
+    ));
+
+    let mut reader = SceneArchiveReader::with_limits(Cursor::new(&data), limits).unwrap();
+    assert!(reader.image(ResourceId(0)).is_ok());
+    assert!(matches!(
+        reader.font(ResourceId(0)),
+        Err(ArchiveError::FileTooLarge { .. })
+    ));
+}
+
+#[test]
+fn test_missing_resource_references_are_rejected() {
+    let mut scene = Scene::new();
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        ImageBrush::new(make_1x1_image(255, 0, 0, 255)).as_ref(),
+        None,
+        &Rect::new(0.0, 0.0, 1.0, 1.0),
+    );
+    let mut archive = SceneArchive::from_scene(&scene, &default_config()).unwrap();
+    let SerializableRenderCommand::Fill(fill) = &mut archive.commands[0] else {
+        panic!("Expected Fill command");
+    };
+    let Brush::Image(image_brush) = &mut fill.brush else {
+        panic!("Expected image brush");
+    };
+    image_brush.image = ResourceId(7);
+
+    assert!(matches!(
+        archive.to_scene(),
+        Err(ArchiveError::ResourceNotFound(ResourceId(7)))
+    ));
+    let data = archive_serialize_to_vec(&archive).unwrap();
+    assert!(matches!(
+        archive_deserialize_from_slice(&data),
+        Err(ArchiveError::ResourceNotFound(ResourceId(7)))
+    ));
+    let mut reader = SceneArchiveReader::new(Cursor::new(&data)).unwrap();
+    assert!(matches!(
+        reader.commands(),
+        Err(ArchiveError::ResourceNotFound(ResourceId(7)))
+    ));
+}
+
+#[test]
+fn test_capturing_window_renderer() {
+    let mut renderer = CapturingWindowRenderer::new(SceneWindowRenderer::default());
+    renderer.set_size(200, 100);
+    renderer.set_scale_factor(2.0);
+    let draw = |painter: &mut Scene| {
+        painter.fill(
+            Fill::NonZero,
+            Affine::translate((10.0, 0.0)),
+            Color::from_rgb8(255, 0, 0),
+            None,
+            &Rect::new(0.0, 0.0, 50.0, 25.0),
+        );
+    };
+
+    // Frames are rendered through the inner renderer, and only captured when requested
+    assert!(renderer.render(draw).is_some());
+    assert_eq!(renderer.inner().rendered.len(), 1);
+    assert!(renderer.take_capture().is_none());
+
+    renderer.capture_next_frame();
+    renderer.render(draw);
+    renderer.render(draw);
+    let archive = renderer.take_capture().unwrap().unwrap();
+    assert!(renderer.take_capture().is_none());
+    assert_eq!(renderer.history().len(), 0);
+
+    // The inner renderer applies the scale factor itself, while captured scenes are in physical pixels
+    let RenderCommand::Fill(rendered) = &renderer.inner().rendered[1].commands[0] else {
+        panic!("Expected Fill command");
+    };
+    assert_eq!(rendered.transform, Affine::translate((10.0, 0.0)));
+    let captured = archive.to_scene().unwrap();
+    let RenderCommand::Fill(fill) = &captured.commands[0] else {
+        panic!("Expected Fill command");
+    };
+    assert_eq!(
+        fill.transform,
+        Affine::scale(2.0) * Affine::translate((10.0, 0.0))
+    );
+}
+
+#[test]
+fn test_capturing_window_renderer_history() {
+    let mut renderer = CapturingWindowRenderer::new(SceneWindowRenderer::default()).with_history(2);
+    renderer.set_size(64, 48);
+    for i in 0..3 {
+        renderer.render(|painter| {
+            painter.fill(
+                Fill::NonZero,
+                Affine::translate((i as f64, 0.0)),
+                Color::from_rgb8(0, 0, 255),
+                None,
+                &Rect::new(0.0, 0.0, 1.0, 1.0),
+            );
+        });
+    }
+    assert_eq!(renderer.history().len(), 2);
+
+    // Only the last frames are kept, oldest first
+    let data = renderer
+        .write_history(Cursor::new(Vec::new()))
+        .unwrap()
+        .into_inner();
+    let mut reader = FrameArchiveReader::new(Cursor::new(&data)).unwrap();
+    let frames: Vec<_> = reader.frames().map(Result::unwrap).collect();
+    assert_eq!(frames.len(), 2);
+    assert!(frames[0].timestamp <= frames[1].timestamp);
+    for (frame, i) in frames.iter().zip(1..) {
+        assert_eq!((frame.width, frame.height), (64, 48));
+        let RenderCommand::Fill(fill) = &frame.scene.commands[0] else {
+            panic!("Expected Fill command");
+        };
+        assert_eq!(fill.transform, Affine::translate((i as f64, 0.0)));
+    }
+
+    renderer.clear_history();
+    assert_eq!(renderer.history().len(), 0);
+}
+
 // Helpers
 
+/// A window renderer that keeps every scene it renders
+#[derive(Default)]
+struct SceneWindowRenderer {
+    rendered: Vec<Scene>,
+}
+
+impl WindowRenderer for SceneWindowRenderer {
+    type ScenePainter<'a> = Scene;
+
+    fn resume(
+        &mut self,
+        _window: Arc<dyn WindowHandle>,
+        _width: u32,
+        _height: u32,
+    ) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn suspend(&mut self) {}
+
+    fn is_active(&self) -> bool {
+        true
+    }
+
+    fn set_size(&mut self, _width: u32, _height: u32) {}
+
+    fn set_scale_factor(&mut self, _scale_factor: f64) {}
+
+    fn render<F: FnOnce(&mut Scene)>(&mut self, draw_fn: F) -> Option<FrameStats> {
+        let mut scene = Scene::new();
+        draw_fn(&mut scene);
+        self.rendered.push(scene);
+        Some(FrameStats::default())
+    }
+}
+
 fn default_config() -> SerializeConfig {
     SerializeConfig::new()
 }
 
+fn binary_config() -> SerializeConfig {
+    SerializeConfig::new().with_command_encoding(CommandEncoding::Binary)
+}
+
 fn subset_and_woff2_config() -> SerializeConfig {
     SerializeConfig::new()
         .with_subset_fonts(true)
@@ -380,6 +1425,40 @@ fn assert_scene_roundtrip(scene: &Scene) {
     assert_eq!(*scene, restored);
 }
 
+fn make_gradient_image(width: u32, height: u32, alpha: u8) -> ImageData {
+    let mut pixels = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            pixels.extend([
+                (x * 255 / width) as u8,
+                (y * 255 / height) as u8,
+                100,
+                alpha,
+            ]);
+        }
+    }
+    ImageData {
+        data: Blob::from(pixels),
+        format: ImageFormat::Rgba8,
+        alpha_type: ImageAlphaType::Alpha,
+        width,
+        height,
+    }
+}
+
+fn build_image_scene(image: &ImageData) -> Scene {
+    let mut scene = Scene::new();
+    scene.draw_image(ImageBrush::new(image.clone()).as_ref(), Affine::IDENTITY);
+    scene
+}
+
+fn assert_pixels_close(actual: &[u8], expected: &[u8], tolerance: u8) {
+    assert_eq!(actual.len(), expected.len());
+    for (i, (a, e)) in actual.iter().zip(expected).enumerate() {
+        assert!(a.abs_diff(*e) <= tolerance, "byte {i}: {a} != {e}");
+    }
+}
+
 fn make_1x1_image(r: u8, g: u8, b: u8, a: u8) -> ImageData {
     ImageData {
         data: Blob::from(vec![r, g, b, a]),
@@ -405,6 +1484,79 @@ fn roboto_font() -> FontData {
     FontData::new(Blob::from(ROBOTO_BYTES.to_vec()), 0)
 }
 
+fn roboto_flex_font() -> FontData {
+    static ROBOTO_FLEX_BYTES: &[u8] =
+        include_bytes!("../../../assets/fonts/roboto_flex/RobotoFlex-Subset.ttf");
+    FontData::new(Blob::from(ROBOTO_FLEX_BYTES.to_vec()), 0)
+}
+
+/// The glyphs of "Hello" in `font`
+fn hello_glyphs(font: &FontData) -> Vec<Glyph> {
+    let font_ref = read_fonts::FontRef::new(font.data.data()).unwrap();
+    let charmap = font_ref.charmap();
+    "Hello"
+        .chars()
+        .enumerate()
+        .map(|(i, c)| Glyph {
+            id: charmap.map(c).unwrap().to_u32(),
+            x: i as f32 * 10.0,
+            y: 0.0,
+        })
+        .collect()
+}
+
+/// A scene with a glyph run drawing "Hello" at each of `locations`
+fn build_variable_glyph_scene(font: &FontData, locations: &[&[i16]]) -> Scene {
+    let mut scene = Scene::new();
+    for (i, coords) in locations.iter().enumerate() {
+        scene.draw_glyphs(
+            font,
+            16.0,
+            false,
+            coords,
+            Fill::NonZero,
+            Color::from_rgb8(0, 0, 0),
+            1.0,
+            Affine::translate((10.0, 20.0 * (i + 1) as f64)),
+            None,
+            hello_glyphs(font).into_iter(),
+        );
+    }
+    scene
+}
+
+/// The on- and off-curve points of a glyph's outline at `coords`, in font units
+fn outline_points(font: &read_fonts::FontRef, gid: u32, coords: &[i16]) -> Vec<(f32, f32)> {
+    struct PointPen(Vec<(f32, f32)>);
+    impl OutlinePen for PointPen {
+        fn move_to(&mut self, x: f32, y: f32) {
+            self.0.push((x, y));
+        }
+        fn line_to(&mut self, x: f32, y: f32) {
+            self.0.push((x, y));
+        }
+        fn quad_to(&mut self, cx0: f32, cy0: f32, x: f32, y: f32) {
+            self.0.extend([(cx0, cy0), (x, y)]);
+        }
+        fn curve_to(&mut self, cx0: f32, cy0: f32, cx1: f32, cy1: f32, x: f32, y: f32) {
+            self.0.extend([(cx0, cy0), (cx1, cy1), (x, y)]);
+        }
+        fn close(&mut self) {}
+    }
+
+    let coords: Vec<F2Dot14> = coords.iter().copied().map(F2Dot14::from_bits).collect();
+    let mut pen = PointPen(Vec::new());
+    font.outline_glyphs()
+        .get(GlyphId::new(gid))
+        .unwrap()
+        .draw(
+            DrawSettings::unhinted(Size::unscaled(), LocationRef::new(&coords)),
+            &mut pen,
+        )
+        .unwrap();
+    pen.0
+}
+
 fn build_glyph_scene(font: &FontData) -> Scene {
     let mut scene = Scene::new();
     let glyphs = [
@@ -445,7 +1597,7 @@ fn assert_glyph_run_preserved(restored: &Scene) {
     match &restored.commands[0] {
         RenderCommand::GlyphRun(glyph_run) => {
             assert_eq!(glyph_run.font_size, 16.0);
-            assert_eq!(glyph_run.hint, false);
+            assert!(!glyph_run.hint);
             assert_eq!(glyph_run.brush_alpha, 1.0);
             assert_eq!(glyph_run.transform, Affine::translate((10.0, 50.0)));
             assert_eq!(glyph_run.glyph_transform, None);
@@ -1,5 +1,4 @@
-use debug_timer::debug_timer;
-use multirender::ImageRenderer;
+use multirender::{DEFAULT_SCALE_FACTOR, FramePhase, FrameStats, FrameTimer, ImageRenderer};
 use skia_safe::{AlphaType, Color, ColorType, ImageInfo, SurfaceProps, graphics, surfaces};
 
 use crate::{SkiaScenePainter, scene::SkiaSceneCache};
@@ -8,6 +7,7 @@ pub struct SkiaImageRenderer {
     image_info: ImageInfo,
     surface_props: SurfaceProps,
     scene_cache: SkiaSceneCache,
+    scale_factor: f64,
 }
 
 impl ImageRenderer for SkiaImageRenderer {
@@ -30,6 +30,7 @@ impl ImageRenderer for SkiaImageRenderer {
             ),
             surface_props: SurfaceProps::default(),
             scene_cache: SkiaSceneCache::default(),
+            scale_factor: DEFAULT_SCALE_FACTOR,
         }
     }
 
@@ -42,14 +43,18 @@ impl ImageRenderer for SkiaImageRenderer {
         );
     }
 
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+    }
+
     fn reset(&mut self) {}
 
     fn render_to_vec<F: FnOnce(&mut Self::ScenePainter<'_>)>(
         &mut self,
         draw_fn: F,
         buffer: &mut Vec<u8>,
-    ) {
-        debug_timer!(timer, feature = "log_frame_times");
+    ) -> Option<FrameStats> {
+        let mut timer = FrameTimer::start();
 
         let mut surface = surfaces::wrap_pixels(
             &self.image_info,
@@ -64,17 +69,26 @@ impl ImageRenderer for SkiaImageRenderer {
         draw_fn(&mut SkiaScenePainter {
             inner: surface.canvas(),
             cache: &mut self.scene_cache,
+            scale_factor: self.scale_factor,
         });
-        timer.record_time("render");
+        timer.record(FramePhase::Rasterize);
 
         self.scene_cache.next_gen();
-        timer.record_time("cache next gen");
+        timer.record(FramePhase::CacheMaintenance);
+
+        let stats = timer.finish();
+        #[cfg(feature = "log_frame_times")]
+        println!("skia_raster: {stats}");
 
-        timer.print_times("skia_raster: ");
+        Some(stats)
     }
 
-    fn render<F: FnOnce(&mut Self::ScenePainter<'_>)>(&mut self, draw_fn: F, buffer: &mut [u8]) {
-        debug_timer!(timer, feature = "log_frame_times");
+    fn render<F: FnOnce(&mut Self::ScenePainter<'_>)>(
+        &mut self,
+        draw_fn: F,
+        buffer: &mut [u8],
+    ) -> Option<FrameStats> {
+        let mut timer = FrameTimer::start();
 
         let mut surface = surfaces::wrap_pixels(
             &self.image_info,
@@ -89,12 +103,17 @@ impl ImageRenderer for SkiaImageRenderer {
         draw_fn(&mut SkiaScenePainter {
             inner: surface.canvas(),
             cache: &mut self.scene_cache,
+            scale_factor: self.scale_factor,
         });
-        timer.record_time("render");
+        timer.record(FramePhase::Rasterize);
 
         self.scene_cache.next_gen();
-        timer.record_time("cache next gen");
+        timer.record(FramePhase::CacheMaintenance);
+
+        let stats = timer.finish();
+        #[cfg(feature = "log_frame_times")]
+        println!("skia_raster: {stats}");
 
-        timer.print_times("skia_raster: ");
+        Some(stats)
     }
 }
@@ -1,4 +1,4 @@
-use multirender::PaintScene;
+use multirender::{PaintScene, hint_at_scale, root_transform, scaled_tolerance};
 use skia_safe::{
     BlurStyle, Canvas, Color, ColorSpace, Font, FontArguments, FontHinting, FontMgr, GlyphId,
     MaskFilter, Paint, PaintCap, PaintJoin, PaintStyle, Point, RRect, Rect, Shader, Typeface,
@@ -51,9 +51,12 @@ impl Default for SkiaSceneCache {
     }
 }
 
+const DEFAULT_TOLERANCE: f64 = 0.1;
+
 pub struct SkiaScenePainter<'a> {
     pub(crate) inner: &'a Canvas,
     pub(crate) cache: &'a mut SkiaSceneCache,
+    pub(crate) scale_factor: f64,
 }
 
 impl SkiaScenePainter<'_> {
@@ -73,7 +76,13 @@ impl SkiaScenePainter<'_> {
     }
 
     fn set_matrix(&self, transform: kurbo::Affine) {
-        self.inner.set_matrix(&sk_kurbo::m44_from_affine(transform));
+        self.inner.set_matrix(&sk_kurbo::m44_from_affine(
+            root_transform(self.scale_factor) * transform,
+        ));
+    }
+
+    fn tolerance(&self) -> f64 {
+        scaled_tolerance(DEFAULT_TOLERANCE, self.scale_factor)
     }
 
     fn concat_matrix(&self, transform: kurbo::Affine) {
@@ -87,8 +96,11 @@ impl SkiaScenePainter<'_> {
             self.inner
                 .clip_rrect(sk_kurbo::rrect_from(rrect), None, true);
         } else {
-            self.inner
-                .clip_path(&sk_kurbo::path_from_shape(shape), None, true);
+            self.inner.clip_path(
+                &sk_kurbo::path_from_shape(shape, self.tolerance()),
+                None,
+                true,
+            );
         }
     }
 
@@ -181,7 +193,7 @@ impl SkiaScenePainter<'_> {
                 &self.cache.paint,
             );
         } else {
-            let mut path = sk_kurbo::path_from_shape(shape);
+            let mut path = sk_kurbo::path_from_shape(shape, self.tolerance());
             if let Some(fill) = fill.into() {
                 path.set_fill_type(sk_peniko::path_fill_type_from_fill(fill));
             }
@@ -458,6 +470,7 @@ impl PaintScene for SkiaScenePainter<'_> {
         self.set_paint_style(style);
         self.set_paint_alpha(brush_alpha);
 
+        let hint = hint_at_scale(hint, self.scale_factor);
         let Some(font) = self.get_or_cache_font(font, normalized_coords, font_size, hint) else {
             return;
         };
@@ -936,7 +949,7 @@ mod sk_kurbo {
         SkPoint::new(p.x as f32, p.y as f32)
     }
 
-    pub(super) fn path_from_shape(shape: &impl Shape) -> SkPath {
+    pub(super) fn path_from_shape(shape: &impl Shape, tolerance: f64) -> SkPath {
         let mut sk_path = SkPathBuilder::new();
 
         if let Some(path_els) = shape.as_path_slice() {
@@ -944,7 +957,7 @@ mod sk_kurbo {
                 append_path_el_to_sk_path(path_el, &mut sk_path);
             }
         } else {
-            for path_el in shape.path_elements(0.1) {
+            for path_el in shape.path_elements(tolerance) {
                 append_path_el_to_sk_path(&path_el, &mut sk_path);
             }
         }
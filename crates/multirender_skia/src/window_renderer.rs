@@ -1,5 +1,4 @@
-use debug_timer::debug_timer;
-use multirender::WindowRenderer;
+use multirender::{DEFAULT_SCALE_FACTOR, FramePhase, FrameStats, FrameTimer, WindowRenderer};
 use skia_safe::{Color, Surface, graphics};
 use std::sync::Arc;
 
@@ -25,6 +24,7 @@ struct ActiveRenderState {
 
 pub struct SkiaWindowRenderer {
     render_state: RenderState,
+    scale_factor: f64,
 }
 
 impl Default for SkiaWindowRenderer {
@@ -37,6 +37,7 @@ impl SkiaWindowRenderer {
     pub fn new() -> Self {
         Self {
             render_state: RenderState::Suspended,
+            scale_factor: DEFAULT_SCALE_FACTOR,
         }
     }
 }
@@ -85,17 +86,19 @@ impl WindowRenderer for SkiaWindowRenderer {
         }
     }
 
-    fn render<F: FnOnce(&mut Self::ScenePainter<'_>)>(&mut self, draw_fn: F) {
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+    }
+
+    fn render<F: FnOnce(&mut Self::ScenePainter<'_>)>(&mut self, draw_fn: F) -> Option<FrameStats> {
         let RenderState::Active(state) = &mut self.render_state else {
-            return;
+            return None;
         };
 
-        debug_timer!(timer, feature = "log_frame_times");
+        let mut timer = FrameTimer::start();
 
-        let mut surface = match state.backend.prepare() {
-            Some(it) => it,
-            None => return,
-        };
+        let mut surface = state.backend.prepare()?;
+        timer.record(FramePhase::Wait);
 
         surface.canvas().restore_to_count(1);
         surface.canvas().clear(Color::WHITE);
@@ -103,16 +106,21 @@ impl WindowRenderer for SkiaWindowRenderer {
         draw_fn(&mut SkiaScenePainter {
             inner: surface.canvas(),
             cache: &mut state.scene_cache,
+            scale_factor: self.scale_factor,
         });
-        timer.record_time("cmd");
+        timer.record(FramePhase::Encode);
 
         state.backend.flush(surface);
-        timer.record_time("render");
+        timer.record(FramePhase::Flush);
 
         state.scene_cache.next_gen();
-        timer.record_time("cache next gen");
+        timer.record(FramePhase::CacheMaintenance);
+
+        let stats = timer.finish();
+        #[cfg(feature = "log_frame_times")]
+        println!("skia: {stats}");
 
-        timer.print_times("skia: ");
+        Some(stats)
     }
 }
 
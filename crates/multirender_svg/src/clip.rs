@@ -0,0 +1,125 @@
+//! Rendering of SVG clip paths.
+//!
+//! A clip path made of a single path filled with the non-zero rule is drawn as the clip shape of the group's layer.
+//! Other clip paths are drawn like alpha masks: the content is drawn in a layer clipped to the bounds of the clip
+//! path, and the shapes of the clip path are then drawn opaque in a nested layer that is composited onto it with
+//! [`Compose::DestIn`]. This handles clip paths with several shapes, text, groups, the even-odd clip rule and clip
+//! paths that are clipped themselves.
+
+use kurbo::{Affine, BezPath, Rect};
+use multirender::PaintScene;
+use peniko::{BlendMode, Color, Compose, Fill, Mix};
+
+use crate::mask::LAYER_ALPHA;
+use crate::util;
+
+/// The clip path as a single shape that can be used to clip a layer directly, in the coordinates of the clip path.
+pub(crate) fn as_path(clip: &usvg::ClipPath) -> Option<BezPath> {
+    if clip.clip_path().is_some() {
+        return None;
+    }
+    match clip.root().children() {
+        [usvg::Node::Path(path)]
+            if path.is_visible()
+                && path
+                    .fill()
+                    .is_some_and(|fill| fill.rule() == usvg::FillRule::NonZero) =>
+        {
+            Some(util::to_bez_path(path))
+        }
+        _ => None,
+    }
+}
+
+/// Whether all content of a clip path can be rendered. Masks and filters on groups inside clip paths are ignored.
+pub(crate) fn is_supported(clip: &usvg::ClipPath) -> bool {
+    fn is_group_supported(group: &usvg::Group) -> bool {
+        group.children().iter().all(|node| match node {
+            usvg::Node::Group(group) => {
+                group.mask().is_none()
+                    && group.filters().is_empty()
+                    && group.clip_path().is_none_or(is_supported)
+                    && is_group_supported(group)
+            }
+            _ => true,
+        })
+    }
+
+    is_group_supported(clip.root()) && clip.clip_path().is_none_or(is_supported)
+}
+
+/// The transform of the content of a clip path applied with `transform`.
+pub(crate) fn transform(clip: &usvg::ClipPath, transform: Affine) -> Affine {
+    transform * util::to_affine(&clip.transform())
+}
+
+/// The bounds of the content of a clip path, in the coordinates of the clip path.
+pub(crate) fn bounds(clip: &usvg::ClipPath) -> Rect {
+    util::to_rect(clip.root().layer_bounding_box())
+}
+
+/// Clip the content of the current layer to `clip`, which is applied with `transform`.
+///
+/// The current layer should have an opacity of at most [`LAYER_ALPHA`], see its documentation.
+pub(crate) fn apply<S: PaintScene>(scene: &mut S, clip: &usvg::ClipPath, transform: Affine) {
+    let clip_transform = self::transform(clip, transform);
+    scene.push_layer(
+        BlendMode {
+            mix: Mix::Normal,
+            compose: Compose::DestIn,
+        },
+        1.0,
+        clip_transform,
+        &bounds(clip),
+    );
+    draw_shapes(scene, clip.root(), clip_transform);
+    scene.pop_layer();
+
+    // A clip path that is clipped itself clips to the intersection of both
+    if let Some(clip) = clip.clip_path() {
+        apply(scene, clip, transform);
+    }
+}
+
+/// Draw the shapes of the content of a clip path opaque.
+fn draw_shapes<S: PaintScene>(scene: &mut S, group: &usvg::Group, transform: Affine) {
+    for node in group.children() {
+        match node {
+            usvg::Node::Path(path) => {
+                let Some(fill) = path.fill().filter(|_| path.is_visible()) else {
+                    continue;
+                };
+                let rule = match fill.rule() {
+                    usvg::FillRule::NonZero => Fill::NonZero,
+                    usvg::FillRule::EvenOdd => Fill::EvenOdd,
+                };
+                scene.fill(
+                    rule,
+                    transform,
+                    Color::BLACK,
+                    None,
+                    &util::to_bez_path(path),
+                );
+            }
+            usvg::Node::Text(text) => draw_shapes(scene, text.flattened(), transform),
+            usvg::Node::Group(group) => {
+                let transform = transform * util::to_affine(&group.transform());
+                if let Some(clip) = group.clip_path() {
+                    scene.push_layer(
+                        BlendMode::default(),
+                        LAYER_ALPHA,
+                        self::transform(clip, transform),
+                        &bounds(clip),
+                    );
+                    draw_shapes(scene, group, transform);
+                    apply(scene, clip, transform);
+                    scene.pop_layer();
+                } else {
+                    draw_shapes(scene, group, transform);
+                }
+            }
+            // Images don't contribute to clip paths
+            usvg::Node::Image(_) => {}
+        }
+    }
+}
@@ -0,0 +1,98 @@
+//! Reports of the SVG features that couldn't be rendered.
+
+use std::fmt;
+
+use kurbo::Rect;
+
+/// The issues found while rendering an SVG with
+/// [`render_svg_tree_with_diagnostics`](crate::render_svg_tree_with_diagnostics).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Report {
+    pub issues: Vec<Issue>,
+}
+
+impl Report {
+    /// Whether the SVG was rendered without issues.
+    pub fn is_empty(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// A node of an SVG that couldn't be rendered, or was only partially rendered.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Issue {
+    /// The id of the node, which is empty if the node has no id.
+    pub id: String,
+    pub kind: IssueKind,
+    /// The bounding box of the node, in the coordinates of the SVG that it belongs to.
+    pub bounding_box: Rect,
+}
+
+impl Issue {
+    pub(crate) fn new(node: &usvg::Node, kind: IssueKind) -> Self {
+        let bounding_box = node.abs_bounding_box();
+        // usvg wraps elements other than `g` that have filters, masks or clip paths in anonymous groups, so the id of
+        // the wrapped element is reported instead
+        let id = match node {
+            usvg::Node::Group(group) if group.id().is_empty() => match group.children() {
+                [child] => child.id(),
+                _ => "",
+            },
+            node => node.id(),
+        };
+        Self {
+            id: id.to_string(),
+            kind,
+            bounding_box: Rect::new(
+                bounding_box.left().into(),
+                bounding_box.top().into(),
+                bounding_box.right().into(),
+                bounding_box.bottom().into(),
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Rect { x0, y0, x1, y1 } = self.bounding_box;
+        if self.id.is_empty() {
+            write!(f, "{} at ({x0}, {y0}, {x1}, {y1})", self.kind)
+        } else {
+            write!(
+                f,
+                "{} in #{} at ({x0}, {y0}, {x1}, {y1})",
+                self.kind, self.id
+            )
+        }
+    }
+}
+
+/// The kind of feature of an [`Issue`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum IssueKind {
+    /// A fill or stroke with a pattern, which needs the `patterns` feature. The fill or stroke isn't drawn.
+    Pattern,
+    /// A luminance mask without the `masks` feature. The group is drawn without its masks.
+    Mask,
+    /// A filter with unsupported primitives, or any filter without the `filters` feature. The group is drawn without
+    /// its filters.
+    Filter,
+    /// A clip path containing groups with masks or filters, which are ignored.
+    ComplexClip,
+    /// An image that couldn't be decoded, or whose format isn't enabled. The image isn't drawn.
+    ImageDecode,
+}
+
+impl fmt::Display for IssueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            IssueKind::Pattern => "unsupported pattern",
+            IssueKind::Mask => "unsupported mask",
+            IssueKind::Filter => "unsupported filter",
+            IssueKind::ComplexClip => "unsupported clip path",
+            IssueKind::ImageDecode => "image decode failure",
+        })
+    }
+}
@@ -0,0 +1,274 @@
+//! SVGs converted to recorded scenes, for drawing the same SVG many times.
+
+use std::path::Path;
+
+use kurbo::{Affine, Rect, Size};
+use multirender::{PaintScene, Scene};
+
+use crate::{Error, RenderOptions, util};
+
+/// An SVG converted to a recorded [`Scene`], which can be drawn many times without walking the [`usvg::Tree`] again.
+///
+/// The SVG is converted once: paths are built, embedded and external images are decoded, and patterns, masks and
+/// filters are rendered to images. Drawing the document replays the recorded scene by reference, so its paths and
+/// images are shared between draws rather than built or decoded again.
+///
+/// Unlike paths and text, the images of patterns, masks and filters have a fixed resolution: the size of the SVG
+/// times the [raster scale](RenderOptions::with_raster_scale) of the options it is converted with. Drawing the
+/// document larger than that makes these parts blurry, so set the raster scale to the largest scale the document is
+/// drawn at.
+///
+/// The scene is recorded in the coordinates of the `viewBox` of the SVG, so the document can be drawn at its own
+/// size with [`SvgDocument::draw`] or fitted into any rectangle according to its `preserveAspectRatio` with
+/// [`SvgDocument::draw_in_rect`]. Content outside of the `viewBox` is not clipped.
+#[derive(Clone, Debug)]
+pub struct SvgDocument {
+    scene: Scene,
+    size: Size,
+    view_box: Rect,
+    aspect_ratio: AspectRatio,
+}
+
+impl SvgDocument {
+    /// Convert an SVG. Unsupported elements are drawn as red boxes.
+    pub fn from_str(svg: &str, options: &RenderOptions) -> Result<Self, Error> {
+        let tree = usvg::Tree::from_str(svg, &options.to_usvg_options())?;
+        let (view_box, aspect_ratio) = parse_view_box(svg);
+        Ok(Self::convert(
+            &tree,
+            view_box,
+            aspect_ratio,
+            options,
+            &mut util::default_error_handler,
+        ))
+    }
+
+    /// Convert an SVG, which may be compressed (SVGZ). Unsupported elements are drawn as red boxes.
+    pub fn from_data(data: &[u8], options: &RenderOptions) -> Result<Self, Error> {
+        if data.starts_with(&[0x1f, 0x8b]) {
+            let data = usvg::decompress_svgz(data)?;
+            return Self::from_data(&data, options);
+        }
+        let svg = std::str::from_utf8(data).map_err(|_| usvg::Error::NotAnUtf8Str)?;
+        Self::from_str(svg, options)
+    }
+
+    /// Convert an SVG file. Relative paths of images are resolved from the directory of the file, unless the options
+    /// set a resources directory. Unsupported elements are drawn as red boxes.
+    pub fn from_file(path: impl AsRef<Path>, options: &RenderOptions) -> Result<Self, Error> {
+        let path = path.as_ref();
+        Self::from_data(&std::fs::read(path)?, &options.for_file(path))
+    }
+
+    /// Convert a [`usvg::Tree`] (with custom error handling).
+    ///
+    /// The tree doesn't keep the `viewBox` and `preserveAspectRatio` of the SVG, so the document is fitted into
+    /// rectangles as if the `viewBox` covered its size, centered and keeping its aspect ratio.
+    pub fn from_tree_with<F: FnMut(&mut Scene, &usvg::Node)>(
+        tree: &usvg::Tree,
+        options: &RenderOptions,
+        error_handler: &mut F,
+    ) -> Self {
+        Self::convert(tree, None, AspectRatio::default(), options, error_handler)
+    }
+
+    fn convert<F: FnMut(&mut Scene, &usvg::Node)>(
+        tree: &usvg::Tree,
+        view_box: Option<Rect>,
+        aspect_ratio: AspectRatio,
+        options: &RenderOptions,
+        error_handler: &mut F,
+    ) -> Self {
+        let size = Size::new(
+            f64::from(tree.size().width()),
+            f64::from(tree.size().height()),
+        );
+        let view_box = view_box.unwrap_or(size.to_rect());
+        // Patterns, masks and filters are rendered at the resolution of the transform the tree is rendered with, so
+        // it is rendered at the raster scale and then scaled into the coordinates of the `viewBox`, undoing the root
+        // transform that usvg applies the `viewBox` in
+        let raster_scale = options.raster_scale();
+        let view_box_transform = aspect_ratio.transform(view_box, size.to_rect());
+        let mut rendered = Scene::new();
+        crate::render_svg_tree_with_options(
+            &mut rendered,
+            tree,
+            Affine::scale(raster_scale),
+            options,
+            error_handler,
+        );
+        let mut scene = Scene::new();
+        scene.append_scene(
+            rendered,
+            view_box_transform.inverse() * Affine::scale(raster_scale.recip()),
+        );
+        Self {
+            scene,
+            size,
+            view_box,
+            aspect_ratio,
+        }
+    }
+
+    /// The size of the SVG, from its `width` and `height`.
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    /// The `viewBox` of the SVG, which the scene is recorded in the coordinates of.
+    pub fn view_box(&self) -> Rect {
+        self.view_box
+    }
+
+    /// The recorded scene, in the coordinates of the `viewBox`.
+    pub fn scene(&self) -> &Scene {
+        &self.scene
+    }
+
+    /// The transform that fits the `viewBox` into `rect` according to the `preserveAspectRatio` of the SVG.
+    pub fn fit_transform(&self, rect: Rect) -> Affine {
+        self.aspect_ratio.transform(self.view_box, rect)
+    }
+
+    /// Draw the document at its size, with its top left corner at the origin of `transform`.
+    pub fn draw<S: PaintScene>(&self, scene: &mut S, transform: Affine) {
+        self.draw_in_rect(scene, transform, self.size.to_rect());
+    }
+
+    /// Draw the document fitted into `rect` according to its `preserveAspectRatio`. `rect` is in the coordinates of
+    /// `transform`.
+    pub fn draw_in_rect<S: PaintScene>(&self, scene: &mut S, transform: Affine, rect: Rect) {
+        scene.replay_scene(&self.scene, transform * self.fit_transform(rect));
+    }
+}
+
+/// The value of a `preserveAspectRatio` attribute.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct AspectRatio {
+    /// The alignment of the `viewBox` in the viewport along each axis, from 0 for the start to 1 for the end, or
+    /// `None` to stretch the `viewBox` to fill the viewport.
+    align: Option<(f64, f64)>,
+    /// Whether the `viewBox` is scaled to cover the viewport rather than to fit within it.
+    slice: bool,
+}
+
+impl Default for AspectRatio {
+    fn default() -> Self {
+        Self {
+            align: Some((0.5, 0.5)),
+            slice: false,
+        }
+    }
+}
+
+impl AspectRatio {
+    fn parse(value: &str) -> Option<Self> {
+        let mut words = value.split_whitespace().peekable();
+        words.next_if_eq(&"defer");
+        let align = match words.next()? {
+            "none" => None,
+            align => {
+                let position = |name: &str| match name {
+                    "Min" => Some(0.0),
+                    "Mid" => Some(0.5),
+                    "Max" => Some(1.0),
+                    _ => None,
+                };
+                let (x, y) = align.strip_prefix('x')?.split_once('Y')?;
+                Some((position(x)?, position(y)?))
+            }
+        };
+        let slice = match words.next() {
+            None | Some("meet") => false,
+            Some("slice") => true,
+            Some(_) => return None,
+        };
+        Some(Self { align, slice })
+    }
+
+    /// The transform that maps `view_box` into `viewport`.
+    fn transform(&self, view_box: Rect, viewport: Rect) -> Affine {
+        let sx = viewport.width() / view_box.width();
+        let sy = viewport.height() / view_box.height();
+        let Some((align_x, align_y)) = self.align else {
+            return Affine::translate((viewport.x0, viewport.y0))
+                * Affine::scale_non_uniform(sx, sy)
+                * Affine::translate((-view_box.x0, -view_box.y0));
+        };
+        let scale = if self.slice { sx.max(sy) } else { sx.min(sy) };
+        let x = viewport.x0 + (viewport.width() - view_box.width() * scale) * align_x;
+        let y = viewport.y0 + (viewport.height() - view_box.height() * scale) * align_y;
+        Affine::translate((x, y))
+            * Affine::scale(scale)
+            * Affine::translate((-view_box.x0, -view_box.y0))
+    }
+}
+
+/// The `viewBox` and `preserveAspectRatio` of the root element of an SVG, if it has a valid `viewBox`.
+fn parse_view_box(svg: &str) -> (Option<Rect>, AspectRatio) {
+    let options = usvg::roxmltree::ParsingOptions {
+        allow_dtd: true,
+        ..Default::default()
+    };
+    let Ok(document) = usvg::roxmltree::Document::parse_with_options(svg, options) else {
+        return (None, AspectRatio::default());
+    };
+    let root = document.root_element();
+    let view_box = root.attribute("viewBox").and_then(|value| {
+        let numbers: Vec<f64> = value
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|number| !number.is_empty())
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .ok()?;
+        match numbers[..] {
+            [x, y, width, height] if width > 0.0 && height > 0.0 => {
+                Some(Rect::new(x, y, x + width, y + height))
+            }
+            _ => None,
+        }
+    });
+    let aspect_ratio = root
+        .attribute("preserveAspectRatio")
+        .and_then(AspectRatio::parse)
+        .unwrap_or_default();
+    (view_box, aspect_ratio)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AspectRatio;
+    use kurbo::{Point, Rect};
+
+    #[test]
+    fn aspect_ratio_transform() {
+        let view_box = Rect::new(10.0, 10.0, 20.0, 30.0);
+        let viewport = Rect::new(0.0, 0.0, 40.0, 40.0);
+        let corners = |value: &str| {
+            let transform = AspectRatio::parse(value)
+                .unwrap()
+                .transform(view_box, viewport);
+            (
+                transform * Point::new(10.0, 10.0),
+                transform * Point::new(20.0, 30.0),
+            )
+        };
+        assert_eq!(
+            corners("xMidYMid"),
+            (Point::new(10.0, 0.0), Point::new(30.0, 40.0))
+        );
+        assert_eq!(
+            corners("defer xMaxYMin meet"),
+            (Point::new(20.0, 0.0), Point::new(40.0, 40.0))
+        );
+        assert_eq!(
+            corners("xMinYMax slice"),
+            (Point::new(0.0, -40.0), Point::new(40.0, 40.0))
+        );
+        assert_eq!(
+            corners("none"),
+            (Point::new(0.0, 0.0), Point::new(40.0, 40.0))
+        );
+        assert_eq!(AspectRatio::parse("xMidYMid stretch"), None);
+    }
+}
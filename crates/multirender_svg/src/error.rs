@@ -9,4 +9,6 @@ use thiserror::Error;
 pub enum Error {
     #[error("Error parsing svg: {0}")]
     Svg(#[from] usvg::Error),
+    #[error("Error reading svg file: {0}")]
+    Io(#[from] std::io::Error),
 }
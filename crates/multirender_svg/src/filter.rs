@@ -0,0 +1,549 @@
+//! Rendering of SVG filter effects, which are applied to the content of a group rendered to an image.
+//!
+//! The content of a filtered group is rendered to a premultiplied RGBA8 image covering the group's layer bounding box
+//! at device resolution. The filter primitives are applied to that image on the CPU, and the result is drawn in place
+//! of the content, so the group's clip path, masks, opacity and blend mode still apply to it.
+
+use std::sync::{Arc, LazyLock};
+
+use kurbo::{Affine, Rect};
+use multirender::PaintScene;
+use peniko::{Blob, ImageBrush, ImageData};
+use usvg::filter::{ColorInterpolation, ColorMatrixKind, CompositeOperator, Input, Kind};
+
+use crate::raster;
+use crate::render::Context;
+use crate::util;
+
+/// A premultiplied RGBA8 pixel.
+type Pixel = [u8; 4];
+
+/// Whether all primitives of a filter can be rendered.
+pub(crate) fn is_supported(filter: &usvg::filter::Filter) -> bool {
+    filter.primitives().iter().all(|primitive| {
+        matches!(
+            primitive.kind(),
+            Kind::ColorMatrix(_)
+                | Kind::Composite(_)
+                | Kind::DropShadow(_)
+                | Kind::Flood(_)
+                | Kind::GaussianBlur(_)
+                | Kind::Merge(_)
+                | Kind::Offset(_)
+        )
+    })
+}
+
+/// Render the children of `group` with the group's filters applied.
+///
+/// `transform` is the transform of the group relative to `global_transform`, as passed to
+/// [`render_group`](crate::render::render_group).
+pub(crate) fn draw<S: PaintScene, F: FnMut(&mut S, &usvg::Node)>(
+    scene: &mut S,
+    context: &Context<'_>,
+    group: &usvg::Group,
+    base_transform: Affine,
+    transform: Affine,
+    global_transform: Affine,
+    error_handler: &mut F,
+) {
+    let bounds = (global_transform * transform)
+        .transform_rect_bbox(util::to_rect(group.layer_bounding_box()));
+    let Some((image, image_transform)) = raster::render_group_to_image(
+        scene,
+        context,
+        group,
+        bounds,
+        base_transform,
+        global_transform,
+        error_handler,
+    ) else {
+        return;
+    };
+    let content_transform = image_transform.inverse() * global_transform;
+
+    let mut pixels: Vec<Pixel> = image
+        .data
+        .data()
+        .chunks_exact(4)
+        .map(|pixel| [pixel[0], pixel[1], pixel[2], pixel[3]])
+        .collect();
+    let size = Size {
+        width: image.width as usize,
+        height: image.height as usize,
+    };
+    for filter in group.filters() {
+        pixels = apply(filter, content_transform * transform, size, pixels);
+    }
+
+    let image = ImageData {
+        data: Blob::new(Arc::new(pixels.into_flattened())),
+        ..image
+    };
+    scene.draw_image(ImageBrush::new(image).as_ref(), image_transform);
+}
+
+#[derive(Clone, Copy)]
+struct Size {
+    width: usize,
+    height: usize,
+}
+
+/// The result of a filter primitive, which has the size of the whole image.
+#[derive(Clone)]
+struct Image {
+    pixels: Vec<Pixel>,
+    color_space: ColorInterpolation,
+}
+
+impl Image {
+    fn new(pixels: Vec<Pixel>, color_space: ColorInterpolation) -> Self {
+        Self {
+            pixels,
+            color_space,
+        }
+    }
+
+    fn into_color_space(mut self, color_space: ColorInterpolation) -> Self {
+        if self.color_space != color_space {
+            let table = match color_space {
+                ColorInterpolation::SRGB => &*LINEAR_RGB_TO_SRGB,
+                ColorInterpolation::LinearRGB => &*SRGB_TO_LINEAR_RGB,
+            };
+            for pixel in &mut self.pixels {
+                *pixel = convert_color(*pixel, table);
+            }
+            self.color_space = color_space;
+        }
+        self
+    }
+}
+
+/// Apply a filter to `source`, where `transform` maps the user space of the filtered group to image pixels.
+fn apply(
+    filter: &usvg::filter::Filter,
+    transform: Affine,
+    size: Size,
+    mut source: Vec<Pixel>,
+) -> Vec<Pixel> {
+    let region = to_pixel_rect(filter.rect(), transform);
+    clear_outside(&mut source, size, region);
+
+    let mut results: Vec<(&str, Image)> = Vec::new();
+    for primitive in filter.primitives() {
+        let input = |input: &Input| get_input(input, &source, &results);
+        let color_space = primitive.color_interpolation();
+        let mut result = match primitive.kind() {
+            Kind::ColorMatrix(fe) => {
+                let mut image = input(fe.input()).into_color_space(color_space);
+                color_matrix(fe.kind(), &mut image.pixels);
+                image
+            }
+            Kind::Composite(fe) => {
+                let source = input(fe.input1()).into_color_space(color_space);
+                let destination = input(fe.input2()).into_color_space(color_space);
+                Image::new(
+                    composite(fe.operator(), &source.pixels, &destination.pixels),
+                    color_space,
+                )
+            }
+            Kind::DropShadow(fe) => drop_shadow(
+                fe,
+                input(fe.input()).into_color_space(color_space),
+                transform,
+                size,
+            ),
+            Kind::Flood(fe) => {
+                let color = fe.color();
+                let pixel = premultiply([color.red, color.green, color.blue, fe.opacity().to_u8()]);
+                Image::new(vec![pixel; source.len()], ColorInterpolation::SRGB)
+            }
+            Kind::GaussianBlur(fe) => {
+                let mut image = input(fe.input()).into_color_space(color_space);
+                let (std_dev_x, std_dev_y) =
+                    scale_by(fe.std_dev_x().get(), fe.std_dev_y().get(), transform);
+                blur(&mut image.pixels, size, std_dev_x, std_dev_y);
+                image
+            }
+            Kind::Merge(fe) => {
+                let mut pixels = vec![[0; 4]; source.len()];
+                for merged in fe.inputs() {
+                    let merged = input(merged).into_color_space(color_space);
+                    pixels = composite(CompositeOperator::Over, &merged.pixels, &pixels);
+                }
+                Image::new(pixels, color_space)
+            }
+            Kind::Offset(fe) => {
+                let image = input(fe.input());
+                let (dx, dy) = scale_by(fe.dx(), fe.dy(), transform);
+                Image::new(offset(&image.pixels, size, dx, dy), image.color_space)
+            }
+            // Filters with other primitives are not rendered, see `is_supported`
+            _ => input(&Input::SourceGraphic),
+        };
+
+        // The result of a primitive is clipped to its subregion, except for offsets, which keep the subregion of
+        // their input.
+        if !matches!(primitive.kind(), Kind::Offset(_)) {
+            let subregion = to_pixel_rect(primitive.rect(), transform);
+            clear_outside(&mut result.pixels, size, subregion);
+        }
+        results.push((primitive.result(), result));
+    }
+
+    let Some((_, result)) = results.pop() else {
+        return vec![[0; 4]; source.len()];
+    };
+    let mut pixels = result.into_color_space(ColorInterpolation::SRGB).pixels;
+    clear_outside(&mut pixels, size, region);
+    pixels
+}
+
+fn get_input(input: &Input, source: &[Pixel], results: &[(&str, Image)]) -> Image {
+    match input {
+        Input::SourceGraphic => Image::new(source.to_vec(), ColorInterpolation::SRGB),
+        Input::SourceAlpha => Image::new(
+            source.iter().map(|pixel| [0, 0, 0, pixel[3]]).collect(),
+            ColorInterpolation::SRGB,
+        ),
+        Input::Reference(name) => results
+            .iter()
+            .rev()
+            .find(|(result, _)| result == name)
+            .map(|(_, image)| image.clone())
+            // usvg resolves references to unknown results to the previous result or the source graphic
+            .unwrap_or_else(|| get_input(&Input::SourceGraphic, source, results)),
+    }
+}
+
+/// Scale a distance in user space to image pixels along each axis.
+fn scale_by(x: f32, y: f32, transform: Affine) -> (f64, f64) {
+    let [a, b, c, d, _, _] = transform.as_coeffs();
+    (x as f64 * a.hypot(b), y as f64 * c.hypot(d))
+}
+
+/// The pixels covered by a rectangle in user space, rounded out to whole pixels.
+fn to_pixel_rect(rect: usvg::NonZeroRect, transform: Affine) -> Rect {
+    transform.transform_rect_bbox(util::to_rect(rect)).expand()
+}
+
+/// Make the pixels outside of `rect` transparent.
+fn clear_outside(pixels: &mut [Pixel], size: Size, rect: Rect) {
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        let (x, y) = ((i % size.width) as f64, (i / size.width) as f64);
+        if x < rect.x0 || x >= rect.x1 || y < rect.y0 || y >= rect.y1 {
+            *pixel = [0; 4];
+        }
+    }
+}
+
+fn offset(pixels: &[Pixel], size: Size, dx: f64, dy: f64) -> Vec<Pixel> {
+    let (dx, dy) = (dx.trunc() as isize, dy.trunc() as isize);
+    let mut result = vec![[0; 4]; pixels.len()];
+    for y in 0..size.height {
+        for x in 0..size.width {
+            let (source_x, source_y) = (x as isize - dx, y as isize - dy);
+            if (0..size.width as isize).contains(&source_x)
+                && (0..size.height as isize).contains(&source_y)
+            {
+                result[y * size.width + x] =
+                    pixels[source_y as usize * size.width + source_x as usize];
+            }
+        }
+    }
+    result
+}
+
+/// Composite `source` onto `destination` with a Porter-Duff operator or an arithmetic combination.
+fn composite(operator: CompositeOperator, source: &[Pixel], destination: &[Pixel]) -> Vec<Pixel> {
+    source
+        .iter()
+        .zip(destination)
+        .map(|(source, destination)| {
+            let [s, d] = [source, destination].map(|pixel| pixel.map(|c| c as f32 / 255.0));
+            let (source_alpha, destination_alpha) = (s[3], d[3]);
+            let result: [f32; 4] = match operator {
+                CompositeOperator::Arithmetic { k1, k2, k3, k4 } => {
+                    let alpha = (k1 * s[3] * d[3] + k2 * s[3] + k3 * d[3] + k4).clamp(0.0, 1.0);
+                    std::array::from_fn(|i| {
+                        (k1 * s[i] * d[i] + k2 * s[i] + k3 * d[i] + k4).clamp(0.0, alpha)
+                    })
+                }
+                _ => {
+                    let (fs, fd) = match operator {
+                        CompositeOperator::Over => (1.0, 1.0 - source_alpha),
+                        CompositeOperator::In => (destination_alpha, 0.0),
+                        CompositeOperator::Out => (1.0 - destination_alpha, 0.0),
+                        CompositeOperator::Atop => (destination_alpha, 1.0 - source_alpha),
+                        CompositeOperator::Xor => (1.0 - destination_alpha, 1.0 - source_alpha),
+                        CompositeOperator::Arithmetic { .. } => unreachable!(),
+                    };
+                    std::array::from_fn(|i| s[i] * fs + d[i] * fd)
+                }
+            };
+            result.map(|c| (c * 255.0).round().clamp(0.0, 255.0) as u8)
+        })
+        .collect()
+}
+
+fn drop_shadow(
+    fe: &usvg::filter::DropShadow,
+    input: Image,
+    transform: Affine,
+    size: Size,
+) -> Image {
+    let mut shadow = input.pixels.clone();
+    let (std_dev_x, std_dev_y) = scale_by(fe.std_dev_x().get(), fe.std_dev_y().get(), transform);
+    blur(&mut shadow, size, std_dev_x, std_dev_y);
+
+    let color = fe.color();
+    let opacity = fe.opacity().get();
+    let shadow = shadow
+        .iter()
+        .map(|pixel| {
+            let alpha = (opacity * pixel[3] as f32).round() as u8;
+            premultiply([color.red, color.green, color.blue, alpha])
+        })
+        .collect();
+    let shadow = Image::new(shadow, ColorInterpolation::SRGB).into_color_space(input.color_space);
+
+    let (dx, dy) = scale_by(fe.dx(), fe.dy(), transform);
+    let shadow = offset(&shadow.pixels, size, dx, dy);
+    Image::new(
+        composite(CompositeOperator::Over, &input.pixels, &shadow),
+        input.color_space,
+    )
+}
+
+fn color_matrix(kind: &ColorMatrixKind, pixels: &mut [Pixel]) {
+    // The matrix applies to the unpremultiplied color, with rows for red, green, blue and alpha, and columns for
+    // red, green, blue, alpha and an offset.
+    let matrix: [f32; 20] = match kind {
+        ColorMatrixKind::Matrix(matrix) => std::array::from_fn(|i| matrix[i]),
+        ColorMatrixKind::Saturate(value) => {
+            let v = value.get().max(0.0);
+            rgb_matrix([
+                0.213 + 0.787 * v,
+                0.715 - 0.715 * v,
+                0.072 - 0.072 * v,
+                0.213 - 0.213 * v,
+                0.715 + 0.285 * v,
+                0.072 - 0.072 * v,
+                0.213 - 0.213 * v,
+                0.715 - 0.715 * v,
+                0.072 + 0.928 * v,
+            ])
+        }
+        ColorMatrixKind::HueRotate(angle) => {
+            let (sin, cos) = angle.to_radians().sin_cos();
+            rgb_matrix([
+                0.213 + 0.787 * cos - 0.213 * sin,
+                0.715 - 0.715 * cos - 0.715 * sin,
+                0.072 - 0.072 * cos + 0.928 * sin,
+                0.213 - 0.213 * cos + 0.143 * sin,
+                0.715 + 0.285 * cos + 0.140 * sin,
+                0.072 - 0.072 * cos - 0.283 * sin,
+                0.213 - 0.213 * cos - 0.787 * sin,
+                0.715 - 0.715 * cos + 0.715 * sin,
+                0.072 + 0.928 * cos + 0.072 * sin,
+            ])
+        }
+        ColorMatrixKind::LuminanceToAlpha => [
+            0.0, 0.0, 0.0, 0.0, 0.0, //
+            0.0, 0.0, 0.0, 0.0, 0.0, //
+            0.0, 0.0, 0.0, 0.0, 0.0, //
+            0.2125, 0.7154, 0.0721, 0.0, 0.0,
+        ],
+    };
+
+    for pixel in pixels {
+        let color = unpremultiply(*pixel).map(|c| c as f32 / 255.0);
+        let result: [u8; 4] = std::array::from_fn(|row| {
+            let row = &matrix[row * 5..row * 5 + 5];
+            let value = (0..4).map(|i| row[i] * color[i]).sum::<f32>() + row[4];
+            (value.clamp(0.0, 1.0) * 255.0).round() as u8
+        });
+        *pixel = premultiply(result);
+    }
+}
+
+/// A color matrix that only transforms the red, green and blue channels.
+fn rgb_matrix(m: [f32; 9]) -> [f32; 20] {
+    [
+        m[0], m[1], m[2], 0.0, 0.0, //
+        m[3], m[4], m[5], 0.0, 0.0, //
+        m[6], m[7], m[8], 0.0, 0.0, //
+        0.0, 0.0, 0.0, 1.0, 0.0,
+    ]
+}
+
+/// Apply a Gaussian blur with standard deviations in pixels. Pixels outside of the image are transparent.
+fn blur(pixels: &mut [Pixel], size: Size, std_dev_x: f64, std_dev_y: f64) {
+    blur_axis(pixels, size, std_dev_x, false);
+    blur_axis(pixels, size, std_dev_y, true);
+}
+
+/// Standard deviations from which a blur is approximated with box blurs, which take constant time per pixel.
+const BOX_BLUR_THRESHOLD: f64 = 2.0;
+
+fn blur_axis(pixels: &mut [Pixel], size: Size, std_dev: f64, vertical: bool) {
+    // Tiny standard deviations have no visible effect
+    if std_dev < 0.05 {
+        return;
+    }
+    if std_dev >= BOX_BLUR_THRESHOLD {
+        for width in box_widths(std_dev) {
+            for_each_line(pixels, size, vertical, |input, output| {
+                box_blur_line(input, output, width / 2);
+            });
+        }
+    } else {
+        let radius = (std_dev * 3.0).ceil() as usize;
+        let mut kernel: Vec<f64> = (0..=2 * radius)
+            .map(|i| {
+                let x = i as f64 - radius as f64;
+                (-x * x / (2.0 * std_dev * std_dev)).exp()
+            })
+            .collect();
+        let sum: f64 = kernel.iter().sum();
+        kernel.iter_mut().for_each(|weight| *weight /= sum);
+        for_each_line(pixels, size, vertical, |input, output| {
+            convolve_line(input, output, &kernel);
+        });
+    }
+}
+
+/// The widths of five successive box blurs that approximate a Gaussian blur.
+///
+/// See <https://www.peterkovesi.com/papers/FastGaussianSmoothing.pdf>.
+fn box_widths(std_dev: f64) -> [usize; 5] {
+    const PASSES: f64 = 5.0;
+    let variance = std_dev * std_dev;
+    let ideal = (12.0 * variance / PASSES).sqrt() + 1.0;
+    let mut lower = ideal.floor() as usize;
+    if lower.is_multiple_of(2) {
+        lower -= 1;
+    }
+    let lower_f = lower as f64;
+    let lower_passes =
+        ((12.0 * variance - PASSES * lower_f * lower_f - 4.0 * PASSES * lower_f - 3.0 * PASSES)
+            / (-4.0 * lower_f - 4.0))
+            .round() as usize;
+    std::array::from_fn(|i| if i < lower_passes { lower } else { lower + 2 })
+}
+
+/// Call `f` with each row or column of the image, and store the line it outputs in its place.
+fn for_each_line(
+    pixels: &mut [Pixel],
+    size: Size,
+    vertical: bool,
+    mut f: impl FnMut(&[Pixel], &mut [Pixel]),
+) {
+    let (length, count) = if vertical {
+        (size.height, size.width)
+    } else {
+        (size.width, size.height)
+    };
+    let mut input = vec![[0; 4]; length];
+    let mut output = vec![[0; 4]; length];
+    for line in 0..count {
+        let index = |i| {
+            if vertical {
+                i * size.width + line
+            } else {
+                line * size.width + i
+            }
+        };
+        for (i, pixel) in input.iter_mut().enumerate() {
+            *pixel = pixels[index(i)];
+        }
+        f(&input, &mut output);
+        for (i, pixel) in output.iter().enumerate() {
+            pixels[index(i)] = *pixel;
+        }
+    }
+}
+
+fn box_blur_line(input: &[Pixel], output: &mut [Pixel], radius: usize) {
+    let window = (2 * radius + 1) as f32;
+    let mut sum = [0_u32; 4];
+    let add = |sum: &mut [u32; 4], pixel: &Pixel| {
+        for (sum, c) in sum.iter_mut().zip(pixel) {
+            *sum += *c as u32;
+        }
+    };
+    for pixel in input.iter().take(radius) {
+        add(&mut sum, pixel);
+    }
+    for (i, out) in output.iter_mut().enumerate() {
+        if let Some(pixel) = input.get(i + radius) {
+            add(&mut sum, pixel);
+        }
+        if let Some(pixel) = i.checked_sub(radius + 1).map(|j| &input[j]) {
+            for (sum, c) in sum.iter_mut().zip(pixel) {
+                *sum -= *c as u32;
+            }
+        }
+        *out = sum.map(|sum| (sum as f32 / window).round() as u8);
+    }
+}
+
+fn convolve_line(input: &[Pixel], output: &mut [Pixel], kernel: &[f64]) {
+    let radius = kernel.len() / 2;
+    for (i, out) in output.iter_mut().enumerate() {
+        let mut sum = [0.0; 4];
+        for (j, weight) in kernel.iter().enumerate() {
+            let Some(pixel) = (i + j).checked_sub(radius).and_then(|k| input.get(k)) else {
+                continue;
+            };
+            for (sum, c) in sum.iter_mut().zip(pixel) {
+                *sum += *c as f64 * weight;
+            }
+        }
+        *out = sum.map(|sum| sum.round().min(255.0) as u8);
+    }
+}
+
+fn premultiply([r, g, b, a]: Pixel) -> Pixel {
+    let alpha = a as f32 / 255.0;
+    let [r, g, b] = [r, g, b].map(|c| (c as f32 * alpha).round() as u8);
+    [r, g, b, a]
+}
+
+fn unpremultiply([r, g, b, a]: Pixel) -> Pixel {
+    if a == 0 {
+        return [0; 4];
+    }
+    let alpha = a as f32 / 255.0;
+    let [r, g, b] = [r, g, b].map(|c| (c as f32 / alpha).round().min(255.0) as u8);
+    [r, g, b, a]
+}
+
+fn convert_color(pixel: Pixel, table: &[u8; 256]) -> Pixel {
+    let [r, g, b, a] = unpremultiply(pixel);
+    premultiply([table[r as usize], table[g as usize], table[b as usize], a])
+}
+
+static SRGB_TO_LINEAR_RGB: LazyLock<[u8; 256]> = LazyLock::new(|| {
+    std::array::from_fn(|i| {
+        let c = i as f64 / 255.0;
+        let linear = if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        };
+        (linear * 255.0).round() as u8
+    })
+});
+
+static LINEAR_RGB_TO_SRGB: LazyLock<[u8; 256]> = LazyLock::new(|| {
+    std::array::from_fn(|i| {
+        let c = i as f64 / 255.0;
+        let srgb = if c <= 0.0031308 {
+            c * 12.92
+        } else {
+            1.055 * c.powf(1.0 / 2.4) - 0.055
+        };
+        (srgb * 255.0).round() as u8
+    })
+});
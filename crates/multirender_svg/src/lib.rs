@@ -3,8 +3,10 @@
 
 //! Render an SVG into any impl of [`multirender::PaintScene`].
 //!
-//! This currently lacks support for some important SVG features. Known missing features include: masking, filter effects, group backgrounds
-//! path shape-rendering, and patterns.
+//! This currently lacks support for some important SVG features. Known missing features include: group backgrounds,
+//! path shape-rendering and filter primitives other than `feColorMatrix`, `feComposite`, `feDropShadow`, `feFlood`,
+//! `feGaussianBlur`, `feMerge` and `feOffset`. Patterns, luminance masks and filter effects are rendered to images
+//! with `vello_cpu`, which requires the `patterns`, `masks` and `filters` features (enabled by default).
 
 // LINEBENDER LINT SET - lib.rs - v1
 // See https://linebender.org/wiki/canonical-lints/
@@ -20,13 +22,32 @@
 #![allow(missing_docs, clippy::shadow_unrelated, clippy::missing_errors_doc)]
 #![cfg_attr(test, allow(unused_crate_dependencies))] // Some dev dependencies are only used in tests
 
+mod clip;
+mod diagnostics;
+mod document;
 mod error;
+#[cfg(feature = "filters")]
+mod filter;
+mod mask;
+mod options;
+#[cfg(feature = "patterns")]
+mod pattern;
+#[cfg(any(feature = "patterns", feature = "masks", feature = "filters"))]
+mod raster;
 mod render;
+#[cfg(feature = "text")]
+mod text;
 mod util;
 
+pub use diagnostics::{Issue, IssueKind, Report};
+pub use document::SvgDocument;
 pub use error::Error;
+pub use options::RenderOptions;
 pub use usvg;
 
+use std::cell::RefCell;
+use std::path::Path;
+
 use kurbo::Affine;
 use multirender::PaintScene;
 
@@ -53,9 +74,63 @@ pub fn render_svg_str_with<S: PaintScene, F: FnMut(&mut S, &usvg::Node)>(
     transform: Affine,
     error_handler: &mut F,
 ) -> Result<(), Error> {
-    let opt = usvg::Options::default();
-    let tree = usvg::Tree::from_str(svg, &opt)?;
-    render_svg_tree_with(scene, &tree, transform, error_handler);
+    render_svg_str_with_options(
+        scene,
+        svg,
+        transform,
+        &RenderOptions::default(),
+        error_handler,
+    )
+}
+
+/// Append an SVG to an [`multirender::PaintScene`] with [`RenderOptions`] (with custom error handling).
+///
+/// See the [module level documentation](crate#unsupported-features) for a list of some unsupported svg features
+pub fn render_svg_str_with_options<S: PaintScene, F: FnMut(&mut S, &usvg::Node)>(
+    scene: &mut S,
+    svg: &str,
+    transform: Affine,
+    options: &RenderOptions,
+    error_handler: &mut F,
+) -> Result<(), Error> {
+    let tree = usvg::Tree::from_str(svg, &options.to_usvg_options())?;
+    render_svg_tree_with_options(scene, &tree, transform, options, error_handler);
+    Ok(())
+}
+
+/// Append an SVG file to an [`multirender::PaintScene`]. Relative paths of images are resolved from the directory of
+/// the file.
+///
+/// This will draw a red box over (some) unsupported elements.
+pub fn render_svg_file<S: PaintScene>(
+    scene: &mut S,
+    path: impl AsRef<Path>,
+    transform: Affine,
+) -> Result<(), Error> {
+    render_svg_file_with_options(
+        scene,
+        path,
+        transform,
+        &RenderOptions::default(),
+        &mut util::default_error_handler,
+    )
+}
+
+/// Append an SVG file to an [`multirender::PaintScene`] with [`RenderOptions`] (with custom error handling). Relative
+/// paths of images are resolved from the directory of the file, unless the options set a resources directory.
+///
+/// See the [module level documentation](crate#unsupported-features) for a list of some unsupported svg features
+pub fn render_svg_file_with_options<S: PaintScene, F: FnMut(&mut S, &usvg::Node)>(
+    scene: &mut S,
+    path: impl AsRef<Path>,
+    transform: Affine,
+    options: &RenderOptions,
+    error_handler: &mut F,
+) -> Result<(), Error> {
+    let path = path.as_ref();
+    let data = std::fs::read(path)?;
+    let tree = usvg::Tree::from_data(&data, &options.for_file(path).to_usvg_options())?;
+    render_svg_tree_with_options(scene, &tree, transform, options, error_handler);
     Ok(())
 }
 
@@ -74,9 +149,29 @@ pub fn render_svg_tree_with<S: PaintScene, F: FnMut(&mut S, &usvg::Node)>(
     svg: &usvg::Tree,
     transform: Affine,
     error_handler: &mut F,
+) {
+    render_svg_tree_with_options(
+        scene,
+        svg,
+        transform,
+        &RenderOptions::default(),
+        error_handler,
+    );
+}
+
+/// Append a [`usvg::Tree`] to an [`multirender::PaintScene`] with [`RenderOptions`] (with custom error handling).
+///
+/// See the [module level documentation](crate#unsupported-features) for a list of some unsupported svg features
+pub fn render_svg_tree_with_options<S: PaintScene, F: FnMut(&mut S, &usvg::Node)>(
+    scene: &mut S,
+    svg: &usvg::Tree,
+    transform: Affine,
+    options: &RenderOptions,
+    error_handler: &mut F,
 ) {
     render::render_group(
         scene,
+        &render::Context::new(options, svg, None),
         svg.root(),
         Affine::IDENTITY,
         transform,
@@ -84,10 +179,27 @@ pub fn render_svg_tree_with<S: PaintScene, F: FnMut(&mut S, &usvg::Node)>(
     );
 }
 
-#[cfg(test)]
-mod tests {
-    // CI will fail unless cargo nextest can execute at least one test per workspace.
-    // Delete this dummy test once we have an actual real test.
-    #[test]
-    fn dummy_test_until_we_have_a_real_test() {}
+/// Append a [`usvg::Tree`] to an [`multirender::PaintScene`] with [`RenderOptions`], and report the nodes that
+/// couldn't be rendered.
+///
+/// Unsupported nodes are drawn as well as they can be, without a red box. Nodes of SVG images nested in the tree are
+/// reported with the ids and coordinates of the nested SVG.
+pub fn render_svg_tree_with_diagnostics<S: PaintScene>(
+    scene: &mut S,
+    svg: &usvg::Tree,
+    transform: Affine,
+    options: &RenderOptions,
+) -> Report {
+    let issues = RefCell::new(Vec::new());
+    render::render_group(
+        scene,
+        &render::Context::new(options, svg, Some(&issues)),
+        svg.root(),
+        Affine::IDENTITY,
+        transform,
+        &mut |_, _| {},
+    );
+    Report {
+        issues: issues.into_inner(),
+    }
 }
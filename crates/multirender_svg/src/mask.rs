@@ -0,0 +1,135 @@
+//! Rendering of SVG masks, which are composited onto the masked content with layers.
+//!
+//! The masked content is drawn in a layer clipped to the mask's rectangle, and the mask is then drawn in a nested
+//! layer that is composited onto it with [`Compose::DestIn`], keeping the content where the mask is opaque. Alpha
+//! masks are drawn directly. Luminance masks are rendered to an image whose alpha is the luminance of the mask,
+//! which requires the `masks` feature.
+
+use kurbo::Affine;
+use multirender::PaintScene;
+use peniko::{BlendMode, Compose, Mix};
+
+use crate::render::{self, Context};
+use crate::util::to_rect;
+
+/// Whether a mask can be rendered with the enabled features.
+pub(crate) fn is_supported(mask: &usvg::Mask) -> bool {
+    match mask.kind() {
+        usvg::MaskType::Alpha => true,
+        usvg::MaskType::Luminance => cfg!(feature = "masks"),
+    }
+}
+
+/// The opacity of the layer that masked or clipped content is drawn in.
+///
+/// `vello_cpu` composites a layer with a compose mode like [`Compose::DestIn`] onto the wrong layer in tiles that a
+/// parent layer which only clips has clipped out, erasing content outside of the mask. An opacity below 1 gives the
+/// layer its own buffer in every tile, and rounds to fully opaque.
+pub(crate) const LAYER_ALPHA: f32 = 1.0 - f32::EPSILON;
+
+/// Push the layer that content masked by `mask` is drawn in. `transform` is the transform of the masked group.
+pub(crate) fn push_layer<S: PaintScene>(scene: &mut S, mask: &usvg::Mask, transform: Affine) {
+    scene.push_layer(
+        BlendMode::default(),
+        LAYER_ALPHA,
+        transform,
+        &to_rect(mask.rect()),
+    );
+}
+
+/// Apply `mask` to the content drawn since the matching [`push_layer`], and pop that layer.
+///
+/// `transform` is the transform of the masked group relative to `global_transform`, as passed to
+/// [`render::render_group`].
+pub(crate) fn pop_layer<S: PaintScene, F: FnMut(&mut S, &usvg::Node)>(
+    scene: &mut S,
+    context: &Context<'_>,
+    mask: &usvg::Mask,
+    transform: Affine,
+    global_transform: Affine,
+    error_handler: &mut F,
+) {
+    scene.push_layer(
+        BlendMode {
+            mix: Mix::Normal,
+            compose: Compose::DestIn,
+        },
+        1.0,
+        global_transform * transform,
+        &to_rect(mask.rect()),
+    );
+    let _referenced = context.referenced();
+    match mask.kind() {
+        usvg::MaskType::Alpha => {
+            render::render_group(
+                scene,
+                context,
+                mask.root(),
+                transform,
+                global_transform,
+                error_handler,
+            );
+        }
+        #[cfg(feature = "masks")]
+        usvg::MaskType::Luminance => {
+            draw_luminance(
+                scene,
+                context,
+                mask,
+                transform,
+                global_transform,
+                error_handler,
+            );
+        }
+        #[cfg(not(feature = "masks"))]
+        usvg::MaskType::Luminance => {}
+    }
+    scene.pop_layer();
+    scene.pop_layer();
+}
+
+/// Draw the content of a luminance mask as an image whose alpha is its luminance, at device resolution.
+#[cfg(feature = "masks")]
+fn draw_luminance<S: PaintScene, F: FnMut(&mut S, &usvg::Node)>(
+    scene: &mut S,
+    context: &Context<'_>,
+    mask: &usvg::Mask,
+    transform: Affine,
+    global_transform: Affine,
+    error_handler: &mut F,
+) {
+    use std::sync::Arc;
+
+    use peniko::{Blob, ImageBrush, ImageData};
+
+    use crate::raster;
+
+    let bounds = (global_transform * transform).transform_rect_bbox(to_rect(mask.rect()));
+    let Some((image, image_transform)) = raster::render_group_to_image(
+        scene,
+        context,
+        mask.root(),
+        bounds,
+        transform,
+        global_transform,
+        error_handler,
+    ) else {
+        return;
+    };
+    let luminance: Vec<u8> = image
+        .data
+        .data()
+        .chunks_exact(4)
+        .flat_map(|pixel| {
+            // The color is premultiplied, so this is the luminance of the color multiplied by its alpha
+            let [r, g, b] = [pixel[0], pixel[1], pixel[2]].map(f32::from);
+            let alpha = (0.2125 * r + 0.7154 * g + 0.0721 * b).round().min(255.0) as u8;
+            [alpha; 4]
+        })
+        .collect();
+    let image = ImageData {
+        data: Blob::new(Arc::new(luminance)),
+        ..image
+    };
+    scene.draw_image(ImageBrush::new(image).as_ref(), image_transform);
+}
@@ -0,0 +1,210 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use multirender::Paint;
+
+/// Options for loading an SVG and rendering it to a [`PaintScene`](multirender::PaintScene).
+///
+/// The loading options are used by the functions that parse SVGs, like
+/// [`render_svg_str_with_options`](crate::render_svg_str_with_options). Use [`RenderOptions::to_usvg_options`] to
+/// parse a [`usvg::Tree`] with them.
+///
+/// The tiles of patterns and the fonts of glyph runs are cached in the options and shared by their clones, so reusing
+/// the options to draw a tree again doesn't render its patterns or load its fonts again.
+#[derive(Clone, Debug, Default)]
+pub struct RenderOptions {
+    text_as_glyphs: bool,
+    resources_dir: Option<PathBuf>,
+    dpi: Option<f32>,
+    raster_scale: Option<f64>,
+    font_family: Option<String>,
+    #[cfg(feature = "text")]
+    fontdb: Option<Arc<usvg::fontdb::Database>>,
+    include_ids: Option<HashSet<String>>,
+    exclude_ids: HashSet<String>,
+    paint_override: Option<PaintOverride>,
+    #[cfg(feature = "text")]
+    pub(crate) fonts: crate::text::FontCache,
+    #[cfg(feature = "patterns")]
+    pub(crate) pattern_tiles: crate::pattern::TileCache,
+}
+
+/// A function that replaces the paint of a node, see [`RenderOptions::with_paint_override`].
+#[derive(Clone)]
+struct PaintOverride(Arc<PaintOverrideFn>);
+
+type PaintOverrideFn = dyn Fn(&usvg::Node, Paint) -> Paint + Send + Sync;
+
+impl fmt::Debug for PaintOverride {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PaintOverride")
+    }
+}
+
+impl RenderOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set whether text is drawn as glyph runs with [`PaintScene::draw_glyphs`](multirender::PaintScene::draw_glyphs)
+    /// instead of as paths. Defaults to `false`.
+    ///
+    /// Glyph runs keep the font of the text in the scene and can be hinted. Text that can't be drawn as glyph runs,
+    /// such as text using color or bitmap fonts or painted with gradients or patterns, is still drawn as paths.
+    /// Requires the `text` feature.
+    pub fn with_text_as_glyphs(mut self, text_as_glyphs: bool) -> Self {
+        self.text_as_glyphs = text_as_glyphs;
+        self
+    }
+
+    /// Whether text is drawn as glyph runs.
+    pub fn text_as_glyphs(&self) -> bool {
+        self.text_as_glyphs
+    }
+
+    /// Set the directory that relative paths of images are resolved from. By default, relative paths are resolved
+    /// from the working directory, or from the directory of the file for
+    /// [`render_svg_file_with_options`](crate::render_svg_file_with_options).
+    pub fn with_resources_dir(mut self, resources_dir: impl Into<PathBuf>) -> Self {
+        self.resources_dir = Some(resources_dir.into());
+        self
+    }
+
+    /// The directory that relative paths of images are resolved from, if set.
+    pub fn resources_dir(&self) -> Option<&Path> {
+        self.resources_dir.as_deref()
+    }
+
+    /// These options for loading the file at `path`, resolving relative paths of images from the directory of the
+    /// file unless a resources directory is set.
+    pub(crate) fn for_file(&self, path: &Path) -> Self {
+        let mut options = self.clone();
+        if options.resources_dir.is_none() {
+            options.resources_dir = path.parent().map(Path::to_path_buf);
+        }
+        options
+    }
+
+    /// Set the resolution used to convert physical units like `mm` and `in` to pixels. Defaults to 96.
+    pub fn with_dpi(mut self, dpi: f32) -> Self {
+        self.dpi = Some(dpi);
+        self
+    }
+
+    /// Set the resolution that [`SvgDocument`](crate::SvgDocument) renders patterns, masks and filters at when it
+    /// converts an SVG, as a scale of the size of the SVG. Defaults to 1.
+    ///
+    /// A document keeps these parts as images, which are blurry when the document is drawn larger than they were
+    /// rendered. Set this to the largest scale the document is drawn at, like the size it's drawn at divided by its
+    /// size times the scale factor of the display. The other functions render these parts at the resolution of the
+    /// transform they draw with, and don't use this. Scales that aren't positive are ignored.
+    pub fn with_raster_scale(mut self, raster_scale: f64) -> Self {
+        self.raster_scale = Some(raster_scale);
+        self
+    }
+
+    /// The resolution that [`SvgDocument`](crate::SvgDocument) renders patterns, masks and filters at, as a scale of
+    /// the size of the SVG.
+    pub fn raster_scale(&self) -> f64 {
+        self.raster_scale
+            .filter(|scale| scale.is_finite() && *scale > 0.0)
+            .unwrap_or(1.0)
+    }
+
+    /// Set the font family used for text that doesn't specify one, or that specifies one that isn't available.
+    /// Defaults to "Times New Roman".
+    pub fn with_font_family(mut self, font_family: impl Into<String>) -> Self {
+        self.font_family = Some(font_family.into());
+        self
+    }
+
+    /// Set the database of fonts that text is drawn with. Defaults to an empty database.
+    ///
+    /// The database is shared rather than copied, so an app can load its fonts, or the system fonts, once and use
+    /// them for every SVG it loads. Requires the `text` feature.
+    #[cfg(feature = "text")]
+    pub fn with_fontdb(mut self, fontdb: Arc<usvg::fontdb::Database>) -> Self {
+        self.fontdb = Some(fontdb);
+        self
+    }
+
+    /// Only draw the nodes with one of `ids`, and their descendants. Groups containing these nodes are still applied
+    /// to them, with their transforms, opacity, clip paths, masks and filters. By default, all nodes are drawn.
+    ///
+    /// The ids of elements referenced by other elements, like the content of patterns, masks and clip paths, are not
+    /// filtered, and neither are the ids of SVG images nested in the SVG.
+    pub fn with_include_ids(mut self, ids: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.include_ids = Some(ids.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Don't draw the nodes with one of `ids`, or their descendants, even if they are included by
+    /// [`RenderOptions::with_include_ids`].
+    pub fn with_exclude_ids(mut self, ids: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.exclude_ids = ids.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Whether the include filter of these options is set.
+    pub(crate) fn filters_ids(&self) -> bool {
+        self.include_ids.is_some()
+    }
+
+    /// Whether `id` is included by the include filter of these options.
+    pub(crate) fn is_included(&self, id: &str) -> bool {
+        !id.is_empty()
+            && self
+                .include_ids
+                .as_ref()
+                .is_some_and(|include_ids| include_ids.contains(id))
+    }
+
+    /// Whether `id` is excluded by these options.
+    pub(crate) fn is_excluded(&self, id: &str) -> bool {
+        !id.is_empty() && self.exclude_ids.contains(id)
+    }
+
+    /// Replace the paints that fills and strokes are drawn with. `paint_override` is called with the node that is
+    /// painted and the paint converted from the SVG, and returns the paint to draw with, for example to tint an icon
+    /// with a solid color.
+    ///
+    /// The node is the path that is painted, or the text node for text drawn as glyph runs and for its decorations.
+    /// As with [`RenderOptions::with_include_ids`], the content of patterns, masks and nested SVG images is painted as
+    /// is.
+    pub fn with_paint_override(
+        mut self,
+        paint_override: impl Fn(&usvg::Node, Paint) -> Paint + Send + Sync + 'static,
+    ) -> Self {
+        self.paint_override = Some(PaintOverride(Arc::new(paint_override)));
+        self
+    }
+
+    /// The paint that `node` is drawn with instead of `paint`.
+    pub(crate) fn override_paint(&self, node: &usvg::Node, paint: Paint) -> Paint {
+        match &self.paint_override {
+            Some(PaintOverride(paint_override)) => paint_override(node, paint),
+            None => paint,
+        }
+    }
+
+    /// The [`usvg::Options`] for parsing an SVG with these options.
+    pub fn to_usvg_options(&self) -> usvg::Options<'static> {
+        let mut options = usvg::Options {
+            resources_dir: self.resources_dir.clone(),
+            ..usvg::Options::default()
+        };
+        if let Some(dpi) = self.dpi {
+            options.dpi = dpi;
+        }
+        if let Some(font_family) = &self.font_family {
+            options.font_family.clone_from(font_family);
+        }
+        #[cfg(feature = "text")]
+        if let Some(fontdb) = &self.fontdb {
+            options.fontdb = Arc::clone(fontdb);
+        }
+        options
+    }
+}
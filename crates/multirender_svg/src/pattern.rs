@@ -0,0 +1,217 @@
+//! Rendering of SVG patterns, which are drawn to an image tile that is repeated.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use kurbo::Affine;
+use multirender::Paint;
+use multirender::recording::Scene;
+use peniko::{Extend, ImageBrush, ImageData};
+
+use crate::raster::{self, MAX_IMAGE_SIZE};
+use crate::render::{self, Context};
+use crate::util;
+
+/// Pattern tiles rendered by previous draws, shared by clones of the [`RenderOptions`](crate::RenderOptions) they are
+/// rendered with, so that drawing a tree again doesn't render its patterns again.
+///
+/// Tiles are keyed by the address of the pattern they are rendered from, whose `Arc` is kept so that the address
+/// isn't reused while the tile is cached. Only the last tile size used for each pattern is kept, and the tiles of
+/// patterns that are no longer used by any tree are evicted when a tile is added.
+#[derive(Clone, Default)]
+pub(crate) struct TileCache(Arc<Mutex<HashMap<usize, CachedTile>>>);
+
+struct CachedTile {
+    pattern: Arc<usvg::Pattern>,
+    width: u32,
+    height: u32,
+    image: ImageData,
+    /// The unsupported nodes of the pattern's content, which are reported every time the tile is drawn
+    unsupported: Vec<usvg::Node>,
+}
+
+impl fmt::Debug for TileCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TileCache")
+    }
+}
+
+impl TileCache {
+    fn get(&self, pattern: &Arc<usvg::Pattern>, width: u32, height: u32) -> Option<CachedTile> {
+        let tiles = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        tiles
+            .get(&key(pattern))
+            .filter(|tile| tile.width == width && tile.height == height)
+            .map(|tile| CachedTile {
+                pattern: Arc::clone(&tile.pattern),
+                unsupported: tile.unsupported.clone(),
+                image: tile.image.clone(),
+                ..*tile
+            })
+    }
+
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.0.lock().unwrap().len()
+    }
+
+    fn insert(&self, tile: CachedTile) {
+        let mut tiles = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        tiles.retain(|_, tile| Arc::strong_count(&tile.pattern) > 1);
+        tiles.insert(key(&tile.pattern), tile);
+    }
+}
+
+fn key(pattern: &Arc<usvg::Pattern>) -> usize {
+    Arc::as_ptr(pattern) as usize
+}
+
+/// Render the content of a pattern to an image tile at the resolution it will be drawn at, returning a repeating
+/// image brush and its brush transform. Tiles are cached in the [`TileCache`] of the render options.
+///
+/// `transform` is the transform of the shape that is filled or stroked with the pattern. Unsupported content of the
+/// pattern is passed to `error_handler`, which is a trait object because patterns can contain shapes painted with
+/// patterns.
+pub(crate) fn to_brush(
+    pattern: &Arc<usvg::Pattern>,
+    context: &Context<'_>,
+    opacity: usvg::Opacity,
+    transform: Affine,
+    error_handler: &mut dyn FnMut(&mut Scene, &usvg::Node),
+) -> (Paint, Affine) {
+    let pattern_transform = util::to_affine(&pattern.transform());
+    let rect = pattern.rect();
+    let (rect_width, rect_height) = (rect.width() as f64, rect.height() as f64);
+
+    // Render the tile at the scale the pattern is drawn at, snapping the scale so that the tile is a whole number of
+    // pixels and repeats without seams.
+    let [a, b, c, d, _, _] = (transform * pattern_transform).as_coeffs();
+    let tile_width = (rect_width * a.hypot(b)).round().clamp(1.0, MAX_IMAGE_SIZE);
+    let tile_height = (rect_height * c.hypot(d))
+        .round()
+        .clamp(1.0, MAX_IMAGE_SIZE);
+    let (sx, sy) = (tile_width / rect_width, tile_height / rect_height);
+    let (width, height) = (tile_width as u32, tile_height as u32);
+
+    // Issues are reported against the nodes of the tree as they are rendered, so tiles aren't reused while they are
+    // collected
+    let cache = &context.options.pattern_tiles;
+    let cached = if context.collects_issues() {
+        None
+    } else {
+        cache.get(pattern, width, height)
+    };
+    let image = match cached {
+        Some(tile) => {
+            for node in &tile.unsupported {
+                error_handler(&mut Scene::new(), node);
+            }
+            tile.image
+        }
+        None => {
+            let mut tile = Scene::new();
+            let mut unsupported = Vec::new();
+            let (content, content_transform) = pattern_content(pattern.root());
+            let _referenced = context.referenced();
+            render::render_group(
+                &mut tile,
+                context,
+                content,
+                content_transform,
+                Affine::scale_non_uniform(sx, sy),
+                &mut |scene: &mut Scene, node: &usvg::Node| {
+                    unsupported.push(node.clone());
+                    error_handler(scene, node);
+                },
+            );
+
+            let image = raster::render_to_image(tile, width, height);
+            cache.insert(CachedTile {
+                pattern: Arc::clone(pattern),
+                width,
+                height,
+                image: image.clone(),
+                unsupported,
+            });
+            image
+        }
+    };
+    let image = ImageBrush::new(image)
+        .with_extend(Extend::Repeat)
+        .with_alpha(opacity.get());
+
+    let brush_transform = pattern_transform
+        * Affine::translate((rect.x() as f64, rect.y() as f64))
+        * Affine::scale_non_uniform(1.0 / sx, 1.0 / sy);
+    (Paint::Image(image), brush_transform)
+}
+
+/// The group to render for the content of a pattern, and the transform to render it with.
+///
+/// Once the bounding box of the element using a pattern is known, usvg wraps the content of patterns with
+/// `objectBoundingBox` content units or a `viewBox` in a group with the corresponding transform, without updating
+/// the absolute transforms of the wrapped content. In that case the content of the wrapper is rendered with the
+/// wrapper's transform.
+fn pattern_content(root: &usvg::Group) -> (&usvg::Group, Affine) {
+    let [usvg::Node::Group(wrapper)] = root.children() else {
+        return (root, Affine::IDENTITY);
+    };
+    let wrapper_transform = wrapper.abs_transform();
+    let is_stale = wrapper.children().first().is_some_and(|child| match child {
+        usvg::Node::Group(group) => {
+            group.abs_transform() != wrapper_transform.pre_concat(group.transform())
+        }
+        usvg::Node::Path(_) | usvg::Node::Text(_) => child.abs_transform() != wrapper_transform,
+        usvg::Node::Image(_) => false,
+    });
+
+    if is_stale {
+        (wrapper, util::to_affine(&wrapper_transform))
+    } else {
+        (root, Affine::IDENTITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kurbo::Affine;
+    use multirender::recording::Scene;
+
+    use crate::RenderOptions;
+
+    const SVG: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="20">
+        <pattern id="p" width="10" height="10" patternUnits="userSpaceOnUse">
+            <rect width="5" height="5" fill="red"/>
+        </pattern>
+        <rect width="20" height="20" fill="url(#p)"/>
+    </svg>"#;
+
+    fn render(tree: &usvg::Tree, options: &RenderOptions) {
+        crate::render_svg_tree_with_options(
+            &mut Scene::new(),
+            tree,
+            Affine::IDENTITY,
+            options,
+            &mut |_, node| panic!("unsupported node: {node:?}"),
+        );
+    }
+
+    #[test]
+    fn tiles_of_dropped_trees_are_evicted() {
+        let options = RenderOptions::new();
+        let tree = usvg::Tree::from_str(SVG, &usvg::Options::default()).unwrap();
+        render(&tree, &options);
+        render(&tree, &options);
+        assert_eq!(options.pattern_tiles.len(), 1);
+
+        // The tile of a dropped tree is evicted when another one is cached
+        let other = usvg::Tree::from_str(SVG, &usvg::Options::default()).unwrap();
+        render(&other, &options);
+        assert_eq!(options.pattern_tiles.len(), 2);
+        drop(tree);
+        let tree = usvg::Tree::from_str(SVG, &usvg::Options::default()).unwrap();
+        render(&tree, &options);
+        assert_eq!(options.pattern_tiles.len(), 2);
+    }
+}
@@ -0,0 +1,100 @@
+//! Rendering of scenes to images with `vello_cpu`, for SVG features that are drawn through an intermediate image.
+
+use std::cell::RefCell;
+use std::sync::Arc;
+
+use kurbo::Affine;
+use multirender::recording::Scene;
+use multirender::{ImageRenderer, PaintScene};
+use multirender_vello_cpu::VelloCpuImageRenderer;
+use peniko::{Blob, ImageAlphaType, ImageData, ImageFormat};
+
+#[cfg(any(feature = "masks", feature = "filters"))]
+use crate::render::{self, Context};
+
+/// The maximum width and height of an intermediate image, in pixels. Content that would need a larger image is
+/// rendered at a lower resolution.
+pub(crate) const MAX_IMAGE_SIZE: f64 = 4096.0;
+
+thread_local! {
+    /// The renderer used for intermediate images on this thread and its size, which is reused between images so
+    /// that its buffers aren't allocated for every image.
+    static RENDERER: RefCell<Option<(VelloCpuImageRenderer, u32, u32)>> = const { RefCell::new(None) };
+}
+
+/// Render a scene to a premultiplied RGBA8 image.
+pub(crate) fn render_to_image(scene: Scene, width: u32, height: u32) -> ImageData {
+    let mut data = Vec::new();
+    RENDERER.with_borrow_mut(|renderer| {
+        let renderer = match renderer {
+            Some((renderer, w, h)) if (*w, *h) == (width, height) => renderer,
+            Some((renderer, w, h)) => {
+                renderer.resize(width, height);
+                (*w, *h) = (width, height);
+                renderer
+            }
+            None => {
+                &mut renderer
+                    .insert((VelloCpuImageRenderer::new(width, height), width, height))
+                    .0
+            }
+        };
+        renderer.render_to_vec(
+            |painter| painter.append_scene(scene, Affine::IDENTITY),
+            &mut data,
+        );
+        renderer.reset();
+    });
+    ImageData {
+        data: Blob::new(Arc::new(data)),
+        format: ImageFormat::Rgba8,
+        alpha_type: ImageAlphaType::AlphaPremultiplied,
+        width,
+        height,
+    }
+}
+
+/// Render the children of `group` to an image covering `bounds` at device resolution, returning the image and the
+/// transform that draws it in place of the content. Returns `None` if `bounds` are empty.
+///
+/// `bounds` are in the coordinates of `scene`, and `base_transform` and `global_transform` are as passed to
+/// [`render::render_group`]. The content is drawn to its own scene, so unsupported nodes are collected and reported
+/// against `scene`, the scene that is drawn to.
+#[cfg(any(feature = "masks", feature = "filters"))]
+pub(crate) fn render_group_to_image<S: PaintScene, F: FnMut(&mut S, &usvg::Node)>(
+    scene: &mut S,
+    context: &Context<'_>,
+    group: &usvg::Group,
+    bounds: kurbo::Rect,
+    base_transform: Affine,
+    global_transform: Affine,
+    error_handler: &mut F,
+) -> Option<(ImageData, Affine)> {
+    let bounds = bounds.expand();
+    if bounds.is_zero_area() {
+        return None;
+    }
+    let scale = (MAX_IMAGE_SIZE / bounds.width())
+        .min(MAX_IMAGE_SIZE / bounds.height())
+        .min(1.0);
+    let width = (bounds.width() * scale).ceil() as u32;
+    let height = (bounds.height() * scale).ceil() as u32;
+    let image_transform = Affine::translate(bounds.origin().to_vec2()) * Affine::scale(1.0 / scale);
+
+    let mut unsupported = Vec::new();
+    let mut collect_unsupported = |_: &mut Scene, node: &usvg::Node| unsupported.push(node.clone());
+    let mut content = Scene::new();
+    render::render_group(
+        &mut content,
+        context,
+        group,
+        base_transform,
+        image_transform.inverse() * global_transform,
+        &mut (&mut collect_unsupported as &mut dyn FnMut(&mut Scene, &usvg::Node)),
+    );
+    for node in &unsupported {
+        error_handler(scene, node);
+    }
+
+    Some((render_to_image(content, width, height), image_transform))
+}
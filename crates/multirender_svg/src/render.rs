@@ -1,44 +1,233 @@
 // Copyright 2024 the Vello Authors
 // SPDX-License-Identifier: Apache-2.0 OR MIT
 
-use crate::util;
+use crate::diagnostics::{Issue, IssueKind};
+#[cfg(feature = "filters")]
+use crate::filter;
+#[cfg(feature = "text")]
+use crate::text;
+use crate::{RenderOptions, clip, mask, util};
 use kurbo::{Affine, BezPath};
-use multirender::PaintScene;
+use multirender::{Paint, PaintScene};
 use peniko::{BlendMode, Fill};
+use std::cell::{Cell, RefCell};
 use usvg::{Node, Path};
 
+/// State shared by the rendering of the nodes of a tree.
+pub(crate) struct Context<'a> {
+    pub(crate) options: &'a RenderOptions,
+    /// The issues found while rendering, if they are collected.
+    issues: Option<&'a RefCell<Vec<Issue>>>,
+    /// The nodes that are drawn by the nodes being rendered.
+    selection: Cell<Selection>,
+    /// The font database of the tree, whose fonts are cached in the options.
+    #[cfg(feature = "text")]
+    pub(crate) fontdb: &'a std::sync::Arc<usvg::fontdb::Database>,
+}
+
+/// Which nodes are drawn, according to the ids included and excluded by the options.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Selection {
+    /// The nodes that are included and not excluded, and the groups containing them.
+    Filtered,
+    /// The nodes that are not excluded, inside an included node.
+    Included,
+    /// All nodes, inside elements referenced by other elements, like patterns and masks, which aren't filtered and
+    /// whose paint isn't overridden.
+    All,
+}
+
+/// Restores the selection of a context when dropped.
+pub(crate) struct RestoreSelection<'a> {
+    selection: &'a Cell<Selection>,
+    previous: Selection,
+}
+
+impl Drop for RestoreSelection<'_> {
+    fn drop(&mut self) {
+        self.selection.set(self.previous);
+    }
+}
+
+impl<'a> Context<'a> {
+    #[cfg_attr(not(feature = "text"), allow(unused_variables))]
+    pub(crate) fn new(
+        options: &'a RenderOptions,
+        tree: &'a usvg::Tree,
+        issues: Option<&'a RefCell<Vec<Issue>>>,
+    ) -> Self {
+        let selection = if options.filters_ids() {
+            Selection::Filtered
+        } else {
+            Selection::Included
+        };
+        Self {
+            options,
+            issues,
+            selection: Cell::new(selection),
+            #[cfg(feature = "text")]
+            fontdb: tree.fontdb(),
+        }
+    }
+
+    /// Whether the issues found while rendering are collected.
+    #[cfg(feature = "patterns")]
+    pub(crate) fn collects_issues(&self) -> bool {
+        self.issues.is_some()
+    }
+
+    /// The context for rendering an SVG nested in the SVG of this context, which collects issues into the same list.
+    fn nested<'b>(&'b self, tree: &'b usvg::Tree) -> Context<'b> {
+        let context = Context::new(self.options, tree, self.issues);
+        context.selection.set(Selection::All);
+        context
+    }
+
+    /// Render the content of an element referenced by another element, like a pattern or mask, with all of its nodes
+    /// and their paints. The referencing node has already been selected.
+    pub(crate) fn referenced(&self) -> RestoreSelection<'_> {
+        self.select_as(Selection::All)
+    }
+
+    /// Select `node` to be drawn according to the ids included and excluded by the options, until the returned value
+    /// is dropped, or return `None` if it isn't drawn.
+    fn select(&self, node: &Node) -> Option<RestoreSelection<'_>> {
+        let id = node.id();
+        match self.selection.get() {
+            Selection::All => Some(self.select_as(Selection::All)),
+            _ if self.options.is_excluded(id) => None,
+            Selection::Included => Some(self.select_as(Selection::Included)),
+            Selection::Filtered if self.options.is_included(id) => {
+                Some(self.select_as(Selection::Included))
+            }
+            Selection::Filtered => match node {
+                Node::Group(group) if self.contains_included(group) => {
+                    Some(self.select_as(Selection::Filtered))
+                }
+                _ => None,
+            },
+        }
+    }
+
+    /// Whether `group` contains an included node that isn't excluded.
+    fn contains_included(&self, group: &usvg::Group) -> bool {
+        group.children().iter().any(|node| {
+            let id = node.id();
+            !self.options.is_excluded(id)
+                && (self.options.is_included(id)
+                    || matches!(node, Node::Group(group) if self.contains_included(group)))
+        })
+    }
+
+    fn select_as(&self, selection: Selection) -> RestoreSelection<'_> {
+        RestoreSelection {
+            selection: &self.selection,
+            previous: self.selection.replace(selection),
+        }
+    }
+
+    /// The paint that `node` is drawn with instead of `paint`.
+    pub(crate) fn paint(&self, node: &Node, paint: Paint) -> Paint {
+        if self.selection.get() == Selection::All {
+            paint
+        } else {
+            self.options.override_paint(node, paint)
+        }
+    }
+}
+
+/// Report a node that can't be rendered to the issues of `context` and to `error_handler`.
+pub(crate) fn report<S: PaintScene, F: FnMut(&mut S, &usvg::Node)>(
+    scene: &mut S,
+    context: &Context<'_>,
+    node: &Node,
+    kind: IssueKind,
+    error_handler: &mut F,
+) {
+    if let Some(issues) = context.issues {
+        issues.borrow_mut().push(Issue::new(node, kind));
+    }
+    error_handler(scene, node);
+}
+
+/// Report a child of `group` like [`report`]. usvg moves the ids of some elements, like images, to a group that wraps
+/// them, so the id of `group` is used if it only wraps the node.
+fn report_child<S: PaintScene, F: FnMut(&mut S, &usvg::Node)>(
+    scene: &mut S,
+    context: &Context<'_>,
+    group: &usvg::Group,
+    node: &Node,
+    kind: IssueKind,
+    error_handler: &mut F,
+) {
+    if let Some(issues) = context.issues {
+        let mut issue = Issue::new(node, kind);
+        if issue.id.is_empty() && group.children().len() == 1 {
+            issue.id = group.id().to_string();
+        }
+        issues.borrow_mut().push(issue);
+    }
+    error_handler(scene, node);
+}
+
 pub(crate) fn render_group<S: PaintScene, F: FnMut(&mut S, &usvg::Node)>(
     scene: &mut S,
+    context: &Context<'_>,
     group: &usvg::Group,
-    transform: Affine,
+    base_transform: Affine,
     global_transform: Affine,
     error_handler: &mut F,
 ) {
     for node in group.children() {
-        let transform = transform * util::to_affine(&node.abs_transform());
+        let Some(_selection) = context.select(node) else {
+            continue;
+        };
+        let transform = base_transform * util::to_affine(&node.abs_transform());
         match node {
             usvg::Node::Group(g) => {
+                let masks: Vec<&usvg::Mask> =
+                    std::iter::successors(g.mask(), |mask| mask.mask()).collect();
+                // A mask without content hides the group entirely
+                if masks.iter().any(|mask| !mask.root().has_children()) {
+                    continue;
+                }
+
                 let alpha = g.opacity().get();
                 let is_fully_opaque = alpha >= 1.0;
                 let mix = util::to_mix(g.blend_mode());
 
-                // Support clip-path with a single path
-                let clip_path = g
-                    .clip_path()
-                    .and_then(|path| path.root().children().first());
+                let clip = g.clip_path();
+                if clip.is_some_and(|clip| !clip::is_supported(clip)) {
+                    report(scene, context, node, IssueKind::ComplexClip, error_handler);
+                }
+                let clip_path = clip.and_then(clip::as_path);
 
-                let did_push_layer = match clip_path {
-                    // If there is a clip path, then push a layer that clips using it
-                    Some(usvg::Node::Path(clip_path)) => {
-                        let local_path = util::to_bez_path(clip_path);
+                let did_push_layer = match (clip, &clip_path) {
+                    // If the clip path is a single shape, then push a layer that clips using it
+                    (Some(clip), Some(clip_path)) => {
                         scene.push_layer(
                             BlendMode {
                                 mix,
                                 compose: peniko::Compose::SrcOver,
                             },
                             alpha,
-                            global_transform * transform,
-                            &local_path,
+                            clip::transform(clip, global_transform * transform),
+                            clip_path,
+                        );
+
+                        true
+                    }
+                    // Else if there is a clip path, then push a layer clipped to its bounds, which the clip path is
+                    // applied to before the layer is popped
+                    (Some(clip), None) => {
+                        scene.push_layer(
+                            BlendMode {
+                                mix,
+                                compose: peniko::Compose::SrcOver,
+                            },
+                            alpha.min(mask::LAYER_ALPHA),
+                            clip::transform(clip, global_transform * transform),
+                            &clip::bounds(clip),
                         );
 
                         true
@@ -68,29 +257,67 @@ pub(crate) fn render_group<S: PaintScene, F: FnMut(&mut S, &usvg::Node)>(
                     _ => false,
                 };
 
-                render_group(scene, g, Affine::IDENTITY, global_transform, error_handler);
+                // Masks are applied to the content of the group before its opacity and blend mode
+                let masks = if masks.iter().all(|mask| mask::is_supported(mask)) {
+                    masks
+                } else {
+                    report(scene, context, node, IssueKind::Mask, error_handler);
+                    Vec::new()
+                };
+                for mask in &masks {
+                    mask::push_layer(scene, mask, global_transform * transform);
+                }
+
+                if g.filters().is_empty() {
+                    render_group(
+                        scene,
+                        context,
+                        g,
+                        base_transform,
+                        global_transform,
+                        error_handler,
+                    );
+                } else {
+                    draw_filtered(
+                        scene,
+                        context,
+                        node,
+                        g,
+                        base_transform,
+                        transform,
+                        global_transform,
+                        error_handler,
+                    );
+                }
+
+                for mask in masks.iter().rev() {
+                    mask::pop_layer(
+                        scene,
+                        context,
+                        mask,
+                        transform,
+                        global_transform,
+                        error_handler,
+                    );
+                }
 
+                if let Some(clip) = clip.filter(|_| clip_path.is_none()) {
+                    clip::apply(scene, clip, global_transform * transform);
+                }
                 if did_push_layer {
                     scene.pop_layer();
                 }
             }
             usvg::Node::Path(path) => {
-                if !path.is_visible() {
-                    continue;
-                }
-                let local_path = util::to_bez_path(path);
-
-                let transform = global_transform * transform;
-                match path.paint_order() {
-                    usvg::PaintOrder::FillAndStroke => {
-                        fill(scene, error_handler, path, transform, &local_path, node);
-                        stroke(scene, error_handler, path, transform, &local_path, node);
-                    }
-                    usvg::PaintOrder::StrokeAndFill => {
-                        stroke(scene, error_handler, path, transform, &local_path, node);
-                        fill(scene, error_handler, path, transform, &local_path, node);
-                    }
-                }
+                draw_path(
+                    scene,
+                    context,
+                    node,
+                    path,
+                    transform,
+                    global_transform,
+                    error_handler,
+                );
             }
             usvg::Node::Image(img) => {
                 if !img.is_visible() {
@@ -105,23 +332,38 @@ pub(crate) fn render_group<S: PaintScene, F: FnMut(&mut S, &usvg::Node)>(
                         {
                             let Ok(decoded_image) = util::decode_raw_raster_image(img.kind())
                             else {
-                                error_handler(scene, node);
+                                report_child(
+                                    scene,
+                                    context,
+                                    group,
+                                    node,
+                                    IssueKind::ImageDecode,
+                                    error_handler,
+                                );
                                 continue;
                             };
                             let image = util::into_image(decoded_image);
-                            let image_ts = global_transform * util::to_affine(&img.abs_transform());
+                            let image_ts = global_transform * transform;
                             scene.draw_image(image.as_ref(), image_ts);
                         }
 
                         #[cfg(not(feature = "image"))]
                         {
-                            error_handler(scene, node);
+                            report_child(
+                                scene,
+                                context,
+                                group,
+                                node,
+                                IssueKind::ImageDecode,
+                                error_handler,
+                            );
                             continue;
                         }
                     }
                     usvg::ImageKind::SVG(svg) => {
                         render_group(
                             scene,
+                            &context.nested(svg),
                             svg.root(),
                             transform,
                             global_transform,
@@ -131,8 +373,22 @@ pub(crate) fn render_group<S: PaintScene, F: FnMut(&mut S, &usvg::Node)>(
                 }
             }
             usvg::Node::Text(text) => {
+                #[cfg(feature = "text")]
+                if context.options.text_as_glyphs() && text::can_draw_glyphs(context, text) {
+                    text::draw_glyphs(
+                        scene,
+                        context,
+                        node,
+                        text,
+                        transform,
+                        global_transform,
+                        error_handler,
+                    );
+                    continue;
+                }
                 render_group(
                     scene,
+                    context,
                     text.flattened(),
                     transform,
                     global_transform,
@@ -143,8 +399,114 @@ pub(crate) fn render_group<S: PaintScene, F: FnMut(&mut S, &usvg::Node)>(
     }
 }
 
+/// Draw a path with the transform `transform` relative to `global_transform`. `node` is the node that is reported to
+/// `error_handler` if the path can't be drawn.
+pub(crate) fn draw_path<S: PaintScene, F: FnMut(&mut S, &usvg::Node)>(
+    scene: &mut S,
+    context: &Context<'_>,
+    node: &Node,
+    path: &Path,
+    transform: Affine,
+    global_transform: Affine,
+    error_handler: &mut F,
+) {
+    if !path.is_visible() {
+        return;
+    }
+    let local_path = util::to_bez_path(path);
+
+    let transform = global_transform * transform;
+    match path.paint_order() {
+        usvg::PaintOrder::FillAndStroke => {
+            fill(
+                scene,
+                context,
+                error_handler,
+                path,
+                transform,
+                &local_path,
+                node,
+            );
+            stroke(
+                scene,
+                context,
+                error_handler,
+                path,
+                transform,
+                &local_path,
+                node,
+            );
+        }
+        usvg::PaintOrder::StrokeAndFill => {
+            stroke(
+                scene,
+                context,
+                error_handler,
+                path,
+                transform,
+                &local_path,
+                node,
+            );
+            fill(
+                scene,
+                context,
+                error_handler,
+                path,
+                transform,
+                &local_path,
+                node,
+            );
+        }
+    }
+}
+
+/// Render the children of a group with its filters applied, with the `filters` feature. Groups with unsupported
+/// filters are passed to `error_handler` and rendered without filters.
+#[allow(clippy::too_many_arguments)]
+fn draw_filtered<S: PaintScene, F: FnMut(&mut S, &usvg::Node)>(
+    scene: &mut S,
+    context: &Context<'_>,
+    node: &Node,
+    group: &usvg::Group,
+    base_transform: Affine,
+    transform: Affine,
+    global_transform: Affine,
+    error_handler: &mut F,
+) {
+    #[cfg(feature = "filters")]
+    if group
+        .filters()
+        .iter()
+        .all(|filter| filter::is_supported(filter))
+    {
+        filter::draw(
+            scene,
+            context,
+            group,
+            base_transform,
+            transform,
+            global_transform,
+            error_handler,
+        );
+        return;
+    }
+
+    #[cfg(not(feature = "filters"))]
+    let _ = transform;
+    report(scene, context, node, IssueKind::Filter, error_handler);
+    render_group(
+        scene,
+        context,
+        group,
+        base_transform,
+        global_transform,
+        error_handler,
+    );
+}
+
 fn fill<S: PaintScene, F: FnMut(&mut S, &usvg::Node)>(
     scene: &mut S,
+    context: &Context<'_>,
     error_handler: &mut F,
     path: &Path,
     transform: Affine,
@@ -152,7 +514,15 @@ fn fill<S: PaintScene, F: FnMut(&mut S, &usvg::Node)>(
     node: &Node,
 ) {
     if let Some(fill) = &path.fill() {
-        if let Some((paint, brush_transform)) = util::to_brush(fill.paint(), fill.opacity()) {
+        if let Some((paint, brush_transform)) = to_brush(
+            scene,
+            context,
+            error_handler,
+            fill.paint(),
+            fill.opacity(),
+            transform,
+        ) {
+            let paint = context.paint(node, paint);
             scene.fill(
                 match fill.rule() {
                     usvg::FillRule::NonZero => Fill::NonZero,
@@ -164,13 +534,14 @@ fn fill<S: PaintScene, F: FnMut(&mut S, &usvg::Node)>(
                 local_path,
             );
         } else {
-            error_handler(scene, node);
+            report(scene, context, node, IssueKind::Pattern, error_handler);
         }
     }
 }
 
 fn stroke<S: PaintScene, F: FnMut(&mut S, &usvg::Node)>(
     scene: &mut S,
+    context: &Context<'_>,
     error_handler: &mut F,
     path: &Path,
     transform: Affine,
@@ -178,7 +549,15 @@ fn stroke<S: PaintScene, F: FnMut(&mut S, &usvg::Node)>(
     node: &Node,
 ) {
     if let Some(stroke) = &path.stroke() {
-        if let Some((paint, brush_transform)) = util::to_brush(stroke.paint(), stroke.opacity()) {
+        if let Some((paint, brush_transform)) = to_brush(
+            scene,
+            context,
+            error_handler,
+            stroke.paint(),
+            stroke.opacity(),
+            transform,
+        ) {
+            let paint = context.paint(node, paint);
             let conv_stroke = util::to_stroke(stroke);
             scene.stroke(
                 &conv_stroke,
@@ -188,7 +567,37 @@ fn stroke<S: PaintScene, F: FnMut(&mut S, &usvg::Node)>(
                 local_path,
             );
         } else {
-            error_handler(scene, node);
+            report(scene, context, node, IssueKind::Pattern, error_handler);
+        }
+    }
+}
+
+/// Convert a paint to a brush, rendering patterns with the `patterns` feature.
+///
+/// `transform` is the transform of the shape that is painted.
+#[cfg_attr(not(feature = "patterns"), allow(unused_variables))]
+fn to_brush<S: PaintScene, F: FnMut(&mut S, &usvg::Node)>(
+    scene: &mut S,
+    context: &Context<'_>,
+    error_handler: &mut F,
+    paint: &usvg::Paint,
+    opacity: usvg::Opacity,
+    transform: Affine,
+) -> Option<(Paint, Affine)> {
+    match paint {
+        #[cfg(feature = "patterns")]
+        usvg::Paint::Pattern(pattern) => {
+            // Unsupported nodes of the pattern's tile are reported against the scene the pattern is drawn to
+            let mut unsupported = Vec::new();
+            let brush =
+                crate::pattern::to_brush(pattern, context, opacity, transform, &mut |_, node| {
+                    unsupported.push(node.clone());
+                });
+            for node in &unsupported {
+                error_handler(scene, node);
+            }
+            Some(brush)
         }
+        paint => util::to_brush(paint, opacity),
     }
 }
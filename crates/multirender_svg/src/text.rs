@@ -0,0 +1,241 @@
+//! Rendering of SVG text as glyph runs, using the glyphs laid out by usvg.
+//!
+//! Glyphs are drawn with [`PaintScene::draw_glyphs`] from the fonts in the tree's font database, so the font
+//! information is kept in the scene. Text that can't be drawn as glyph runs, such as text using color or bitmap fonts
+//! or painted with gradients or patterns, is drawn as paths instead.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use kurbo::Affine;
+use multirender::{Glyph, PaintScene};
+use peniko::{Blob, Fill, FontData, StyleRef};
+use read_fonts::types::Tag;
+use read_fonts::{FontRef, TableProvider};
+use usvg::fontdb;
+
+use crate::diagnostics::IssueKind;
+use crate::render::{self, Context};
+use crate::util;
+
+/// The fonts that glyphs are drawn from, loaded from font databases on first use and shared by clones of the
+/// [`RenderOptions`](crate::RenderOptions) they are drawn with, so that the same font keeps the same data when
+/// drawing trees again and caches of glyphs keyed by the font data are reused.
+///
+/// Fonts are keyed by the address of their database, whose `Arc` is kept so that the address isn't reused while its
+/// fonts are cached, and their id in it. The fonts of databases that are no longer used by any tree are evicted when
+/// a font is loaded.
+#[derive(Clone, Default)]
+pub(crate) struct FontCache(Arc<Mutex<HashMap<(usize, fontdb::ID), CachedFont>>>);
+
+struct CachedFont {
+    fontdb: Arc<fontdb::Database>,
+    font: Option<Font>,
+}
+
+#[derive(Clone)]
+struct Font {
+    data: FontData,
+    units_per_em: u16,
+}
+
+impl fmt::Debug for FontCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("FontCache")
+    }
+}
+
+impl FontCache {
+    /// The font with `id` in `fontdb`, if its glyphs can be drawn as glyph runs.
+    fn get(&self, fontdb: &Arc<fontdb::Database>, id: fontdb::ID) -> Option<Font> {
+        let mut fonts = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        let key = (Arc::as_ptr(fontdb) as usize, id);
+        if let Some(cached) = fonts.get(&key) {
+            return cached.font.clone();
+        }
+        fonts.retain(|_, cached| Arc::strong_count(&cached.fontdb) > 1);
+        let font = load_font(fontdb, id);
+        fonts.insert(
+            key,
+            CachedFont {
+                fontdb: Arc::clone(fontdb),
+                font: font.clone(),
+            },
+        );
+        font
+    }
+}
+
+/// Tables of glyphs that aren't outlines, which are drawn as paths by usvg.
+const NON_OUTLINE_TABLES: [Tag; 4] = [
+    Tag::new(b"COLR"),
+    Tag::new(b"SVG "),
+    Tag::new(b"CBDT"),
+    Tag::new(b"sbix"),
+];
+
+fn load_font(fontdb: &fontdb::Database, id: fontdb::ID) -> Option<Font> {
+    let face = fontdb.face(id)?;
+    let data = match &face.source {
+        fontdb::Source::Binary(data) => Blob::new(data.clone()),
+        _ => fontdb.with_face_data(id, |data, _| Blob::new(Arc::new(data.to_vec())))?,
+    };
+    let font = FontRef::from_index(data.data(), face.index).ok()?;
+    if NON_OUTLINE_TABLES
+        .iter()
+        .any(|tag| font.table_data(*tag).is_some())
+    {
+        return None;
+    }
+    let units_per_em = font.head().ok()?.units_per_em();
+    Some(Font {
+        data: FontData::new(data, face.index),
+        units_per_em,
+    })
+}
+
+/// Whether `text` can be drawn as glyph runs: all of its glyphs are outlines, and it is painted with solid colors.
+pub(crate) fn can_draw_glyphs(context: &Context<'_>, text: &usvg::Text) -> bool {
+    let is_solid = |paint: &usvg::Paint| matches!(paint, usvg::Paint::Color(_));
+    text.layouted().iter().all(|span| {
+        span.fill.as_ref().is_none_or(|fill| is_solid(fill.paint()))
+            && span
+                .stroke
+                .as_ref()
+                .is_none_or(|stroke| is_solid(stroke.paint()))
+            && span.positioned_glyphs.iter().all(|glyph| {
+                context
+                    .options
+                    .fonts
+                    .get(context.fontdb, glyph.font)
+                    .is_some()
+            })
+    })
+}
+
+/// Draw `text` as glyph runs, which must be possible according to [`can_draw_glyphs`].
+///
+/// `transform` is the transform of the text node relative to `global_transform`.
+pub(crate) fn draw_glyphs<S: PaintScene, F: FnMut(&mut S, &usvg::Node)>(
+    scene: &mut S,
+    context: &Context<'_>,
+    node: &usvg::Node,
+    text: &usvg::Text,
+    transform: Affine,
+    global_transform: Affine,
+    error_handler: &mut F,
+) {
+    let hint = text.rendering_mode() != usvg::TextRendering::GeometricPrecision;
+    for span in text.layouted() {
+        if !span.visible {
+            continue;
+        }
+        for decoration in [&span.overline, &span.underline].into_iter().flatten() {
+            render::draw_path(
+                scene,
+                context,
+                node,
+                decoration,
+                transform,
+                global_transform,
+                error_handler,
+            );
+        }
+
+        let fill = span.fill.as_ref().map(|fill| {
+            let rule = match fill.rule() {
+                usvg::FillRule::NonZero => Fill::NonZero,
+                usvg::FillRule::EvenOdd => Fill::EvenOdd,
+            };
+            (StyleRef::Fill(rule), fill.paint(), fill.opacity())
+        });
+        let stroke_style = span.stroke.as_ref().map(util::to_stroke);
+        let stroke = span
+            .stroke
+            .as_ref()
+            .zip(stroke_style.as_ref())
+            .map(|(stroke, style)| (StyleRef::Stroke(style), stroke.paint(), stroke.opacity()));
+        let styles = match span.paint_order {
+            usvg::PaintOrder::FillAndStroke => [fill, stroke],
+            usvg::PaintOrder::StrokeAndFill => [stroke, fill],
+        };
+        for (style, paint, opacity) in styles.into_iter().flatten() {
+            let Some((paint, _)) = util::to_brush(paint, opacity) else {
+                render::report(scene, context, node, IssueKind::Pattern, error_handler);
+                continue;
+            };
+            let paint = context.paint(node, paint);
+            draw_span(
+                scene,
+                context,
+                span,
+                style,
+                &paint,
+                hint,
+                global_transform * transform,
+            );
+        }
+
+        if let Some(line_through) = &span.line_through {
+            render::draw_path(
+                scene,
+                context,
+                node,
+                line_through,
+                transform,
+                global_transform,
+                error_handler,
+            );
+        }
+    }
+}
+
+/// Draw the glyphs of a span with one glyph run for each sequence of glyphs that share a font and orientation.
+fn draw_span<S: PaintScene>(
+    scene: &mut S,
+    context: &Context<'_>,
+    span: &usvg::layout::Span,
+    style: StyleRef<'_>,
+    paint: &multirender::Paint,
+    hint: bool,
+    transform: Affine,
+) {
+    let font_size = span.font_size.get();
+    // The transform of each glyph, which usvg scales from font units, is split into the position of the glyph and a
+    // glyph transform for rotated or stretched glyphs, which is shared by the glyphs of a run.
+    let glyphs: Vec<_> = span
+        .positioned_glyphs
+        .iter()
+        .filter_map(|glyph| {
+            let font = context.options.fonts.get(context.fontdb, glyph.font)?;
+            let glyph_transform = util::to_affine(&glyph.transform())
+                * Affine::scale(f64::from(font.units_per_em) / f64::from(font_size));
+            let [a, b, c, d, x, y] = glyph_transform.as_coeffs();
+            let glyph = Glyph {
+                id: u32::from(glyph.id.0),
+                x: x as f32,
+                y: y as f32,
+            };
+            Some((font, Affine::new([a, b, c, d, 0.0, 0.0]), glyph))
+        })
+        .collect();
+
+    for run in glyphs.chunk_by(|(font_a, transform_a, _), (font_b, transform_b, _)| {
+        font_a.data == font_b.data && transform_a == transform_b
+    }) {
+        let (font, glyph_transform, _) = &run[0];
+        scene.draw_glyphs(
+            &font.data,
+            font_size,
+            hint,
+            &[],
+            style,
+            paint.as_ref(),
+            1.0,
+            transform,
+            (*glyph_transform != Affine::IDENTITY).then_some(*glyph_transform),
+            run.iter().map(|(_, _, glyph)| *glyph),
+        );
+    }
+}
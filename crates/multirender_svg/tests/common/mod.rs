@@ -0,0 +1,70 @@
+//! Fixtures shared by the integration tests, which render with `vello_cpu`.
+// Each test crate only uses some of the fixtures
+#![allow(dead_code)]
+
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+use kurbo::Affine;
+use multirender::ImageRenderer;
+use multirender_svg::{RenderOptions, usvg};
+use multirender_vello_cpu::VelloCpuImageRenderer;
+
+pub const RED: [u8; 4] = [255, 0, 0, 255];
+pub const BLUE: [u8; 4] = [0, 0, 255, 255];
+pub const CLEAR: [u8; 4] = [0, 0, 0, 0];
+
+pub type Painter<'a> = <VelloCpuImageRenderer as ImageRenderer>::ScenePainter<'a>;
+
+/// A render of `size` × `size` premultiplied RGBA8 pixels.
+pub struct Pixels {
+    size: u32,
+    data: Vec<u8>,
+}
+
+impl Pixels {
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 4] {
+        let i = (y * self.size as usize + x) * 4;
+        self.data[i..i + 4].try_into().unwrap()
+    }
+}
+
+impl Deref for Pixels {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// The directory of the files used by the tests of `multirender_svg`.
+pub fn fixtures() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+/// Render what `draw` draws to an image of `size` × `size` pixels.
+pub fn render(size: u32, draw: impl FnOnce(&mut Painter<'_>)) -> Pixels {
+    let mut renderer = VelloCpuImageRenderer::new(size, size);
+    let mut data = Vec::new();
+    renderer.render_to_vec(draw, &mut data);
+    Pixels { size, data }
+}
+
+/// Render an SVG with `options` to an image of `size` × `size` pixels, failing on unsupported nodes.
+pub fn render_str(size: u32, svg: &str, options: &RenderOptions) -> Pixels {
+    render(size, |scene| {
+        multirender_svg::render_svg_str_with_options(
+            scene,
+            svg,
+            Affine::IDENTITY,
+            options,
+            &mut fail_on_unsupported,
+        )
+        .unwrap();
+    })
+}
+
+/// An error handler that fails the test on unsupported nodes.
+pub fn fail_on_unsupported<S>(_: &mut S, node: &usvg::Node) {
+    panic!("unsupported node: {node:?}")
+}
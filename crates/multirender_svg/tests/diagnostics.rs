@@ -0,0 +1,97 @@
+//! Tests for reports of unsupported SVG features.
+
+use kurbo::{Affine, Rect};
+use multirender::Scene;
+use multirender_svg::{Issue, IssueKind, RenderOptions, usvg};
+
+fn diagnose(svg: &str) -> Vec<Issue> {
+    let tree = usvg::Tree::from_str(svg, &usvg::Options::default()).unwrap();
+    let mut scene = Scene::new();
+    multirender_svg::render_svg_tree_with_diagnostics(
+        &mut scene,
+        &tree,
+        Affine::IDENTITY,
+        &RenderOptions::new(),
+    )
+    .issues
+}
+
+#[test]
+fn unsupported_features() {
+    let issues = diagnose(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+            <filter id="turbulence">
+                <feTurbulence baseFrequency="0.1"/>
+            </filter>
+            <g transform="translate(10 20)">
+                <rect id="noise" width="30" height="40" filter="url(#turbulence)"/>
+            </g>
+            <image id="broken" x="50" y="50" width="10" height="10"
+                href="data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAIAAAACCAIAAAD91JpzAAAABElEQVQAAAAA6iPnBwAAAABJRU5ErkJggg=="/>
+        </svg>"#,
+    );
+    let kinds: Vec<_> = issues
+        .iter()
+        .map(|issue| (issue.id.as_str(), issue.kind))
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            ("noise", IssueKind::Filter),
+            ("broken", IssueKind::ImageDecode),
+        ]
+    );
+    assert_eq!(issues[0].bounding_box, Rect::new(10.0, 20.0, 40.0, 60.0));
+    assert_eq!(
+        issues[0].to_string(),
+        "unsupported filter in #noise at (10, 20, 40, 60)"
+    );
+}
+
+#[cfg(all(feature = "patterns", feature = "masks", feature = "filters"))]
+#[test]
+fn supported_features() {
+    let issues = diagnose(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+            <pattern id="pattern" width="10" height="10" patternUnits="userSpaceOnUse">
+                <rect width="5" height="5" fill="red"/>
+            </pattern>
+            <mask id="mask">
+                <rect width="50" height="50" fill="white"/>
+            </mask>
+            <filter id="blur">
+                <feGaussianBlur stdDeviation="2"/>
+            </filter>
+            <g mask="url(#mask)" filter="url(#blur)">
+                <rect width="100" height="100" fill="url(#pattern)"/>
+            </g>
+        </svg>"#,
+    );
+    assert_eq!(issues, []);
+}
+
+#[cfg(feature = "patterns")]
+#[test]
+fn issues_in_nested_content() {
+    // Unsupported nodes inside patterns are reported once, and nodes of nested SVGs with their own ids
+    let issues = diagnose(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+            <filter id="turbulence">
+                <feTurbulence baseFrequency="0.1"/>
+            </filter>
+            <pattern id="pattern" width="10" height="10" patternUnits="userSpaceOnUse">
+                <rect id="tile" width="5" height="5" filter="url(#turbulence)"/>
+            </pattern>
+            <rect width="100" height="50" fill="url(#pattern)"/>
+            <image y="50" width="50" height="50" href="data:image/svg+xml;utf8,&lt;svg xmlns='http://www.w3.org/2000/svg' width='10' height='10'&gt;&lt;filter id='t'&gt;&lt;feTurbulence baseFrequency='0.1'/&gt;&lt;/filter&gt;&lt;rect id='inner' width='10' height='10' filter='url(%23t)'/&gt;&lt;/svg&gt;"/>
+        </svg>"#,
+    );
+    let kinds: Vec<_> = issues
+        .iter()
+        .map(|issue| (issue.id.as_str(), issue.kind))
+        .collect();
+    assert_eq!(
+        kinds,
+        [("tile", IssueKind::Filter), ("inner", IssueKind::Filter)]
+    );
+}
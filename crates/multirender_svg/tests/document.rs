@@ -0,0 +1,243 @@
+//! Tests for SVGs converted to recorded scenes.
+
+mod common;
+
+use kurbo::{Affine, Rect};
+use multirender_svg::{RenderOptions, SvgDocument};
+
+use common::{CLEAR, Painter, Pixels, RED};
+
+const SIZE: u32 = 20;
+
+fn render(draw: impl FnOnce(&mut Painter<'_>)) -> Pixels {
+    common::render(SIZE, draw)
+}
+
+#[test]
+fn draws_like_tree() {
+    let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="16" viewBox="5 5 10 10"
+            preserveAspectRatio="xMinYMax meet">
+        <circle cx="10" cy="10" r="4" fill="green" stroke="navy"/>
+    </svg>"#;
+    let document = SvgDocument::from_str(svg, &RenderOptions::new()).unwrap();
+    assert_eq!(document.size(), kurbo::Size::new(20.0, 16.0));
+    assert_eq!(document.view_box(), Rect::new(5.0, 5.0, 15.0, 15.0));
+
+    let expected = render(|scene| {
+        multirender_svg::render_svg_str(scene, svg, Affine::translate((2.0, 2.0))).unwrap()
+    });
+    let drawn = render(|scene| document.draw(scene, Affine::translate((2.0, 2.0))));
+    assert!(
+        expected
+            .iter()
+            .zip(drawn.iter())
+            .all(|(expected, drawn)| expected.abs_diff(*drawn) <= 2)
+    );
+}
+
+#[test]
+fn draw_in_rect() {
+    let svg = |aspect_ratio: &str| {
+        format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 10 10" preserveAspectRatio="{aspect_ratio}">
+                <rect width="10" height="10" fill="red"/>
+            </svg>"#
+        )
+    };
+    let draw = |aspect_ratio: &str| {
+        let document = SvgDocument::from_str(&svg(aspect_ratio), &RenderOptions::new()).unwrap();
+        render(|scene| {
+            document.draw_in_rect(scene, Affine::IDENTITY, Rect::new(0.0, 5.0, 20.0, 15.0))
+        })
+    };
+
+    let centered = draw("xMidYMid");
+    assert_eq!(centered.pixel(2, 10), CLEAR);
+    assert_eq!(centered.pixel(10, 10), RED);
+    assert_eq!(centered.pixel(17, 10), CLEAR);
+    assert_eq!(centered.pixel(10, 2), CLEAR);
+
+    let end = draw("xMaxYMid");
+    assert_eq!(end.pixel(7, 10), CLEAR);
+    assert_eq!(end.pixel(17, 10), RED);
+
+    let stretched = draw("none");
+    assert_eq!(stretched.pixel(2, 10), RED);
+    assert_eq!(stretched.pixel(17, 10), RED);
+    assert_eq!(stretched.pixel(10, 2), CLEAR);
+}
+
+#[cfg(feature = "patterns")]
+#[test]
+fn raster_scale() {
+    // A checkerboard of 1×1 squares in a 10×10 SVG, drawn at four times its size
+    let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10" viewBox="0 0 5 5">
+        <pattern id="p" width="1" height="1" patternUnits="userSpaceOnUse">
+            <rect width="0.5" height="0.5" fill="red"/>
+            <rect x="0.5" y="0.5" width="0.5" height="0.5" fill="red"/>
+        </pattern>
+        <rect width="5" height="5" fill="url(#p)"/>
+    </svg>"#;
+    let expected = common::render(40, |scene| {
+        multirender_svg::render_svg_str(scene, svg, Affine::scale(4.0)).unwrap()
+    });
+    let max_difference = |options: &RenderOptions| {
+        let document = SvgDocument::from_str(svg, options).unwrap();
+        let drawn = common::render(40, |scene| document.draw(scene, Affine::scale(4.0)));
+        expected
+            .iter()
+            .zip(drawn.iter())
+            .map(|(expected, drawn)| expected.abs_diff(*drawn))
+            .max()
+            .unwrap()
+    };
+
+    // The pattern is rendered at the size of the SVG, so drawing it larger blurs the squares
+    assert!(max_difference(&RenderOptions::new()) > 64);
+    assert!(max_difference(&RenderOptions::new().with_raster_scale(4.0)) <= 2);
+}
+
+#[cfg(feature = "image_format_png")]
+#[test]
+fn images_are_decoded_once() {
+    use multirender::Scene;
+    use multirender::recording::RenderCommand;
+    use peniko::Brush;
+
+    let document =
+        SvgDocument::from_file(common::fixtures().join("image.svg"), &RenderOptions::new())
+            .unwrap();
+
+    let mut scene = Scene::new();
+    document.draw(&mut scene, Affine::IDENTITY);
+    document.draw(&mut scene, Affine::translate((20.0, 0.0)));
+    let images: Vec<_> = scene
+        .commands
+        .iter()
+        .filter_map(|command| match command {
+            RenderCommand::Fill(fill) => match &fill.brush {
+                Brush::Image(image) => Some(image.image.data.id()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    assert_eq!(images.len(), 2);
+    assert_eq!(images[0], images[1]);
+
+    let buffer = render(|scene| document.draw(scene, Affine::IDENTITY));
+    assert_eq!(buffer.pixel(10, 10), RED);
+}
+
+/// Records the addresses of the paths it is asked to draw.
+#[derive(Default)]
+struct PathAddresses(Vec<*const kurbo::PathEl>);
+
+impl PathAddresses {
+    fn record(&mut self, shape: &impl kurbo::Shape) {
+        self.0.push(shape.as_path_slice().unwrap().as_ptr());
+    }
+}
+
+impl multirender::PaintScene for PathAddresses {
+    fn reset(&mut self) {}
+
+    fn push_layer(
+        &mut self,
+        _blend: impl Into<peniko::BlendMode>,
+        _alpha: f32,
+        _transform: Affine,
+        clip: &impl kurbo::Shape,
+    ) {
+        self.record(clip);
+    }
+
+    fn push_clip_layer(&mut self, _transform: Affine, clip: &impl kurbo::Shape) {
+        self.record(clip);
+    }
+
+    fn pop_layer(&mut self) {}
+
+    fn stroke<'a>(
+        &mut self,
+        _style: &kurbo::Stroke,
+        _transform: Affine,
+        _brush: impl Into<multirender::PaintRef<'a>>,
+        _brush_transform: Option<Affine>,
+        shape: &impl kurbo::Shape,
+    ) {
+        self.record(shape);
+    }
+
+    fn fill<'a>(
+        &mut self,
+        _style: peniko::Fill,
+        _transform: Affine,
+        _brush: impl Into<multirender::PaintRef<'a>>,
+        _brush_transform: Option<Affine>,
+        shape: &impl kurbo::Shape,
+    ) {
+        self.record(shape);
+    }
+
+    fn draw_glyphs<'a, 's: 'a>(
+        &'s mut self,
+        _font: &'a peniko::FontData,
+        _font_size: f32,
+        _hint: bool,
+        _normalized_coords: &'a [multirender::NormalizedCoord],
+        _style: impl Into<peniko::StyleRef<'a>>,
+        _brush: impl Into<multirender::PaintRef<'a>>,
+        _brush_alpha: f32,
+        _transform: Affine,
+        _glyph_transform: Option<Affine>,
+        _glyphs: impl Iterator<Item = multirender::Glyph>,
+    ) {
+    }
+
+    fn draw_box_shadow(
+        &mut self,
+        _transform: Affine,
+        _rect: Rect,
+        _brush: peniko::Color,
+        _radius: f64,
+        _std_dev: f64,
+    ) {
+    }
+}
+
+#[test]
+fn draws_share_paths() {
+    use multirender::recording::RenderCommand;
+
+    let document = SvgDocument::from_str(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="20">
+            <clipPath id="c"><circle cx="10" cy="10" r="8"/></clipPath>
+            <g clip-path="url(#c)">
+                <rect width="10" height="20" fill="red"/>
+                <path d="M 10 0 L 20 10 L 10 20 Z" fill="blue" stroke="black"/>
+            </g>
+        </svg>"#,
+        &RenderOptions::new(),
+    )
+    .unwrap();
+    let recorded: Vec<_> = document
+        .scene()
+        .commands
+        .iter()
+        .filter_map(|command| match command {
+            RenderCommand::PushLayer(layer) => Some(layer.clip.elements().as_ptr()),
+            RenderCommand::PushClipLayer(clip) => Some(clip.clip.elements().as_ptr()),
+            RenderCommand::Fill(fill) => Some(fill.shape.elements().as_ptr()),
+            RenderCommand::Stroke(stroke) => Some(stroke.shape.elements().as_ptr()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(recorded.len(), 4);
+
+    for transform in [Affine::IDENTITY, Affine::scale(2.0)] {
+        let mut painter = PathAddresses::default();
+        document.draw(&mut painter, transform);
+        assert_eq!(painter.0, recorded);
+    }
+}
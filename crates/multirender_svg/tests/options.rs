@@ -0,0 +1,88 @@
+//! Tests for the options used to load SVGs.
+
+mod common;
+
+use kurbo::Affine;
+use multirender_svg::{Error, RenderOptions};
+
+use common::{CLEAR, RED, fixtures};
+
+const SIZE: u32 = 20;
+
+fn render_str(svg: &str, options: &RenderOptions) -> common::Pixels {
+    common::render_str(SIZE, svg, options)
+}
+
+#[cfg(feature = "image_format_png")]
+#[test]
+fn file_resolves_images_from_its_directory() {
+    let buffer = common::render(SIZE, |scene| {
+        multirender_svg::render_svg_file_with_options(
+            scene,
+            fixtures().join("image.svg"),
+            Affine::IDENTITY,
+            &RenderOptions::new(),
+            &mut common::fail_on_unsupported,
+        )
+        .unwrap()
+    });
+    assert_eq!(buffer.pixel(10, 10), RED);
+}
+
+#[cfg(feature = "image_format_png")]
+#[test]
+fn str_resolves_images_from_resources_dir() {
+    let svg = std::fs::read_to_string(fixtures().join("image.svg")).unwrap();
+    let buffer = render_str(&svg, &RenderOptions::new().with_resources_dir(fixtures()));
+    assert_eq!(buffer.pixel(10, 10), RED);
+
+    // Without a resources directory the image isn't found, and is skipped
+    let buffer = render_str(&svg, &RenderOptions::new());
+    assert_eq!(buffer.pixel(10, 10), CLEAR);
+}
+
+#[test]
+fn missing_file() {
+    let mut scene = multirender::Scene::new();
+    let result = multirender_svg::render_svg_file(
+        &mut scene,
+        fixtures().join("missing.svg"),
+        Affine::IDENTITY,
+    );
+    assert!(matches!(result, Err(Error::Io(_))));
+}
+
+#[test]
+fn dpi() {
+    let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="20">
+        <rect width="1in" height="1in" fill="red"/>
+    </svg>"#;
+    let buffer = render_str(svg, &RenderOptions::new());
+    assert_eq!(buffer.pixel(15, 5), RED);
+    let buffer = render_str(svg, &RenderOptions::new().with_dpi(10.0));
+    assert_eq!(buffer.pixel(5, 5), RED);
+    assert_eq!(buffer.pixel(15, 5), CLEAR);
+}
+
+#[cfg(feature = "text")]
+#[test]
+fn shared_fontdb() {
+    use std::sync::Arc;
+
+    static ROBOTO: &[u8] = include_bytes!("../../../assets/fonts/roboto/Roboto.ttf");
+
+    let mut fontdb = multirender_svg::usvg::fontdb::Database::new();
+    fontdb.load_font_data(ROBOTO.to_vec());
+    let options = RenderOptions::new()
+        .with_fontdb(Arc::new(fontdb))
+        .with_font_family("Roboto");
+
+    let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="20">
+        <text x="2" y="16" font-size="16" fill="red">H</text>
+    </svg>"#;
+    let is_drawn = |buffer: &[u8]| buffer.chunks_exact(4).any(|pixel| pixel[3] != 0);
+    for _ in 0..2 {
+        assert!(is_drawn(&render_str(svg, &options)));
+    }
+    assert!(!is_drawn(&render_str(svg, &RenderOptions::new())));
+}
@@ -0,0 +1,122 @@
+//! Tests for patterns, which are rendered to image tiles.
+#![cfg(feature = "patterns")]
+
+mod common;
+
+use kurbo::Affine;
+use multirender::recording::{RenderCommand, Scene};
+use multirender_svg::{RenderOptions, usvg};
+use peniko::Brush;
+
+use common::{CLEAR, Pixels, RED};
+
+const SIZE: u32 = 20;
+
+const CHECKERED: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="20">
+    <pattern id="p" width="10" height="10" patternUnits="userSpaceOnUse">
+        <rect width="5" height="5" fill="red"/>
+    </pattern>
+    <rect width="20" height="20" fill="url(#p)"/>
+</svg>"#;
+
+/// A 10×10 tile with a red square in its top left quarter, repeated over the image
+fn assert_checkered(buffer: &Pixels) {
+    for (x, y, expected) in [
+        (2, 2, RED),
+        (7, 2, CLEAR),
+        (7, 7, CLEAR),
+        (12, 2, RED),
+        (2, 12, RED),
+        (12, 12, RED),
+        (17, 17, CLEAR),
+    ] {
+        assert_eq!(buffer.pixel(x, y), expected, "pixel at {x}, {y}");
+    }
+}
+
+#[test]
+fn pattern_fill() {
+    assert_checkered(&common::render_str(SIZE, CHECKERED, &RenderOptions::new()));
+}
+
+#[test]
+fn pattern_with_view_box() {
+    // The content is in a nested group, and is only positioned once the bounding box of the rect is known.
+    let buffer = common::render_str(
+        SIZE,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="20">
+            <pattern id="p" width="0.5" height="0.5" viewBox="0 0 2 2">
+                <g transform="translate(0.5 0.5)">
+                    <rect x="-0.5" y="-0.5" width="1" height="1" fill="red"/>
+                </g>
+            </pattern>
+            <rect width="20" height="20" fill="url(#p)"/>
+        </svg>"#,
+        &RenderOptions::new(),
+    );
+    assert_checkered(&buffer);
+}
+
+#[cfg(feature = "image_format_png")]
+#[test]
+fn pattern_with_image() {
+    // The content is wrapped in a group that scales it to the bounding box of the rect
+    let buffer = common::render_str(
+        SIZE,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="20">
+            <pattern id="p" width="0.5" height="0.5" patternContentUnits="objectBoundingBox">
+                <image width="0.25" height="0.25"
+                    href="data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mP4z8DwHwAFAAH/VscvDQAAAABJRU5ErkJggg=="/>
+            </pattern>
+            <rect width="20" height="20" fill="url(#p)"/>
+        </svg>"#,
+        &RenderOptions::new(),
+    );
+    assert_checkered(&buffer);
+}
+
+#[test]
+fn pattern_tiles_are_cached() {
+    let tile_ids = |tree: &usvg::Tree, options: &RenderOptions| {
+        let mut scene = Scene::new();
+        multirender_svg::render_svg_tree_with_options(
+            &mut scene,
+            tree,
+            Affine::IDENTITY,
+            options,
+            &mut common::fail_on_unsupported,
+        );
+        scene
+            .commands
+            .iter()
+            .filter_map(|command| match command {
+                RenderCommand::Fill(fill) => match &fill.brush {
+                    Brush::Image(image) => Some(image.image.data.id()),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let tree = usvg::Tree::from_str(CHECKERED, &usvg::Options::default()).unwrap();
+    let options = RenderOptions::new();
+    let tiles = tile_ids(&tree, &options);
+    assert_eq!(tiles.len(), 1);
+    assert_eq!(tile_ids(&tree, &options), tiles);
+    assert_eq!(tile_ids(&tree, &options.clone()), tiles);
+    // Other options and other trees have their own tiles
+    assert_ne!(tile_ids(&tree, &RenderOptions::new()), tiles);
+    let other = usvg::Tree::from_str(CHECKERED, &usvg::Options::default()).unwrap();
+    assert_ne!(tile_ids(&other, &options), tiles);
+
+    assert_checkered(&common::render(SIZE, |scene| {
+        multirender_svg::render_svg_tree_with_options(
+            scene,
+            &tree,
+            Affine::IDENTITY,
+            &options,
+            &mut common::fail_on_unsupported,
+        );
+    }));
+}
@@ -0,0 +1,302 @@
+//! Compares SVGs rendered through multirender with `vello_cpu` against reference renders from resvg.
+#![cfg(all(feature = "patterns", feature = "masks", feature = "filters"))]
+
+mod common;
+
+use kurbo::Affine;
+
+const SIZE: u32 = 64;
+
+/// Both renderers render at this many pixels per compared pixel, and the renders are downsampled before they are
+/// compared. `vello_cpu` flattens curves to within a quarter of a pixel, which alone changes the coverage of pixels
+/// along curved edges by up to a quarter, so the renders are only compared once that is averaged out.
+const SUPERSAMPLING: u32 = 4;
+
+/// The largest difference of any channel of a pixel
+const MAX_DIFFERENCE: u8 = 16;
+
+/// The largest share of pixels with a channel that differs by more than [`SMALL_DIFFERENCE`]
+const MAX_DIFFERING_SHARE: f64 = 0.02;
+
+const SMALL_DIFFERENCE: u8 = 8;
+
+/// Render with multirender, failing on unsupported nodes
+fn render(svg: &str) -> Vec<u8> {
+    let buffer = common::render(SIZE * SUPERSAMPLING, |scene| {
+        multirender_svg::render_svg_str_with(
+            scene,
+            svg,
+            Affine::scale(SUPERSAMPLING as f64),
+            &mut common::fail_on_unsupported,
+        )
+        .unwrap();
+    });
+    downsample(&buffer)
+}
+
+fn render_resvg(svg: &str) -> Vec<u8> {
+    let tree = resvg::usvg::Tree::from_str(svg, &resvg::usvg::Options::default()).unwrap();
+    let mut pixmap =
+        resvg::tiny_skia::Pixmap::new(SIZE * SUPERSAMPLING, SIZE * SUPERSAMPLING).unwrap();
+    resvg::render(
+        &tree,
+        resvg::tiny_skia::Transform::from_scale(SUPERSAMPLING as f32, SUPERSAMPLING as f32),
+        &mut pixmap.as_mut(),
+    );
+    downsample(&pixmap.take())
+}
+
+/// Average each block of `SUPERSAMPLING` × `SUPERSAMPLING` pixels of a render into one pixel
+fn downsample(buffer: &[u8]) -> Vec<u8> {
+    let (size, factor) = (SIZE as usize, SUPERSAMPLING as usize);
+    let mut pixels = Vec::with_capacity(size * size * 4);
+    for y in 0..size {
+        for x in 0..size {
+            for channel in 0..4 {
+                let sum: u32 = (0..factor * factor)
+                    .map(|i| {
+                        let (sx, sy) = (x * factor + i % factor, y * factor + i / factor);
+                        buffer[(sy * size * factor + sx) * 4 + channel] as u32
+                    })
+                    .sum();
+                let count = (factor * factor) as u32;
+                pixels.push(((sum + count / 2) / count) as u8);
+            }
+        }
+    }
+    pixels
+}
+
+/// Both renders are premultiplied RGBA8. Anti-aliasing and image sampling differ slightly between the renderers, so
+/// a few pixels along edges may differ a little, but no pixel may differ by much.
+#[track_caller]
+fn assert_matches_resvg(svg: &str) {
+    let actual = render(svg);
+    let expected = render_resvg(svg);
+    assert_eq!(actual.len(), expected.len());
+
+    let diffs: Vec<_> = actual
+        .chunks_exact(4)
+        .zip(expected.chunks_exact(4))
+        .enumerate()
+        .map(|(i, (a, e))| {
+            let diff = a.iter().zip(e).map(|(a, e)| a.abs_diff(*e)).max().unwrap();
+            (diff, i as u32 % SIZE, i as u32 / SIZE, a, e)
+        })
+        .collect();
+    let worst = diffs.iter().max_by_key(|(diff, ..)| *diff).unwrap();
+    assert!(
+        worst.0 <= MAX_DIFFERENCE,
+        "pixel differs from resvg (difference, x, y, actual, expected): {worst:?}"
+    );
+    let differing = diffs
+        .iter()
+        .filter(|(diff, ..)| *diff > SMALL_DIFFERENCE)
+        .count();
+    assert!(
+        differing as f64 <= diffs.len() as f64 * MAX_DIFFERING_SHARE,
+        "{differing} pixels differ from resvg"
+    );
+}
+
+#[test]
+fn pattern() {
+    assert_matches_resvg(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="64">
+            <pattern id="p" width="0.25" height="0.25" viewBox="0 0 4 4" patternTransform="rotate(45 32 32)">
+                <rect width="2" height="2" fill="green"/>
+                <circle cx="3" cy="3" r="1" fill="blue"/>
+            </pattern>
+            <rect x="4" y="4" width="56" height="56" fill="url(#p)" stroke="url(#p)" stroke-width="4"/>
+        </svg>"#,
+    );
+}
+
+#[test]
+fn alpha_mask() {
+    assert_matches_resvg(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="64">
+            <mask id="m" mask-type="alpha">
+                <rect x="8" y="8" width="32" height="48" fill="black"/>
+                <rect x="40" y="8" width="16" height="48" fill="white" fill-opacity="0.5"/>
+            </mask>
+            <rect width="64" height="64" fill="red" mask="url(#m)"/>
+        </svg>"#,
+    );
+}
+
+#[test]
+fn luminance_mask() {
+    assert_matches_resvg(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="64">
+            <linearGradient id="g">
+                <stop offset="0" stop-color="white"/>
+                <stop offset="1" stop-color="black"/>
+            </linearGradient>
+            <mask id="m" x="0" y="0" width="1" height="1">
+                <rect width="64" height="32" fill="url(#g)"/>
+                <rect y="32" width="64" height="32" fill="rgb(0, 128, 255)" fill-opacity="0.75"/>
+            </mask>
+            <rect width="64" height="64" fill="blue" mask="url(#m)"/>
+        </svg>"#,
+    );
+}
+
+#[test]
+fn nested_masks() {
+    assert_matches_resvg(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="64">
+            <mask id="inner" maskUnits="userSpaceOnUse" x="0" y="0" width="64" height="40">
+                <rect width="64" height="64" fill="white"/>
+            </mask>
+            <mask id="outer" mask="url(#inner)" mask-type="alpha">
+                <circle cx="32" cy="32" r="24" fill="black"/>
+            </mask>
+            <g mask="url(#outer)">
+                <rect width="64" height="64" fill="green"/>
+                <rect x="16" y="16" width="32" height="32" fill="orange" opacity="0.5"/>
+            </g>
+        </svg>"#,
+    );
+}
+
+#[test]
+fn masks_with_transforms() {
+    assert_matches_resvg(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="64">
+            <mask id="m" maskContentUnits="objectBoundingBox">
+                <rect x="0.25" y="0.25" width="0.5" height="0.5" fill="white" transform="rotate(10 0.5 0.5)"/>
+            </mask>
+            <mask id="nested">
+                <g transform="translate(16 0)">
+                    <rect width="24" height="64" fill="white" mask="url(#m)"/>
+                </g>
+            </mask>
+            <g transform="translate(8 4) scale(1.5)">
+                <rect width="32" height="32" fill="purple" mask="url(#m)"/>
+            </g>
+            <rect y="40" width="64" height="24" fill="teal" mask="url(#nested)"/>
+        </svg>"#,
+    );
+}
+
+/// Images in masks are placed relative to the masked group like other mask content
+#[test]
+fn image_mask_on_transformed_group() {
+    assert_matches_resvg(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="64">
+            <mask id="m" mask-type="alpha">
+                <image x="8" y="4" width="24" height="32" href="data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAYAAAAfFcSJAAAADUlEQVR42mNgYPj/HwADAgH/OSkZvgAAAABJRU5ErkJggg=="/>
+            </mask>
+            <g transform="translate(10 10)" mask="url(#m)">
+                <rect width="44" height="44" fill="green"/>
+            </g>
+        </svg>"#,
+    );
+}
+
+#[test]
+fn gaussian_blur() {
+    assert_matches_resvg(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="64">
+            <filter id="large">
+                <feGaussianBlur stdDeviation="4"/>
+            </filter>
+            <filter id="small" x="0" y="0" width="1" height="1">
+                <feGaussianBlur stdDeviation="1 0.5"/>
+            </filter>
+            <rect x="8" y="8" width="24" height="24" fill="blue" filter="url(#large)"/>
+            <g transform="translate(36 36) scale(1.5)">
+                <rect width="16" height="16" fill="green" stroke="black" filter="url(#small)"/>
+            </g>
+        </svg>"#,
+    );
+}
+
+#[test]
+fn drop_shadow() {
+    // The filter region is aligned to pixels, so that both renderers clip the shadow at the same edge. The shadow is
+    // composited in linearRGB: resvg converts the flood color of a drop shadow to the wrong color space in sRGB.
+    assert_matches_resvg(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="64">
+            <filter id="f" x="-0.25" y="-0.25" width="1.5" height="1.5">
+                <feDropShadow dx="4" dy="6" stdDeviation="2" flood-color="purple" flood-opacity="0.75"/>
+            </filter>
+            <circle cx="28" cy="28" r="18" fill="orange" filter="url(#f)"/>
+        </svg>"#,
+    );
+}
+
+#[test]
+fn filter_primitives() {
+    assert_matches_resvg(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="64">
+            <filter id="f" x="0" y="0" width="1" height="1">
+                <feOffset in="SourceAlpha" dx="6" dy="6" result="offset"/>
+                <feFlood flood-color="teal" flood-opacity="0.5" result="flood"/>
+                <feComposite in="flood" in2="offset" operator="in" result="shadow"/>
+                <feColorMatrix in="SourceGraphic" type="hueRotate" values="90" result="rotated"/>
+                <feComposite in="rotated" in2="SourceGraphic" operator="arithmetic" k2="0.5" k3="0.5"
+                    result="mixed"/>
+                <feMerge>
+                    <feMergeNode in="shadow"/>
+                    <feMergeNode in="mixed"/>
+                </feMerge>
+            </filter>
+            <g filter="url(#f)" opacity="0.75">
+                <rect x="4" y="4" width="40" height="40" fill="red"/>
+                <rect x="16" y="16" width="24" height="24" fill="yellow"/>
+            </g>
+        </svg>"#,
+    );
+}
+
+#[test]
+fn clip_path_with_shapes() {
+    assert_matches_resvg(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="64">
+            <clipPath id="simple" transform="translate(4 4)">
+                <circle cx="12" cy="12" r="12"/>
+            </clipPath>
+            <clipPath id="union">
+                <rect x="32" y="4" width="12" height="24"/>
+                <circle cx="52" cy="16" r="8"/>
+            </clipPath>
+            <clipPath id="even-odd">
+                <path d="M4 36h24v24h-24z M10 42h12v12h-12z" clip-rule="evenodd"/>
+            </clipPath>
+            <clipPath id="bounding-box" clipPathUnits="objectBoundingBox">
+                <rect width="0.5" height="0.5" transform="rotate(15)"/>
+                <circle cx="0.75" cy="0.75" r="0.25"/>
+            </clipPath>
+            <rect width="32" height="32" fill="green" clip-path="url(#simple)"/>
+            <rect x="32" width="32" height="32" fill="blue" clip-path="url(#union)"/>
+            <rect y="32" width="32" height="32" fill="orange" clip-path="url(#even-odd)"/>
+            <rect x="36" y="36" width="24" height="24" fill="purple" clip-path="url(#bounding-box)"/>
+        </svg>"#,
+    );
+}
+
+#[test]
+fn nested_clip_paths() {
+    assert_matches_resvg(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="64">
+            <clipPath id="half">
+                <rect width="64" height="40"/>
+            </clipPath>
+            <clipPath id="clipped" clip-path="url(#half)">
+                <circle cx="32" cy="32" r="28"/>
+            </clipPath>
+            <clipPath id="group">
+                <g transform="translate(8 8)" clip-path="url(#half)">
+                    <rect width="20" height="48"/>
+                    <rect x="28" width="20" height="48"/>
+                </g>
+            </clipPath>
+            <g clip-path="url(#clipped)" opacity="0.5">
+                <rect width="64" height="64" fill="green"/>
+            </g>
+            <rect width="64" height="64" fill="red" fill-opacity="0.5" clip-path="url(#group)"/>
+        </svg>"#,
+    );
+}
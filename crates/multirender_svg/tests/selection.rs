@@ -0,0 +1,114 @@
+//! Tests for drawing parts of SVGs and overriding their paints.
+
+mod common;
+
+use multirender::Paint;
+use multirender_svg::RenderOptions;
+use peniko::Color;
+
+use common::{BLUE, CLEAR, Pixels, RED};
+
+const SIZE: u32 = 40;
+
+fn render(svg: &str, options: &RenderOptions) -> Pixels {
+    common::render_str(SIZE, svg, options)
+}
+
+const ICON: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="40">
+    <g id="states" transform="translate(0 20)">
+        <g id="on">
+            <rect width="20" height="20" fill="red"/>
+            <rect id="badge" x="15" width="5" height="5" fill="blue"/>
+        </g>
+        <rect id="off" x="20" width="20" height="20" fill="blue"/>
+    </g>
+    <rect id="background" width="40" height="20" fill="blue"/>
+</svg>"#;
+
+#[test]
+fn include_ids() {
+    let buffer = render(ICON, &RenderOptions::new().with_include_ids(["on"]));
+    // The transforms of the groups containing included nodes are applied
+    assert_eq!(buffer.pixel(5, 25), RED);
+    assert_eq!(buffer.pixel(17, 22), BLUE);
+    assert_eq!(buffer.pixel(25, 25), CLEAR);
+    assert_eq!(buffer.pixel(5, 5), CLEAR);
+
+    let buffer = render(
+        ICON,
+        &RenderOptions::new().with_include_ids(["off", "background"]),
+    );
+    assert_eq!(buffer.pixel(5, 25), CLEAR);
+    assert_eq!(buffer.pixel(25, 25), BLUE);
+    assert_eq!(buffer.pixel(5, 5), BLUE);
+}
+
+#[test]
+fn exclude_ids() {
+    let buffer = render(
+        ICON,
+        &RenderOptions::new().with_exclude_ids(["off", "badge"]),
+    );
+    assert_eq!(buffer.pixel(5, 25), RED);
+    assert_eq!(buffer.pixel(17, 22), RED);
+    assert_eq!(buffer.pixel(25, 25), CLEAR);
+    assert_eq!(buffer.pixel(5, 5), BLUE);
+
+    // Excluded nodes inside included nodes aren't drawn
+    let options = RenderOptions::new()
+        .with_include_ids(["states"])
+        .with_exclude_ids(["badge"]);
+    let buffer = render(ICON, &options);
+    assert_eq!(buffer.pixel(17, 22), RED);
+    assert_eq!(buffer.pixel(25, 25), BLUE);
+    assert_eq!(buffer.pixel(5, 5), CLEAR);
+
+    // Groups only containing excluded nodes aren't drawn
+    let options = RenderOptions::new()
+        .with_include_ids(["on"])
+        .with_exclude_ids(["on"]);
+    assert!(render(ICON, &options).iter().all(|&byte| byte == 0));
+}
+
+#[cfg(feature = "masks")]
+#[test]
+fn referenced_elements_are_not_filtered() {
+    let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="40">
+        <mask id="mask">
+            <rect id="mask-content" width="20" height="40" fill="white"/>
+        </mask>
+        <g id="masked" mask="url(#mask)">
+            <rect id="icon" width="40" height="40" fill="red"/>
+        </g>
+    </svg>"#;
+    let options = RenderOptions::new()
+        .with_include_ids(["icon"])
+        .with_exclude_ids(["mask-content"])
+        .with_paint_override(|_, _| Paint::Solid(Color::from_rgb8(0, 0, 255)));
+    let buffer = render(svg, &options);
+    assert_eq!(buffer.pixel(10, 10), BLUE);
+    assert_eq!(buffer.pixel(30, 10), CLEAR);
+}
+
+#[test]
+fn paint_override() {
+    let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="40">
+        <linearGradient id="gradient">
+            <stop offset="0" stop-color="green"/>
+            <stop offset="1" stop-color="yellow"/>
+        </linearGradient>
+        <rect id="tinted" width="20" height="20" fill="url(#gradient)" stroke="green"/>
+        <rect x="20" width="20" height="20" fill="red"/>
+    </svg>"#;
+    let options = RenderOptions::new().with_paint_override(|node, paint| {
+        if node.id() == "tinted" {
+            Paint::Solid(Color::from_rgb8(0, 0, 255))
+        } else {
+            paint
+        }
+    });
+    let buffer = render(svg, &options);
+    assert_eq!(buffer.pixel(10, 10), BLUE);
+    assert_eq!(buffer.pixel(10, 0), BLUE);
+    assert_eq!(buffer.pixel(30, 10), RED);
+}
@@ -0,0 +1,147 @@
+//! Tests for rendering text as glyph runs.
+#![cfg(feature = "text")]
+
+mod common;
+
+use kurbo::Affine;
+use multirender::PaintScene;
+use multirender::recording::{RenderCommand, Scene};
+use multirender_svg::{RenderOptions, usvg};
+
+use common::Pixels;
+
+const SIZE: u32 = 64;
+
+static ROBOTO: &[u8] = include_bytes!("../../../assets/fonts/roboto/Roboto.ttf");
+
+fn parse(svg: &str) -> usvg::Tree {
+    let mut options = usvg::Options {
+        font_family: "Roboto".to_string(),
+        ..usvg::Options::default()
+    };
+    options.fontdb_mut().load_font_data(ROBOTO.to_vec());
+    usvg::Tree::from_str(svg, &options).unwrap()
+}
+
+fn draw<S: PaintScene>(scene: &mut S, tree: &usvg::Tree, text_as_glyphs: bool) {
+    multirender_svg::render_svg_tree_with_options(
+        scene,
+        tree,
+        Affine::IDENTITY,
+        &RenderOptions::new().with_text_as_glyphs(text_as_glyphs),
+        &mut common::fail_on_unsupported,
+    );
+}
+
+fn record(svg: &str, text_as_glyphs: bool) -> Scene {
+    let mut scene = Scene::new();
+    draw(&mut scene, &parse(svg), text_as_glyphs);
+    scene
+}
+
+fn render(svg: &str, text_as_glyphs: bool) -> Pixels {
+    let tree = parse(svg);
+    common::render(SIZE, |scene| draw(scene, &tree, text_as_glyphs))
+}
+
+fn glyph_counts(scene: &Scene) -> Vec<usize> {
+    scene
+        .commands
+        .iter()
+        .filter_map(|command| match command {
+            RenderCommand::GlyphRun(run) => Some(run.glyphs.len()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn text_as_glyph_runs() {
+    let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="64">
+        <text x="4" y="24" font-size="16" fill="navy">Hello</text>
+    </svg>"#;
+
+    let glyphs = record(svg, true);
+    assert_eq!(glyph_counts(&glyphs), [5]);
+    assert!(
+        !glyphs
+            .commands
+            .iter()
+            .any(|command| matches!(command, RenderCommand::Fill(_)))
+    );
+
+    let paths = record(svg, false);
+    assert!(glyph_counts(&paths).is_empty());
+}
+
+fn font_data_ids(scene: &Scene) -> Vec<u64> {
+    scene
+        .commands
+        .iter()
+        .filter_map(|command| match command {
+            RenderCommand::GlyphRun(run) => Some(run.font_data.data.id()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn fonts_are_reused_across_renders() {
+    let tree = parse(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="64">
+            <text x="4" y="24" font-size="16">Hello</text>
+        </svg>"#,
+    );
+    let options = RenderOptions::new().with_text_as_glyphs(true);
+    let record = |options: &RenderOptions| {
+        let mut scene = Scene::new();
+        multirender_svg::render_svg_tree_with_options(
+            &mut scene,
+            &tree,
+            Affine::IDENTITY,
+            options,
+            &mut common::fail_on_unsupported,
+        );
+        font_data_ids(&scene)
+    };
+
+    let first = record(&options);
+    assert_eq!(first.len(), 1);
+    assert_eq!(record(&options), first);
+    assert_eq!(record(&options.clone()), first);
+}
+
+#[test]
+fn glyphs_match_paths() {
+    // Rotated glyphs are drawn in a separate run, and decorations are drawn as paths.
+    let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="64">
+        <g text-rendering="geometricPrecision" font-size="14">
+            <text x="4" y="16" fill="green" stroke="black" stroke-width="0.5">Te<tspan rotate="20">xt</tspan></text>
+            <text x="4" y="36" fill="purple" text-decoration="underline" transform="rotate(10 32 32)">Glyph</text>
+            <text x="4" y="56" fill="orange" fill-opacity="0.5" paint-order="stroke" stroke="blue">Run</text>
+        </g>
+    </svg>"#;
+    assert_eq!(glyph_counts(&record(svg, true)).len(), 7);
+
+    let glyphs = render(svg, true);
+    let paths = render(svg, false);
+    let differing = glyphs
+        .chunks_exact(4)
+        .zip(paths.chunks_exact(4))
+        .filter(|(a, b)| a.iter().zip(*b).any(|(a, b)| a.abs_diff(*b) > 32))
+        .count();
+    assert!(differing <= 16, "{differing} pixels differ");
+}
+
+#[test]
+fn text_with_gradient_as_paths() {
+    let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="64">
+        <linearGradient id="g">
+            <stop offset="0" stop-color="red"/>
+            <stop offset="1" stop-color="blue"/>
+        </linearGradient>
+        <text x="4" y="24" font-size="16" fill="url(#g)">Hello</text>
+        <text x="4" y="48" font-size="16">World</text>
+    </svg>"#;
+    assert_eq!(glyph_counts(&record(svg, true)), [5]);
+}
@@ -0,0 +1,23 @@
+//! An SVG export backend for the [`multirender`] 2D drawing abstraction
+//!
+//! [`SvgScenePainter`] writes the commands drawn to it as an SVG document, so the same drawing code that renders to a
+//! window or image can produce vector output:
+//!
+//! - fills and strokes are written as `<path>` elements, and strokes with different start and end caps as the filled
+//!   outline of the stroke, as SVG strokes have a single cap
+//! - gradients are written as `<linearGradient>` and `<radialGradient>` elements
+//! - images are embedded as PNG data and drawn with `<image>` elements, or `<pattern>` elements when they fill a shape
+//! - layers are written as `<g>` elements with a `clip-path`, `opacity` and `mix-blend-mode`
+//! - box shadows are written as rounded rectangles with a gaussian blur filter
+//! - glyph runs are written as the outlines of the glyphs
+//!
+//! Sweep gradients have no SVG equivalent and are drawn with the average color of their stops. Layers composited with
+//! [`Compose::DestIn`](peniko::Compose::DestIn) are written as alpha masks of the content below them, other compose
+//! modes are drawn as [`Compose::SrcOver`](peniko::Compose::SrcOver). Image brushes that are padded rather than
+//! repeated or reflected are repeated, as SVG patterns can't extend the edges of an image. Custom paints are invisible.
+#![cfg_attr(docsrs, feature(doc_cfg))]
+
+mod scene;
+mod write;
+
+pub use scene::SvgScenePainter;
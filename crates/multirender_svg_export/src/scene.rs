@@ -0,0 +1,571 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use kurbo::{Affine, BezPath, Rect, Shape, Stroke, StrokeOpts};
+use multirender::{Glyph, NormalizedCoord, Paint, PaintRef, PaintScene};
+use peniko::color::{AlphaColor, Srgb};
+use peniko::{
+    BlendMode, Color, Compose, Extend, Fill, FontData, Gradient, GradientKind, ImageData,
+    ImageQuality, LinearGradientPosition, RadialGradientPosition, StyleRef,
+};
+use skrifa::instance::{LocationRef, Size};
+use skrifa::outline::{DrawSettings, OutlinePen};
+use skrifa::{GlyphId, MetadataProvider};
+
+use crate::write;
+
+const DEFAULT_TOLERANCE: f64 = 0.1;
+
+/// A [`PaintScene`] that writes an SVG document.
+///
+/// Shapes are written in the coordinates they are drawn in, with their transform as a `transform` attribute, so the
+/// document has the same size and coordinate system as the scene. Use [`SvgScenePainter::finish`] to get the document.
+///
+/// Image brushes are written as patterns, which always repeat: [`Extend::Reflect`] repeats the image and its mirror
+/// image, and [`Extend::Pad`] repeats the image instead of extending its edge pixels.
+pub struct SvgScenePainter {
+    width: f64,
+    height: f64,
+    /// The content of the `<defs>` element: clip paths, masks, gradients, patterns, images and filters.
+    defs: String,
+    /// The stack of layers, starting with the root of the document.
+    layers: Vec<Layer>,
+    /// The ids of the images embedded in `defs`, or `None` for images that can't be encoded.
+    images: HashMap<ImageKey, Option<String>>,
+    next_id: u64,
+}
+
+/// Identifies an embedded image. The same data can be drawn as images of different sizes or formats.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct ImageKey {
+    data: u64,
+    width: u32,
+    height: u32,
+    format: u8,
+    alpha_type: u8,
+}
+
+impl ImageKey {
+    fn new(image: &ImageData) -> Self {
+        Self {
+            data: image.data.id(),
+            width: image.width,
+            height: image.height,
+            format: image.format as u8,
+            alpha_type: image.alpha_type as u8,
+        }
+    }
+}
+
+struct Layer {
+    /// The start tag of the `<g>` element of the layer.
+    start: String,
+    /// Whether the layer is composited with [`Compose::DestIn`], and so is written as an alpha mask of the content of
+    /// the layer below.
+    is_mask: bool,
+    content: String,
+}
+
+impl Layer {
+    fn root() -> Self {
+        Self {
+            start: String::new(),
+            is_mask: false,
+            content: String::new(),
+        }
+    }
+}
+
+impl SvgScenePainter {
+    /// Create a painter for a document that is `width` by `height` user units in size.
+    pub fn new(width: f64, height: f64) -> Self {
+        Self {
+            width,
+            height,
+            defs: String::new(),
+            layers: vec![Layer::root()],
+            images: HashMap::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Finish the document, closing any layers that haven't been popped, and return it as a string.
+    pub fn finish(mut self) -> String {
+        while self.layers.len() > 1 {
+            self.pop_layer();
+        }
+        let (width, height) = (self.width, self.height);
+        let mut svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+        );
+        svg.push('\n');
+        if !self.defs.is_empty() {
+            writeln!(svg, "<defs>\n{}</defs>", self.defs).unwrap();
+        }
+        svg.push_str(&self.layers[0].content);
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    fn id(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{prefix}{}", self.next_id)
+    }
+
+    fn content(&mut self) -> &mut String {
+        &mut self.layers.last_mut().unwrap().content
+    }
+
+    /// The value of a `fill` or `stroke` attribute for `paint` and its opacity, or `None` if it is invisible.
+    fn paint(
+        &mut self,
+        paint: PaintRef<'_>,
+        brush_transform: Option<Affine>,
+    ) -> Option<(String, f32)> {
+        let brush_transform = brush_transform.unwrap_or_default();
+        match paint {
+            Paint::Solid(color) => Some(write::color(color)),
+            Paint::Gradient(gradient) => self.gradient(gradient, brush_transform),
+            Paint::Image(image) => {
+                let image_id = self.image(image.image)?;
+                let id = self.id("pattern");
+                // Patterns always repeat, so a reflected axis repeats a tile of the image and its mirror image, and
+                // a padded axis repeats the image like `Extend::Repeat`
+                let (width, height) = (f64::from(image.image.width), f64::from(image.image.height));
+                let mirrors = |extend, size: f64| -> (f64, &[f64]) {
+                    match extend {
+                        Extend::Reflect => (size * 2.0, &[1.0, -1.0]),
+                        Extend::Pad | Extend::Repeat => (size, &[1.0]),
+                    }
+                };
+                let (tile_width, x_scales) = mirrors(image.sampler.x_extend, width);
+                let (tile_height, y_scales) = mirrors(image.sampler.y_extend, height);
+                let mut uses = String::new();
+                for &sx in x_scales {
+                    for &sy in y_scales {
+                        // A mirrored copy is flipped about the far edge of the tile
+                        let offset = |scale: f64, size: f64| if scale < 0.0 { size } else { 0.0 };
+                        let transform = Affine::new([
+                            sx,
+                            0.0,
+                            0.0,
+                            sy,
+                            offset(sx, tile_width),
+                            offset(sy, tile_height),
+                        ]);
+                        // The copy is wrapped in a group, as usvg applies the transform of a `<use>` element to the
+                        // absolute transform of its content twice
+                        write!(
+                            uses,
+                            r##"<g{}><use href="#{image_id}"/></g>"##,
+                            write::transform("transform", transform)
+                        )
+                        .unwrap();
+                    }
+                }
+                writeln!(
+                    self.defs,
+                    r##"<pattern id="{id}" patternUnits="userSpaceOnUse" width="{tile_width}" height="{tile_height}"{}{}>{uses}</pattern>"##,
+                    write::transform("patternTransform", brush_transform),
+                    image_rendering(image.sampler.quality),
+                )
+                .unwrap();
+                Some((format!("url(#{id})"), image.sampler.alpha))
+            }
+            // Custom paints are translated into "invisible" as they can't be exported
+            Paint::Custom(_) => None,
+        }
+    }
+
+    fn gradient(&mut self, gradient: &Gradient, transform: Affine) -> Option<(String, f32)> {
+        let stops: Vec<(f32, AlphaColor<Srgb>)> = gradient
+            .stops
+            .iter()
+            .map(|stop| (stop.offset, stop.color.to_alpha_color::<Srgb>()))
+            .collect();
+        if stops.is_empty() {
+            return None;
+        }
+
+        let id = self.id("gradient");
+        let attrs = format!(
+            r#"id="{id}" gradientUnits="userSpaceOnUse"{}{}"#,
+            write::spread_method(gradient.extend),
+            write::transform("gradientTransform", transform)
+        );
+        let element = match gradient.kind {
+            GradientKind::Linear(LinearGradientPosition { start, end }) => {
+                writeln!(
+                    self.defs,
+                    r#"<linearGradient {attrs} x1="{}" y1="{}" x2="{}" y2="{}">"#,
+                    start.x, start.y, end.x, end.y
+                )
+                .unwrap();
+                "linearGradient"
+            }
+            GradientKind::Radial(RadialGradientPosition {
+                start_center,
+                start_radius,
+                end_center,
+                end_radius,
+            }) => {
+                writeln!(
+                    self.defs,
+                    r#"<radialGradient {attrs} fx="{}" fy="{}" fr="{start_radius}" cx="{}" cy="{}" r="{end_radius}">"#,
+                    start_center.x, start_center.y, end_center.x, end_center.y
+                )
+                .unwrap();
+                "radialGradient"
+            }
+            // SVG has no sweep gradients
+            GradientKind::Sweep(_) => {
+                let sum = stops.iter().fold([0.0; 4], |sum, (_, color)| {
+                    std::array::from_fn(|i| sum[i] + color.components[i])
+                });
+                let average = sum.map(|component| component / stops.len() as f32);
+                return Some(write::color(AlphaColor::new(average)));
+            }
+        };
+        for (offset, color) in stops {
+            let (color, opacity) = write::color(color);
+            writeln!(
+                self.defs,
+                r#"<stop offset="{offset}" stop-color="{color}"{}/>"#,
+                write::opacity("stop-opacity", opacity)
+            )
+            .unwrap();
+        }
+        writeln!(self.defs, "</{element}>").unwrap();
+        Some((format!("url(#{id})"), 1.0))
+    }
+
+    /// The id of the `<image>` element of `image`, which is embedded the first time it is drawn.
+    fn image(&mut self, image: &ImageData) -> Option<String> {
+        let key = ImageKey::new(image);
+        if let Some(id) = self.images.get(&key) {
+            return id.clone();
+        }
+        let id = write::image_data_url(image).map(|url| {
+            let id = self.id("image");
+            writeln!(
+                self.defs,
+                r#"<image id="{id}" width="{}" height="{}" href="{url}"/>"#,
+                image.width, image.height
+            )
+            .unwrap();
+            id
+        });
+        self.images.insert(key, id.clone());
+        id
+    }
+
+    fn draw_path(
+        &mut self,
+        style: StyleRef<'_>,
+        transform: Affine,
+        paint: PaintRef<'_>,
+        brush_transform: Option<Affine>,
+        alpha: f32,
+        path: &BezPath,
+    ) {
+        if path.elements().is_empty() {
+            return;
+        }
+        // SVG strokes have a single `stroke-linecap`, so strokes with different caps are written as their outline
+        if let StyleRef::Stroke(stroke) = style
+            && stroke.start_cap != stroke.end_cap
+        {
+            let outline = kurbo::stroke(path, stroke, &StrokeOpts::default(), DEFAULT_TOLERANCE);
+            let style = StyleRef::Fill(Fill::NonZero);
+            self.draw_path(style, transform, paint, brush_transform, alpha, &outline);
+            return;
+        }
+        let Some((paint, opacity)) = self.paint(paint, brush_transform) else {
+            return;
+        };
+        let opacity = opacity * alpha;
+        if opacity <= 0.0 {
+            return;
+        }
+        let attrs = match style {
+            StyleRef::Fill(fill) => format!(
+                r#" fill="{paint}"{}{}"#,
+                write::opacity("fill-opacity", opacity),
+                match fill {
+                    Fill::NonZero => "",
+                    Fill::EvenOdd => r#" fill-rule="evenodd""#,
+                }
+            ),
+            StyleRef::Stroke(stroke) => format!(
+                r#" fill="none" stroke="{paint}"{}{}"#,
+                write::opacity("stroke-opacity", opacity),
+                write::stroke(stroke)
+            ),
+        };
+        writeln!(
+            self.content(),
+            r#"<path d="{}"{}{attrs}/>"#,
+            path.to_svg(),
+            write::transform("transform", transform)
+        )
+        .unwrap();
+    }
+}
+
+impl PaintScene for SvgScenePainter {
+    fn reset(&mut self) {
+        self.defs.clear();
+        self.layers = vec![Layer::root()];
+        self.images.clear();
+        self.next_id = 0;
+    }
+
+    fn push_layer(
+        &mut self,
+        blend: impl Into<BlendMode>,
+        alpha: f32,
+        transform: Affine,
+        clip: &impl Shape,
+    ) {
+        let blend = blend.into();
+        let clip_id = self.id("clip");
+        writeln!(
+            self.defs,
+            r#"<clipPath id="{clip_id}" clipPathUnits="userSpaceOnUse"><path d="{}"{}/></clipPath>"#,
+            clip.to_path(DEFAULT_TOLERANCE).to_svg(),
+            write::transform("transform", transform)
+        )
+        .unwrap();
+        let start = format!(
+            r#"<g clip-path="url(#{clip_id})"{}{}>"#,
+            write::opacity("opacity", alpha),
+            write::mix_blend_mode(blend.mix)
+        );
+        self.layers.push(Layer {
+            start,
+            is_mask: blend.compose == Compose::DestIn,
+            content: String::new(),
+        });
+    }
+
+    fn push_clip_layer(&mut self, transform: Affine, clip: &impl Shape) {
+        self.push_layer(BlendMode::default(), 1.0, transform, clip);
+    }
+
+    fn pop_layer(&mut self) {
+        if self.layers.len() <= 1 {
+            return;
+        }
+        let layer = self.layers.pop().unwrap();
+        let group = format!("{}\n{}</g>\n", layer.start, layer.content);
+        if layer.is_mask {
+            // Like in vello, the layer is composited onto everything below it, so content outside of its clip path
+            // is masked out as well
+            let id = self.id("mask");
+            writeln!(
+                self.defs,
+                r#"<mask id="{id}" mask-type="alpha" maskUnits="userSpaceOnUse" x="0" y="0" width="{}" height="{}">"#,
+                self.width, self.height
+            )
+            .unwrap();
+            writeln!(self.defs, "{group}</mask>").unwrap();
+            let content = self.content();
+            *content = format!("<g mask=\"url(#{id})\">\n{content}</g>\n");
+        } else {
+            self.content().push_str(&group);
+        }
+    }
+
+    fn stroke<'a>(
+        &mut self,
+        style: &Stroke,
+        transform: Affine,
+        brush: impl Into<PaintRef<'a>>,
+        brush_transform: Option<Affine>,
+        shape: &impl Shape,
+    ) {
+        self.draw_path(
+            StyleRef::Stroke(style),
+            transform,
+            brush.into(),
+            brush_transform,
+            1.0,
+            &shape.to_path(DEFAULT_TOLERANCE),
+        );
+    }
+
+    fn fill<'a>(
+        &mut self,
+        style: Fill,
+        transform: Affine,
+        brush: impl Into<PaintRef<'a>>,
+        brush_transform: Option<Affine>,
+        shape: &impl Shape,
+    ) {
+        let brush = brush.into();
+        // Images drawn at their natural size are written as `<use>` elements of the image instead of patterns
+        if let Paint::Image(image) = brush
+            && brush_transform.is_none_or(|transform| transform == Affine::IDENTITY)
+            && shape.as_rect()
+                == Some(Rect::new(
+                    0.0,
+                    0.0,
+                    f64::from(image.image.width),
+                    f64::from(image.image.height),
+                ))
+        {
+            if let Some(id) = self.image(image.image) {
+                writeln!(
+                    self.content(),
+                    r##"<use href="#{id}"{}{}{}/>"##,
+                    write::transform("transform", transform),
+                    write::opacity("opacity", image.sampler.alpha),
+                    image_rendering(image.sampler.quality)
+                )
+                .unwrap();
+            }
+            return;
+        }
+        self.draw_path(
+            StyleRef::Fill(style),
+            transform,
+            brush,
+            brush_transform,
+            1.0,
+            &shape.to_path(DEFAULT_TOLERANCE),
+        );
+    }
+
+    fn draw_glyphs<'a, 's: 'a>(
+        &'s mut self,
+        font: &'a FontData,
+        font_size: f32,
+        _hint: bool,
+        normalized_coords: &'a [NormalizedCoord],
+        style: impl Into<StyleRef<'a>>,
+        brush: impl Into<PaintRef<'a>>,
+        brush_alpha: f32,
+        transform: Affine,
+        glyph_transform: Option<Affine>,
+        glyphs: impl Iterator<Item = Glyph>,
+    ) {
+        let Ok(font_ref) = skrifa::FontRef::from_index(font.data.data(), font.index) else {
+            return;
+        };
+        let outlines = font_ref.outline_glyphs();
+        let coords: Vec<_> = normalized_coords
+            .iter()
+            .map(|coord| skrifa::instance::NormalizedCoord::from_bits(*coord))
+            .collect();
+        let location = LocationRef::new(&coords);
+        // Outlines are y-up
+        let glyph_transform = glyph_transform.unwrap_or_default() * Affine::FLIP_Y;
+
+        let mut path = BezPath::new();
+        for glyph in glyphs {
+            let Some(outline) = outlines.get(GlyphId::new(glyph.id)) else {
+                continue;
+            };
+            let mut pen = BezPathPen(BezPath::new());
+            let settings = DrawSettings::unhinted(Size::new(font_size), location);
+            if outline.draw(settings, &mut pen).is_ok() {
+                let transform =
+                    Affine::translate((f64::from(glyph.x), f64::from(glyph.y))) * glyph_transform;
+                path.extend(transform * pen.0);
+            }
+        }
+
+        self.draw_path(
+            style.into(),
+            transform,
+            brush.into(),
+            None,
+            brush_alpha,
+            &path,
+        );
+    }
+
+    fn draw_box_shadow(
+        &mut self,
+        transform: Affine,
+        rect: Rect,
+        brush: Color,
+        radius: f64,
+        std_dev: f64,
+    ) {
+        let (fill, opacity) = write::color(brush);
+        if opacity <= 0.0 {
+            return;
+        }
+        let filter = if std_dev > 0.0 {
+            let id = self.id("filter");
+            let region = rect.inflate(3.0 * std_dev, 3.0 * std_dev);
+            writeln!(
+                self.defs,
+                r#"<filter id="{id}" filterUnits="userSpaceOnUse" x="{}" y="{}" width="{}" height="{}"><feGaussianBlur stdDeviation="{std_dev}"/></filter>"#,
+                region.x0,
+                region.y0,
+                region.width(),
+                region.height()
+            )
+            .unwrap();
+            format!(r#" filter="url(#{id})""#)
+        } else {
+            String::new()
+        };
+        let corner_radius = if radius > 0.0 {
+            format!(r#" rx="{radius}""#)
+        } else {
+            String::new()
+        };
+        writeln!(
+            self.content(),
+            r#"<rect x="{}" y="{}" width="{}" height="{}"{corner_radius} fill="{fill}"{}{filter}{}/>"#,
+            rect.x0,
+            rect.y0,
+            rect.width(),
+            rect.height(),
+            write::opacity("fill-opacity", opacity),
+            write::transform("transform", transform)
+        )
+        .unwrap();
+    }
+}
+
+/// The `image-rendering` attribute for images sampled with `quality`.
+fn image_rendering(quality: ImageQuality) -> &'static str {
+    match quality {
+        ImageQuality::Low => r#" image-rendering="optimizeSpeed""#,
+        ImageQuality::Medium | ImageQuality::High => "",
+    }
+}
+
+/// Collects an outline into a [`BezPath`].
+struct BezPathPen(BezPath);
+
+impl OutlinePen for BezPathPen {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.0.move_to((x as f64, y as f64));
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.0.line_to((x as f64, y as f64));
+    }
+
+    fn quad_to(&mut self, cx0: f32, cy0: f32, x: f32, y: f32) {
+        self.0
+            .quad_to((cx0 as f64, cy0 as f64), (x as f64, y as f64));
+    }
+
+    fn curve_to(&mut self, cx0: f32, cy0: f32, cx1: f32, cy1: f32, x: f32, y: f32) {
+        self.0.curve_to(
+            (cx0 as f64, cy0 as f64),
+            (cx1 as f64, cy1 as f64),
+            (x as f64, y as f64),
+        );
+    }
+
+    fn close(&mut self) {
+        self.0.close_path();
+    }
+}
@@ -0,0 +1,121 @@
+//! Formatting of SVG attribute values.
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use image::ImageEncoder;
+use kurbo::{Affine, Cap, Join, Stroke};
+use peniko::color::{AlphaColor, Srgb};
+use peniko::{Extend, ImageAlphaType, ImageData, ImageFormat, Mix};
+
+/// A color as an SVG color and an opacity.
+pub(crate) fn color(color: AlphaColor<Srgb>) -> (String, f32) {
+    let [r, g, b, _] = color.to_rgba8().to_u8_array();
+    (format!("#{r:02x}{g:02x}{b:02x}"), color.components[3])
+}
+
+/// A `transform` attribute, which is omitted for the identity transform.
+pub(crate) fn transform(name: &str, transform: Affine) -> String {
+    if transform == Affine::IDENTITY {
+        return String::new();
+    }
+    let [a, b, c, d, e, f] = transform.as_coeffs();
+    format!(r#" {name}="matrix({a} {b} {c} {d} {e} {f})""#)
+}
+
+/// An opacity attribute, which is omitted for opaque content.
+pub(crate) fn opacity(name: &str, opacity: f32) -> String {
+    if opacity >= 1.0 {
+        return String::new();
+    }
+    format!(r#" {name}="{}""#, opacity.max(0.0))
+}
+
+/// The `spreadMethod` attribute of a gradient.
+pub(crate) fn spread_method(extend: Extend) -> &'static str {
+    match extend {
+        Extend::Pad => "",
+        Extend::Repeat => r#" spreadMethod="repeat""#,
+        Extend::Reflect => r#" spreadMethod="reflect""#,
+    }
+}
+
+/// The attributes of a stroke, other than its paint. Attributes with the SVG default value are omitted.
+pub(crate) fn stroke(stroke: &Stroke) -> String {
+    let mut attrs = format!(r#" stroke-width="{}""#, stroke.width);
+    match stroke.start_cap {
+        Cap::Butt => {}
+        Cap::Round => attrs.push_str(r#" stroke-linecap="round""#),
+        Cap::Square => attrs.push_str(r#" stroke-linecap="square""#),
+    }
+    match stroke.join {
+        Join::Miter => {}
+        Join::Round => attrs.push_str(r#" stroke-linejoin="round""#),
+        Join::Bevel => attrs.push_str(r#" stroke-linejoin="bevel""#),
+    }
+    if stroke.join == Join::Miter && stroke.miter_limit != 4.0 {
+        attrs.push_str(&format!(r#" stroke-miterlimit="{}""#, stroke.miter_limit));
+    }
+    if !stroke.dash_pattern.is_empty() {
+        let dashes: Vec<_> = stroke.dash_pattern.iter().map(f64::to_string).collect();
+        attrs.push_str(&format!(r#" stroke-dasharray="{}""#, dashes.join(" ")));
+        if stroke.dash_offset != 0.0 {
+            attrs.push_str(&format!(r#" stroke-dashoffset="{}""#, stroke.dash_offset));
+        }
+    }
+    attrs
+}
+
+/// The `mix-blend-mode` of a layer, which is omitted for normal blending.
+pub(crate) fn mix_blend_mode(mix: Mix) -> &'static str {
+    match mix {
+        Mix::Normal => "",
+        Mix::Multiply => r#" style="mix-blend-mode:multiply""#,
+        Mix::Screen => r#" style="mix-blend-mode:screen""#,
+        Mix::Overlay => r#" style="mix-blend-mode:overlay""#,
+        Mix::Darken => r#" style="mix-blend-mode:darken""#,
+        Mix::Lighten => r#" style="mix-blend-mode:lighten""#,
+        Mix::ColorDodge => r#" style="mix-blend-mode:color-dodge""#,
+        Mix::ColorBurn => r#" style="mix-blend-mode:color-burn""#,
+        Mix::HardLight => r#" style="mix-blend-mode:hard-light""#,
+        Mix::SoftLight => r#" style="mix-blend-mode:soft-light""#,
+        Mix::Difference => r#" style="mix-blend-mode:difference""#,
+        Mix::Exclusion => r#" style="mix-blend-mode:exclusion""#,
+        Mix::Hue => r#" style="mix-blend-mode:hue""#,
+        Mix::Saturation => r#" style="mix-blend-mode:saturation""#,
+        Mix::Color => r#" style="mix-blend-mode:color""#,
+        Mix::Luminosity => r#" style="mix-blend-mode:luminosity""#,
+    }
+}
+
+/// An image as a PNG data URL, or `None` if it has an unsupported format.
+pub(crate) fn image_data_url(image: &ImageData) -> Option<String> {
+    let mut pixels = image.data.data().to_vec();
+    match image.format {
+        ImageFormat::Rgba8 => {}
+        ImageFormat::Bgra8 => pixels
+            .chunks_exact_mut(4)
+            .for_each(|pixel| pixel.swap(0, 2)),
+        _ => return None,
+    }
+    if image.alpha_type == ImageAlphaType::AlphaPremultiplied {
+        for pixel in pixels.chunks_exact_mut(4) {
+            let alpha = u16::from(pixel[3]);
+            for channel in &mut pixel[..3] {
+                if let Some(value) = (u16::from(*channel) * 255 + alpha / 2).checked_div(alpha) {
+                    *channel = value.min(255) as u8;
+                }
+            }
+        }
+    }
+
+    let mut png = Vec::new();
+    image::codecs::png::PngEncoder::new(&mut png)
+        .write_image(
+            &pixels,
+            image.width,
+            image.height,
+            image::ExtendedColorType::Rgba8,
+        )
+        .ok()?;
+    Some(format!("data:image/png;base64,{}", STANDARD.encode(png)))
+}
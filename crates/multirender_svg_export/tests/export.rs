@@ -0,0 +1,369 @@
+//! Tests that scenes exported as SVG render like the original scenes, by rendering the exported documents with
+//! `multirender_svg`.
+
+use kurbo::{Affine, Circle, Point, Rect, RoundedRect, Stroke};
+use multirender::recording::Scene;
+use multirender::{Glyph, ImageRenderer, PaintScene};
+use multirender_svg_export::SvgScenePainter;
+use multirender_vello_cpu::VelloCpuImageRenderer;
+use peniko::{
+    BlendMode, Blob, Color, Compose, Extend, Fill, FontData, Gradient, ImageAlphaType, ImageBrush,
+    ImageData, ImageFormat, Mix,
+};
+
+const SIZE: u32 = 64;
+
+static ROBOTO: &[u8] = include_bytes!("../../../assets/fonts/roboto/Roboto.ttf");
+
+fn export(scene: &Scene) -> String {
+    let mut painter = SvgScenePainter::new(SIZE as f64, SIZE as f64);
+    painter.append_scene(scene.clone(), Affine::IDENTITY);
+    painter.finish()
+}
+
+fn render<F: FnOnce(&mut <VelloCpuImageRenderer as ImageRenderer>::ScenePainter<'_>)>(
+    draw: F,
+) -> Vec<u8> {
+    let mut renderer = VelloCpuImageRenderer::new(SIZE, SIZE);
+    let mut buffer = Vec::new();
+    renderer.render_to_vec(draw, &mut buffer);
+    buffer
+}
+
+/// Assert that `scene` renders the same when it is exported as SVG, allowing for differences in antialiasing.
+fn assert_round_trips(scene: &Scene, max_differing: usize) -> String {
+    let svg = export(scene);
+    let tree = multirender_svg::usvg::Tree::from_str(&svg, &Default::default()).unwrap();
+    let exported = render(|painter| {
+        multirender_svg::render_svg_tree_with(painter, &tree, Affine::IDENTITY, &mut |_, node| {
+            panic!("unsupported node: {node:?}")
+        })
+    });
+    let expected = render(|painter| painter.append_scene(scene.clone(), Affine::IDENTITY));
+    let differing = exported
+        .chunks_exact(4)
+        .zip(expected.chunks_exact(4))
+        .filter(|(a, b)| a.iter().zip(*b).any(|(a, b)| a.abs_diff(*b) > 32))
+        .count();
+    assert!(
+        differing <= max_differing,
+        "{differing} pixels differ in\n{svg}"
+    );
+    svg
+}
+
+fn image() -> ImageData {
+    let data: Vec<u8> = (0..8 * 8)
+        .flat_map(|i| {
+            let (x, y) = (i % 8, i / 8);
+            [
+                x * 32,
+                y * 32,
+                128,
+                if (x + y) % 2 == 0 { 255 } else { 128 },
+            ]
+        })
+        .collect();
+    ImageData {
+        data: Blob::from(data),
+        format: ImageFormat::Rgba8,
+        alpha_type: ImageAlphaType::Alpha,
+        width: 8,
+        height: 8,
+    }
+}
+
+#[test]
+fn shapes_and_gradients() {
+    let mut scene = Scene::new();
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        Color::from_rgb8(0x33, 0x66, 0x99),
+        None,
+        &Rect::new(4.0, 4.0, 28.0, 28.0),
+    );
+    scene.fill(
+        Fill::EvenOdd,
+        Affine::rotate_about(0.3, Point::new(48.0, 16.0)),
+        Color::from_rgba8(0xcc, 0x33, 0x00, 0x99),
+        None,
+        &RoundedRect::new(36.0, 4.0, 60.0, 28.0, 6.0),
+    );
+    let linear = Gradient::new_linear((4.0, 0.0), (28.0, 0.0)).with_stops([
+        Color::from_rgb8(0xff, 0, 0),
+        Color::from_rgba8(0, 0, 0xff, 0x80),
+    ]);
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        &linear,
+        Some(Affine::translate((0.0, 32.0))),
+        &Rect::new(4.0, 36.0, 28.0, 60.0),
+    );
+    let radial = Gradient::new_radial((48.0, 48.0), 12.0)
+        .with_stops([Color::WHITE, Color::from_rgb8(0, 0x80, 0)]);
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        &radial,
+        None,
+        &Circle::new((48.0, 48.0), 12.0),
+    );
+    scene.stroke(
+        &Stroke::new(3.0).with_dashes(2.0, [6.0, 3.0]),
+        Affine::scale_non_uniform(1.0, 0.5),
+        Color::BLACK,
+        None,
+        &Circle::new((32.0, 64.0), 20.0),
+    );
+
+    let svg = assert_round_trips(&scene, 24);
+    assert!(svg.contains("<linearGradient"));
+    assert!(svg.contains("<radialGradient"));
+    assert!(svg.contains("stroke-dasharray"));
+}
+
+#[test]
+fn strokes_with_different_caps() {
+    let mut scene = Scene::new();
+    let line = kurbo::Line::new((12.0, 16.0), (52.0, 16.0));
+    for (i, (start_cap, end_cap)) in [
+        (kurbo::Cap::Butt, kurbo::Cap::Round),
+        (kurbo::Cap::Square, kurbo::Cap::Butt),
+        (kurbo::Cap::Round, kurbo::Cap::Round),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let stroke = Stroke::new(10.0)
+            .with_start_cap(start_cap)
+            .with_end_cap(end_cap);
+        scene.stroke(
+            &stroke,
+            Affine::translate((0.0, i as f64 * 16.0)),
+            Color::from_rgb8(0, 0, 255),
+            None,
+            &line,
+        );
+    }
+    let svg = assert_round_trips(&scene, 8);
+    assert_eq!(svg.matches("stroke-linecap").count(), 1);
+}
+
+#[test]
+fn layers() {
+    let mut scene = Scene::new();
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        Color::from_rgb8(0xff, 0xcc, 0),
+        None,
+        &Rect::new(0.0, 0.0, 64.0, 32.0),
+    );
+    scene.push_layer(
+        Mix::Multiply,
+        0.8,
+        Affine::IDENTITY,
+        &Circle::new((32.0, 24.0), 20.0),
+    );
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        Color::from_rgb8(0, 0x99, 0xcc),
+        None,
+        &Rect::new(0.0, 0.0, 64.0, 64.0),
+    );
+    scene.pop_layer();
+
+    // A mask made of a layer composited onto the content with DestIn. vello_cpu only isolates the content from the
+    // layers below it when the content layer isn't opaque.
+    scene.push_layer(
+        Mix::Normal,
+        1.0 - f32::EPSILON,
+        Affine::IDENTITY,
+        &Rect::new(4.0, 36.0, 60.0, 60.0),
+    );
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        Color::from_rgb8(0x66, 0, 0x99),
+        None,
+        &Rect::new(0.0, 32.0, 64.0, 64.0),
+    );
+    scene.push_layer(
+        BlendMode::new(Mix::Normal, Compose::DestIn),
+        1.0,
+        Affine::IDENTITY,
+        &Rect::new(0.0, 32.0, 64.0, 64.0),
+    );
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        Color::from_rgba8(0, 0, 0, 0x80),
+        None,
+        &Circle::new((32.0, 48.0), 12.0),
+    );
+    scene.pop_layer();
+    scene.pop_layer();
+
+    let svg = assert_round_trips(&scene, 24);
+    assert!(svg.contains("mix-blend-mode:multiply"));
+    assert!(svg.contains(r#"mask-type="alpha""#));
+}
+
+#[test]
+fn images() {
+    let image = image();
+    let mut scene = Scene::new();
+    scene.draw_image(
+        ImageBrush::new(image.clone()).as_ref(),
+        Affine::translate((4.0, 4.0)) * Affine::scale(3.0),
+    );
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        ImageBrush::new(image).with_extend(Extend::Repeat).as_ref(),
+        Some(Affine::translate((32.0, 32.0))),
+        &Rect::new(32.0, 32.0, 60.0, 60.0),
+    );
+
+    let svg = assert_round_trips(&scene, 24);
+    // The image is embedded once and used by both the `<use>` element and the pattern
+    assert_eq!(svg.matches("data:image/png;base64").count(), 1);
+    assert!(svg.contains("<pattern"));
+}
+
+#[test]
+fn reflected_images() {
+    let mut scene = Scene::new();
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        ImageBrush::new(image())
+            .with_extend(Extend::Reflect)
+            .as_ref(),
+        Some(Affine::translate((4.0, 4.0))),
+        &Rect::new(4.0, 4.0, 60.0, 60.0),
+    );
+
+    let svg = assert_round_trips(&scene, 24);
+    assert_eq!(svg.matches("<use").count(), 4);
+}
+
+#[test]
+fn images_sharing_data() {
+    // The same data as an 8×8 image and a 16×4 image
+    let square = image();
+    let wide = ImageData {
+        width: 16,
+        height: 4,
+        ..square.clone()
+    };
+    let mut scene = Scene::new();
+    scene.draw_image(
+        ImageBrush::new(square).as_ref(),
+        Affine::translate((4.0, 4.0)) * Affine::scale(3.0),
+    );
+    scene.draw_image(
+        ImageBrush::new(wide).as_ref(),
+        Affine::translate((4.0, 40.0)) * Affine::scale(3.0),
+    );
+
+    let svg = assert_round_trips(&scene, 24);
+    assert_eq!(svg.matches("data:image/png;base64").count(), 2);
+}
+
+#[test]
+fn mask_layer_clipped_inside_content() {
+    // A DestIn layer masks out the content outside of its clip as well
+    let mut scene = Scene::new();
+    scene.push_layer(
+        Mix::Normal,
+        1.0 - f32::EPSILON,
+        Affine::IDENTITY,
+        &Rect::new(0.0, 0.0, 64.0, 64.0),
+    );
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        Color::from_rgb8(0x66, 0, 0x99),
+        None,
+        &Rect::new(4.0, 4.0, 60.0, 60.0),
+    );
+    scene.push_layer(
+        BlendMode::new(Mix::Normal, Compose::DestIn),
+        1.0,
+        Affine::IDENTITY,
+        &Rect::new(0.0, 0.0, 32.0, 64.0),
+    );
+    scene.fill(
+        Fill::NonZero,
+        Affine::IDENTITY,
+        Color::BLACK,
+        None,
+        &Circle::new((32.0, 32.0), 16.0),
+    );
+    scene.pop_layer();
+    scene.pop_layer();
+
+    assert_round_trips(&scene, 24);
+}
+
+#[test]
+fn box_shadow() {
+    let mut scene = Scene::new();
+    scene.draw_box_shadow(
+        Affine::IDENTITY,
+        Rect::new(16.0, 16.0, 48.0, 48.0),
+        Color::from_rgba8(0, 0, 0, 0xcc),
+        6.0,
+        3.0,
+    );
+    let svg = assert_round_trips(&scene, 16);
+    assert!(svg.contains("<feGaussianBlur"));
+}
+
+#[test]
+fn glyphs_as_outlines() {
+    let font = FontData::new(Blob::new(std::sync::Arc::new(ROBOTO)), 0);
+    // "Hi !" in Roboto
+    let glyphs = |ids: &[u32], x: f32| {
+        ids.iter()
+            .enumerate()
+            .map(move |(i, id)| Glyph {
+                id: *id,
+                x: x + i as f32 * 12.0,
+                y: 0.0,
+            })
+            .collect::<Vec<_>>()
+    };
+    let mut scene = Scene::new();
+    scene.draw_glyphs(
+        &font,
+        20.0,
+        false,
+        &[],
+        Fill::NonZero,
+        Color::from_rgb8(0, 0x33, 0x66),
+        1.0,
+        Affine::translate((4.0, 28.0)),
+        None,
+        glyphs(&[44, 77], 0.0).into_iter(),
+    );
+    scene.draw_glyphs(
+        &font,
+        20.0,
+        false,
+        &[],
+        Fill::NonZero,
+        Color::from_rgb8(0x99, 0, 0),
+        1.0,
+        Affine::translate((4.0, 56.0)),
+        Some(Affine::skew(-0.3, 0.0)),
+        glyphs(&[44, 77, 5], 0.0).into_iter(),
+    );
+
+    let svg = assert_round_trips(&scene, 24);
+    assert_eq!(svg.matches("<path").count(), 2);
+}
@@ -1,4 +1,4 @@
-use multirender::ImageRenderer;
+use multirender::{DEFAULT_SCALE_FACTOR, FramePhase, FrameStats, FrameTimer, ImageRenderer};
 use multirender_wgpu_context::{BufferRenderer, BufferRendererConfig, WGPUContext};
 use rustc_hash::FxHashMap;
 use vello::{Renderer as VelloRenderer, RendererOptions, Scene as VelloScene};
@@ -10,6 +10,7 @@ pub struct VelloImageRenderer {
     buffer_renderer: BufferRenderer,
     vello_renderer: VelloRenderer,
     scene: VelloScene,
+    scale_factor: f64,
 }
 
 impl ImageRenderer for VelloImageRenderer {
@@ -47,6 +48,7 @@ impl ImageRenderer for VelloImageRenderer {
             buffer_renderer,
             vello_renderer,
             scene: VelloScene::new(),
+            scale_factor: DEFAULT_SCALE_FACTOR,
         }
     }
 
@@ -54,6 +56,10 @@ impl ImageRenderer for VelloImageRenderer {
         self.buffer_renderer.resize(width, height);
     }
 
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+    }
+
     fn reset(&mut self) {
         self.scene.reset();
     }
@@ -62,22 +68,26 @@ impl ImageRenderer for VelloImageRenderer {
         &mut self,
         draw_fn: F,
         cpu_buffer: &mut Vec<u8>,
-    ) {
+    ) -> Option<FrameStats> {
         let size = self.buffer_renderer.size();
         cpu_buffer.resize((size.width * size.height * 4) as usize, 0);
-        self.render(draw_fn, cpu_buffer);
+        self.render(draw_fn, cpu_buffer)
     }
 
     fn render<F: FnOnce(&mut Self::ScenePainter<'_>)>(
         &mut self,
         draw_fn: F,
         cpu_buffer: &mut [u8],
-    ) {
+    ) -> Option<FrameStats> {
+        let mut timer = FrameTimer::start();
+
         draw_fn(&mut VelloScenePainter {
             inner: &mut self.scene,
             renderer: Some(&mut self.vello_renderer),
             custom_paint_sources: Some(&mut FxHashMap::default()),
+            scale_factor: self.scale_factor,
         });
+        timer.record(FramePhase::Encode);
 
         let size = self.buffer_renderer.size();
         self.vello_renderer
@@ -94,10 +104,18 @@ impl ImageRenderer for VelloImageRenderer {
                 },
             )
             .expect("Got non-Send/Sync error from rendering");
+        timer.record(FramePhase::Rasterize);
 
         self.buffer_renderer.copy_texture_to_buffer(cpu_buffer);
+        timer.record(FramePhase::Wait);
 
         // Empty the Vello scene (memory optimisation)
         self.scene.reset();
+
+        let stats = timer.finish();
+        #[cfg(feature = "log_frame_times")]
+        println!("vello: {stats}");
+
+        Some(stats)
     }
 }
@@ -1,5 +1,8 @@
 use kurbo::{Affine, Rect, Shape, Stroke};
-use multirender::{CustomPaint, NormalizedCoord, Paint, PaintRef, PaintScene};
+use multirender::{
+    CustomPaint, DEFAULT_SCALE_FACTOR, NormalizedCoord, Paint, PaintRef, PaintScene, hint_at_scale,
+    root_transform,
+};
 use peniko::{BlendMode, BrushRef, Color, Fill, FontData, ImageBrush, StyleRef};
 use rustc_hash::FxHashMap;
 use vello::Renderer as VelloRenderer;
@@ -10,6 +13,7 @@ pub struct VelloScenePainter<'r, 's> {
     pub(crate) renderer: Option<&'r mut VelloRenderer>,
     pub(crate) custom_paint_sources: Option<&'r mut FxHashMap<u64, Box<dyn CustomPaintSource>>>,
     pub(crate) inner: &'s mut vello::Scene,
+    pub(crate) scale_factor: f64,
 }
 
 impl VelloScenePainter<'_, '_> {
@@ -18,9 +22,19 @@ impl VelloScenePainter<'_, '_> {
             renderer: None,
             custom_paint_sources: None,
             inner: scene,
+            scale_factor: DEFAULT_SCALE_FACTOR,
         }
     }
 
+    /// Set the factor by which all drawing commands are scaled
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+    }
+
+    fn root_transform(&self) -> Affine {
+        root_transform(self.scale_factor)
+    }
+
     fn render_custom_source(&mut self, custom_paint: CustomPaint) -> Option<peniko::ImageBrush> {
         let (Some(renderer), Some(custom_paint_sources)) =
             (&mut self.renderer, &mut self.custom_paint_sources)
@@ -57,12 +71,18 @@ impl PaintScene for VelloScenePainter<'_, '_> {
         transform: Affine,
         clip: &impl Shape,
     ) {
-        self.inner
-            .push_layer(Fill::NonZero, blend, alpha, transform, clip);
+        self.inner.push_layer(
+            Fill::NonZero,
+            blend,
+            alpha,
+            self.root_transform() * transform,
+            clip,
+        );
     }
 
     fn push_clip_layer(&mut self, transform: Affine, clip: &impl Shape) {
-        self.inner.push_clip_layer(Fill::NonZero, transform, clip);
+        self.inner
+            .push_clip_layer(Fill::NonZero, self.root_transform() * transform, clip);
     }
 
     fn pop_layer(&mut self) {
@@ -79,6 +99,7 @@ impl PaintScene for VelloScenePainter<'_, '_> {
     ) {
         let paint_ref: PaintRef<'_> = paint_ref.into();
         let brush_ref: BrushRef<'_> = paint_ref.into();
+        let transform = self.root_transform() * transform;
         self.inner
             .stroke(style, transform, brush_ref, brush_transform, shape);
     }
@@ -110,6 +131,7 @@ impl PaintScene for VelloScenePainter<'_, '_> {
             }
         };
 
+        let transform = self.root_transform() * transform;
         self.inner
             .fill(style, transform, brush_ref, brush_transform, shape);
     }
@@ -130,11 +152,11 @@ impl PaintScene for VelloScenePainter<'_, '_> {
         self.inner
             .draw_glyphs(font)
             .font_size(font_size)
-            .hint(hint)
+            .hint(hint_at_scale(hint, self.scale_factor))
             .normalized_coords(normalized_coords)
             .brush(paint.into())
             .brush_alpha(brush_alpha)
-            .transform(transform)
+            .transform(root_transform(self.scale_factor) * transform)
             .glyph_transform(glyph_transform)
             .draw(
                 style,
@@ -154,6 +176,7 @@ impl PaintScene for VelloScenePainter<'_, '_> {
         radius: f64,
         std_dev: f64,
     ) {
+        let transform = self.root_transform() * transform;
         self.inner
             .draw_blurred_rounded_rect(transform, rect, brush, radius, std_dev);
     }
@@ -1,5 +1,6 @@
-use debug_timer::debug_timer;
-use multirender::{WindowHandle, WindowRenderer};
+use multirender::{
+    DEFAULT_SCALE_FACTOR, FramePhase, FrameStats, FrameTimer, WindowHandle, WindowRenderer,
+};
 use multirender_wgpu_context::{
     DeviceHandle, SurfaceRenderer, SurfaceRendererConfiguration, TextureConfiguration, WGPUContext,
 };
@@ -69,6 +70,7 @@ pub struct VelloWindowRenderer {
     wgpu_context: WGPUContext,
     scene: VelloScene,
     config: VelloRendererOptions,
+    scale_factor: f64,
 
     custom_paint_sources: FxHashMap<u64, Box<dyn CustomPaintSource>>,
 }
@@ -91,6 +93,7 @@ impl VelloWindowRenderer {
             render_state: RenderState::Suspended,
             window_handle: None,
             scene: VelloScene::new(),
+            scale_factor: DEFAULT_SCALE_FACTOR,
             custom_paint_sources: FxHashMap::default(),
         }
     }
@@ -197,22 +200,27 @@ impl WindowRenderer for VelloWindowRenderer {
         };
     }
 
-    fn render<F: FnOnce(&mut Self::ScenePainter<'_>)>(&mut self, draw_fn: F) {
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+    }
+
+    fn render<F: FnOnce(&mut Self::ScenePainter<'_>)>(&mut self, draw_fn: F) -> Option<FrameStats> {
         let RenderState::Active(state) = &mut self.render_state else {
-            return;
+            return None;
         };
 
         let render_surface = &mut state.render_surface;
 
-        debug_timer!(timer, feature = "log_frame_times");
+        let mut timer = FrameTimer::start();
 
         // Regenerate the vello scene
         draw_fn(&mut VelloScenePainter {
             inner: &mut self.scene,
             renderer: Some(&mut state.renderer),
             custom_paint_sources: Some(&mut self.custom_paint_sources),
+            scale_factor: self.scale_factor,
         });
-        timer.record_time("cmd");
+        timer.record(FramePhase::Encode);
 
         let texture_view = render_surface.target_texture_view();
         state
@@ -230,25 +238,30 @@ impl WindowRenderer for VelloWindowRenderer {
                 },
             )
             .expect("failed to render to texture");
-        timer.record_time("render");
+        timer.record(FramePhase::Rasterize);
 
         drop(texture_view);
 
         render_surface.maybe_blit_and_present();
-        timer.record_time("present");
+        timer.record(FramePhase::Present);
 
         render_surface
             .device()
             .poll(wgpu::PollType::wait_indefinitely())
             .unwrap();
 
-        timer.record_time("wait");
-        timer.print_times("vello: ");
+        timer.record(FramePhase::Wait);
+
+        let stats = timer.finish();
+        #[cfg(feature = "log_frame_times")]
+        println!("vello: {stats}");
 
         // static COUNTER: AtomicU64 = AtomicU64::new(0);
         // println!("FRAME {}", COUNTER.fetch_add(1, atomic::Ordering::Relaxed));
 
         // Empty the Vello scene (memory optimisation)
         self.scene.reset();
+
+        Some(stats)
     }
 }
@@ -1,57 +1,218 @@
 use crate::VelloCpuScenePainter;
-use debug_timer::debug_timer;
-use multirender::ImageRenderer;
+use multirender::{FramePhase, FrameStats, FrameTimer, ImageRenderer};
+use std::fmt;
 use vello_cpu::{RenderContext, RenderMode};
 
+/// Error returned when creating or resizing a [`VelloCpuImageRenderer`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VelloCpuImageRendererError {
+    /// The width or height exceeds [`ImageRenderer::MAX_DIMENSION`]
+    TooLarge { width: u32, height: u32 },
+}
+
+impl fmt::Display for VelloCpuImageRendererError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VelloCpuImageRendererError::TooLarge { width, height } => write!(
+                f,
+                "vello_cpu cannot render a {width}x{height} image: dimensions are limited to {max}x{max}. \
+                 Use multirender::TiledRenderer to render larger images.",
+                max = u16::MAX
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VelloCpuImageRendererError {}
+
 pub struct VelloCpuImageRenderer {
     scene: VelloCpuScenePainter,
 }
 
+impl VelloCpuImageRenderer {
+    /// Create a renderer, returning an error if `width` or `height` exceed [`ImageRenderer::MAX_DIMENSION`]
+    pub fn try_new(width: u32, height: u32) -> Result<Self, VelloCpuImageRendererError> {
+        let (width, height) = checked_dimensions(width, height)?;
+        Ok(Self {
+            scene: VelloCpuScenePainter::new(RenderContext::new(width, height)),
+        })
+    }
+
+    /// Resize the renderer, returning an error if `width` or `height` exceed [`ImageRenderer::MAX_DIMENSION`]
+    pub fn try_resize(
+        &mut self,
+        width: u32,
+        height: u32,
+    ) -> Result<(), VelloCpuImageRendererError> {
+        let (width, height) = checked_dimensions(width, height)?;
+        self.scene.ctx = RenderContext::new(width, height);
+        Ok(())
+    }
+}
+
+fn checked_dimensions(width: u32, height: u32) -> Result<(u16, u16), VelloCpuImageRendererError> {
+    match (u16::try_from(width), u16::try_from(height)) {
+        (Ok(width), Ok(height)) => Ok((width, height)),
+        _ => Err(VelloCpuImageRendererError::TooLarge { width, height }),
+    }
+}
+
 impl ImageRenderer for VelloCpuImageRenderer {
     type ScenePainter<'a> = VelloCpuScenePainter;
 
+    const MAX_DIMENSION: u32 = u16::MAX as u32;
+
+    /// Create a renderer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` or `height` exceed [`ImageRenderer::MAX_DIMENSION`]; use
+    /// [`VelloCpuImageRenderer::try_new`] to handle this instead.
     fn new(width: u32, height: u32) -> Self {
-        Self {
-            scene: VelloCpuScenePainter(RenderContext::new(width as u16, height as u16)),
-        }
+        Self::try_new(width, height).unwrap_or_else(|err| panic!("{err}"))
     }
 
+    /// Resize the renderer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` or `height` exceed [`ImageRenderer::MAX_DIMENSION`]; use
+    /// [`VelloCpuImageRenderer::try_resize`] to handle this instead.
     fn resize(&mut self, width: u32, height: u32) {
-        self.scene.0 = RenderContext::new(width as u16, height as u16);
+        self.try_resize(width, height)
+            .unwrap_or_else(|err| panic!("{err}"));
+    }
+
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scene.set_scale_factor(scale_factor);
     }
 
     fn reset(&mut self) {
-        self.scene.0.reset();
+        self.scene.ctx.reset();
     }
 
-    fn render<F: FnOnce(&mut Self::ScenePainter<'_>)>(&mut self, draw_fn: F, buffer: &mut [u8]) {
-        debug_timer!(timer, feature = "log_frame_times");
+    fn render<F: FnOnce(&mut Self::ScenePainter<'_>)>(
+        &mut self,
+        draw_fn: F,
+        buffer: &mut [u8],
+    ) -> Option<FrameStats> {
+        let mut timer = FrameTimer::start();
 
         draw_fn(&mut self.scene);
-        timer.record_time("cmds");
+        timer.record(FramePhase::Encode);
 
-        self.scene.0.flush();
-        timer.record_time("flush");
+        self.scene.ctx.flush();
+        timer.record(FramePhase::Flush);
 
-        self.scene.0.render_to_buffer(
+        self.scene.ctx.render_to_buffer(
             buffer,
-            self.scene.0.width(),
-            self.scene.0.height(),
+            self.scene.ctx.width(),
+            self.scene.ctx.height(),
             RenderMode::OptimizeSpeed,
         );
-        timer.record_time("render");
+        timer.record(FramePhase::Rasterize);
 
-        timer.print_times("vello_cpu: ");
+        let stats = timer.finish();
+        #[cfg(feature = "log_frame_times")]
+        println!("vello_cpu: {stats}");
+
+        Some(stats)
     }
 
     fn render_to_vec<F: FnOnce(&mut Self::ScenePainter<'_>)>(
         &mut self,
         draw_fn: F,
         buffer: &mut Vec<u8>,
-    ) {
-        let width = self.scene.0.width();
-        let height = self.scene.0.height();
+    ) -> Option<FrameStats> {
+        let width = self.scene.ctx.width();
+        let height = self.scene.ctx.height();
         buffer.resize(width as usize * height as usize * 4, 0);
-        self.render(draw_fn, buffer);
+        self.render(draw_fn, buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kurbo::{Affine, Rect};
+    use multirender::{PaintScene, Scene};
+    use peniko::{Color, Fill};
+
+    #[test]
+    fn try_new_rejects_oversized_dimensions() {
+        assert!(VelloCpuImageRenderer::try_new(65535, 1).is_ok());
+        assert_eq!(
+            VelloCpuImageRenderer::try_new(70000, 10).err(),
+            Some(VelloCpuImageRendererError::TooLarge {
+                width: 70000,
+                height: 10
+            })
+        );
+        assert!(VelloCpuImageRenderer::try_new(10, 65536).is_err());
+    }
+
+    #[test]
+    fn try_resize_keeps_size_on_error() {
+        let mut renderer = VelloCpuImageRenderer::try_new(4, 2).unwrap();
+        assert_eq!(
+            renderer.try_resize(1, 70000),
+            Err(VelloCpuImageRendererError::TooLarge {
+                width: 1,
+                height: 70000
+            })
+        );
+        let mut buffer = Vec::new();
+        renderer.render_to_vec(|_| {}, &mut buffer);
+        assert_eq!(buffer.len(), 4 * 2 * 4);
+
+        renderer.try_resize(3, 5).unwrap();
+        renderer.render_to_vec(|_| {}, &mut buffer);
+        assert_eq!(buffer.len(), 3 * 5 * 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot render a 70000x10 image")]
+    fn new_panics_on_oversized_dimensions() {
+        VelloCpuImageRenderer::new(70000, 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot render a 10x70000 image")]
+    fn resize_panics_on_oversized_dimensions() {
+        VelloCpuImageRenderer::new(10, 10).resize(10, 70000);
+    }
+
+    #[test]
+    fn appended_scenes_are_scaled_once() {
+        // A 1x1 square at (1, 0) in the appended scene, offset by 1 logical unit along x
+        let mut fragment = Scene::new();
+        fragment.fill(
+            Fill::NonZero,
+            Affine::IDENTITY,
+            Color::WHITE,
+            None,
+            &Rect::new(1.0, 0.0, 2.0, 1.0),
+        );
+
+        let mut renderer = VelloCpuImageRenderer::new(10, 2);
+        renderer.set_scale_factor(2.0);
+        let mut buffer = Vec::new();
+        renderer.render_to_vec(
+            |scene| scene.append_scene(fragment, Affine::translate((1.0, 0.0))),
+            &mut buffer,
+        );
+
+        // Logical x in 2..3 maps to physical pixels 4 and 5, in both rows
+        for y in 0..2 {
+            let lit: Vec<usize> = (0..10)
+                .filter(|x| buffer[(y * 10 + x) * 4 + 3] == 255)
+                .collect();
+            assert_eq!(lit, vec![4, 5]);
+        }
+        assert!(
+            buffer
+                .chunks_exact(4)
+                .all(|pixel| matches!(pixel[3], 0 | 255))
+        );
     }
 }
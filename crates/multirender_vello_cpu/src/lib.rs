@@ -5,11 +5,17 @@ mod image_renderer;
 mod scene;
 mod window_renderer;
 
-pub use image_renderer::VelloCpuImageRenderer;
+pub use image_renderer::{VelloCpuImageRenderer, VelloCpuImageRendererError};
 pub use scene::VelloCpuScenePainter;
 
+/// Renders recorded scenes with [`VelloCpuImageRenderer`] on a background thread
+pub type VelloCpuThreadedImageRenderer = multirender::ThreadedImageRenderer<VelloCpuImageRenderer>;
+
 #[cfg(any(
     feature = "pixels_window_renderer",
     feature = "softbuffer_window_renderer"
 ))]
 pub use window_renderer::*;
+
+/// Renders recorded scenes of any size with [`VelloCpuImageRenderer`] by splitting them into tiles
+pub type VelloCpuTiledRenderer = multirender::TiledRenderer<VelloCpuImageRenderer>;
@@ -1,5 +1,8 @@
 use kurbo::{Affine, Rect, Shape, Stroke};
-use multirender::{NormalizedCoord, Paint, PaintRef, PaintScene};
+use multirender::{
+    DEFAULT_SCALE_FACTOR, NormalizedCoord, Paint, PaintRef, PaintScene, hint_at_scale,
+    root_transform, scaled_tolerance,
+};
 use peniko::{BlendMode, Color, Fill, FontData, ImageBrush, StyleRef};
 use vello_cpu::{ImageSource, PaintType, Pixmap};
 
@@ -35,19 +38,51 @@ fn convert_image_cached(image: &peniko::ImageData) -> ImageSource {
         .clone()
 }
 
-pub struct VelloCpuScenePainter(pub vello_cpu::RenderContext);
+/// Wraps a [`vello_cpu::RenderContext`] along with the scale factor that drawing commands are scaled by
+///
+/// The render context is the named field [`ctx`](Self::ctx). This is a breaking change from earlier versions, where
+/// the painter was a tuple struct and the render context its field `.0`.
+pub struct VelloCpuScenePainter {
+    pub ctx: vello_cpu::RenderContext,
+    pub(crate) scale_factor: f64,
+}
 
 impl VelloCpuScenePainter {
+    pub fn new(ctx: vello_cpu::RenderContext) -> Self {
+        Self::with_scale_factor(ctx, DEFAULT_SCALE_FACTOR)
+    }
+
+    pub fn with_scale_factor(ctx: vello_cpu::RenderContext, scale_factor: f64) -> Self {
+        Self { ctx, scale_factor }
+    }
+
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+    }
+
+    fn set_transform(&mut self, transform: Affine) {
+        self.ctx
+            .set_transform(root_transform(self.scale_factor) * transform);
+    }
+
+    fn tolerance(&self) -> f64 {
+        scaled_tolerance(DEFAULT_TOLERANCE, self.scale_factor)
+    }
+
     pub fn finish(self) -> Pixmap {
-        let mut pixmap = Pixmap::new(self.0.width(), self.0.height());
-        self.0.render_to_pixmap(&mut pixmap);
+        let mut pixmap = Pixmap::new(self.ctx.width(), self.ctx.height());
+        self.ctx.render_to_pixmap(&mut pixmap);
         pixmap
     }
 }
 
 impl PaintScene for VelloCpuScenePainter {
     fn reset(&mut self) {
-        self.0.reset();
+        self.ctx.reset();
     }
 
     fn push_layer(
@@ -57,9 +92,9 @@ impl PaintScene for VelloCpuScenePainter {
         transform: Affine,
         clip: &impl Shape,
     ) {
-        self.0.set_transform(transform);
-        self.0.push_layer(
-            Some(&clip.into_path(DEFAULT_TOLERANCE)),
+        self.set_transform(transform);
+        self.ctx.push_layer(
+            Some(&clip.into_path(self.tolerance())),
             Some(blend.into()),
             Some(alpha),
             None,
@@ -68,12 +103,12 @@ impl PaintScene for VelloCpuScenePainter {
     }
 
     fn push_clip_layer(&mut self, transform: Affine, clip: &impl Shape) {
-        self.0.set_transform(transform);
-        self.0.push_clip_layer(&clip.into_path(DEFAULT_TOLERANCE));
+        self.set_transform(transform);
+        self.ctx.push_clip_layer(&clip.into_path(self.tolerance()));
     }
 
     fn pop_layer(&mut self) {
-        self.0.pop_layer();
+        self.ctx.pop_layer();
     }
 
     fn stroke<'a>(
@@ -84,13 +119,13 @@ impl PaintScene for VelloCpuScenePainter {
         brush_transform: Option<Affine>,
         shape: &impl Shape,
     ) {
-        self.0.set_transform(transform);
-        self.0.set_stroke(style.clone());
-        self.0
+        self.set_transform(transform);
+        self.ctx.set_stroke(style.clone());
+        self.ctx
             .set_paint(multirender_paint_to_vello_cpu_paint(paint.into()));
-        self.0
+        self.ctx
             .set_paint_transform(brush_transform.unwrap_or(Affine::IDENTITY));
-        self.0.stroke_path(&shape.into_path(DEFAULT_TOLERANCE));
+        self.ctx.stroke_path(&shape.into_path(self.tolerance()));
     }
 
     fn fill<'a>(
@@ -101,13 +136,13 @@ impl PaintScene for VelloCpuScenePainter {
         brush_transform: Option<Affine>,
         shape: &impl Shape,
     ) {
-        self.0.set_transform(transform);
-        self.0.set_fill_rule(style);
-        self.0
+        self.set_transform(transform);
+        self.ctx.set_fill_rule(style);
+        self.ctx
             .set_paint(multirender_paint_to_vello_cpu_paint(paint.into()));
-        self.0
+        self.ctx
             .set_paint_transform(brush_transform.unwrap_or(Affine::IDENTITY));
-        self.0.fill_path(&shape.into_path(DEFAULT_TOLERANCE));
+        self.ctx.fill_path(&shape.into_path(self.tolerance()));
     }
 
     fn draw_glyphs<'a, 's: 'a>(
@@ -123,8 +158,8 @@ impl PaintScene for VelloCpuScenePainter {
         glyph_transform: Option<Affine>,
         glyphs: impl Iterator<Item = multirender::Glyph>,
     ) {
-        self.0.set_transform(transform);
-        self.0
+        self.set_transform(transform);
+        self.ctx
             .set_paint(multirender_paint_to_vello_cpu_paint(paint.into()));
 
         fn into_vello_cpu_glyph(g: multirender::Glyph) -> vello_cpu::Glyph {
@@ -138,21 +173,21 @@ impl PaintScene for VelloCpuScenePainter {
         let style: StyleRef<'a> = style.into();
         match style {
             StyleRef::Fill(fill) => {
-                self.0.set_fill_rule(fill);
-                self.0
+                self.ctx.set_fill_rule(fill);
+                self.ctx
                     .glyph_run(font)
                     .font_size(font_size)
-                    .hint(hint)
+                    .hint(hint_at_scale(hint, self.scale_factor))
                     .normalized_coords(normalized_coords)
                     .glyph_transform(glyph_transform.unwrap_or_default())
                     .fill_glyphs(glyphs.map(into_vello_cpu_glyph));
             }
             StyleRef::Stroke(stroke) => {
-                self.0.set_stroke(stroke.clone());
-                self.0
+                self.ctx.set_stroke(stroke.clone());
+                self.ctx
                     .glyph_run(font)
                     .font_size(font_size)
-                    .hint(hint)
+                    .hint(hint_at_scale(hint, self.scale_factor))
                     .normalized_coords(normalized_coords)
                     .glyph_transform(glyph_transform.unwrap_or_default())
                     .stroke_glyphs(glyphs.map(into_vello_cpu_glyph));
@@ -167,9 +202,9 @@ impl PaintScene for VelloCpuScenePainter {
         radius: f64,
         std_dev: f64,
     ) {
-        self.0.set_transform(transform);
-        self.0.set_paint(PaintType::Solid(color));
-        self.0
+        self.set_transform(transform);
+        self.ctx.set_paint(PaintType::Solid(color));
+        self.ctx
             .fill_blurred_rounded_rect(&rect, radius as f32, std_dev as f32);
     }
 }
@@ -1,5 +1,8 @@
 use kurbo::{Affine, Rect, Shape, Stroke};
-use multirender::{NormalizedCoord, Paint, PaintRef, PaintScene};
+use multirender::{
+    DEFAULT_SCALE_FACTOR, NormalizedCoord, Paint, PaintRef, PaintScene, hint_at_scale,
+    root_transform, scaled_tolerance,
+};
 use peniko::{BlendMode, Color, Fill, FontData, ImageBrush, ImageData, StyleRef};
 use rustc_hash::FxHashMap;
 use vello_common::paint::{ImageId, ImageSource, PaintType};
@@ -89,6 +92,7 @@ pub struct VelloHybridScenePainter<'s> {
     pub(crate) scene: &'s mut vello_hybrid::Scene,
     pub(crate) layer_stack: Vec<LayerKind>,
     pub(crate) image_manager: ImageManager<'s>,
+    pub(crate) scale_factor: f64,
 }
 
 impl VelloHybridScenePainter<'_> {
@@ -100,8 +104,23 @@ impl VelloHybridScenePainter<'_> {
             scene,
             layer_stack: Vec::with_capacity(16),
             image_manager,
+            scale_factor: DEFAULT_SCALE_FACTOR,
         }
     }
+
+    /// Set the factor by which all drawing commands are scaled
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+    }
+
+    fn set_transform(&mut self, transform: Affine) {
+        self.scene
+            .set_transform(root_transform(self.scale_factor) * transform);
+    }
+
+    fn tolerance(&self) -> f64 {
+        scaled_tolerance(DEFAULT_TOLERANCE, self.scale_factor)
+    }
 }
 
 impl PaintScene for VelloHybridScenePainter<'_> {
@@ -116,10 +135,10 @@ impl PaintScene for VelloHybridScenePainter<'_> {
         transform: Affine,
         clip: &impl Shape,
     ) {
-        self.scene.set_transform(transform);
+        self.set_transform(transform);
         self.layer_stack.push(LayerKind::Layer);
         self.scene.push_layer(
-            Some(&clip.into_path(DEFAULT_TOLERANCE)),
+            Some(&clip.into_path(self.tolerance())),
             Some(blend.into()),
             Some(alpha),
             None,
@@ -128,10 +147,9 @@ impl PaintScene for VelloHybridScenePainter<'_> {
     }
 
     fn push_clip_layer(&mut self, transform: Affine, clip: &impl Shape) {
-        self.scene.set_transform(transform);
+        self.set_transform(transform);
         self.layer_stack.push(LayerKind::Clip);
-        self.scene
-            .push_clip_path(&clip.into_path(DEFAULT_TOLERANCE));
+        self.scene.push_clip_path(&clip.into_path(self.tolerance()));
     }
 
     fn pop_layer(&mut self) {
@@ -151,13 +169,13 @@ impl PaintScene for VelloHybridScenePainter<'_> {
         brush_transform: Option<Affine>,
         shape: &impl Shape,
     ) {
-        self.scene.set_transform(transform);
+        self.set_transform(transform);
         self.scene.set_stroke(style.clone());
         let paint = multirender_paint_to_vello_hybrid_paint(paint.into(), &mut self.image_manager);
         self.scene.set_paint(paint);
         self.scene
             .set_paint_transform(brush_transform.unwrap_or(Affine::IDENTITY));
-        self.scene.stroke_path(&shape.into_path(DEFAULT_TOLERANCE));
+        self.scene.stroke_path(&shape.into_path(self.tolerance()));
     }
 
     fn fill<'a>(
@@ -168,13 +186,13 @@ impl PaintScene for VelloHybridScenePainter<'_> {
         brush_transform: Option<Affine>,
         shape: &impl Shape,
     ) {
-        self.scene.set_transform(transform);
+        self.set_transform(transform);
         self.scene.set_fill_rule(style);
         let paint = multirender_paint_to_vello_hybrid_paint(paint.into(), &mut self.image_manager);
         self.scene.set_paint(paint);
         self.scene
             .set_paint_transform(brush_transform.unwrap_or(Affine::IDENTITY));
-        self.scene.fill_path(&shape.into_path(DEFAULT_TOLERANCE));
+        self.scene.fill_path(&shape.into_path(self.tolerance()));
     }
 
     fn draw_glyphs<'a, 's: 'a>(
@@ -192,7 +210,7 @@ impl PaintScene for VelloHybridScenePainter<'_> {
     ) {
         let paint = multirender_paint_to_vello_hybrid_paint(paint.into(), &mut self.image_manager);
         self.scene.set_paint(paint);
-        self.scene.set_transform(transform);
+        self.set_transform(transform);
 
         fn into_vello_hybrid_glyph(g: multirender::Glyph) -> vello_common::glyph::Glyph {
             vello_common::glyph::Glyph {
@@ -209,7 +227,7 @@ impl PaintScene for VelloHybridScenePainter<'_> {
                 self.scene
                     .glyph_run(font)
                     .font_size(font_size)
-                    .hint(hint)
+                    .hint(hint_at_scale(hint, self.scale_factor))
                     .normalized_coords(normalized_coords)
                     .glyph_transform(glyph_transform.unwrap_or_default())
                     .fill_glyphs(glyphs.map(into_vello_hybrid_glyph));
@@ -219,7 +237,7 @@ impl PaintScene for VelloHybridScenePainter<'_> {
                 self.scene
                     .glyph_run(font)
                     .font_size(font_size)
-                    .hint(hint)
+                    .hint(hint_at_scale(hint, self.scale_factor))
                     .normalized_coords(normalized_coords)
                     .glyph_transform(glyph_transform.unwrap_or_default())
                     .stroke_glyphs(glyphs.map(into_vello_hybrid_glyph));
@@ -236,7 +254,7 @@ impl PaintScene for VelloHybridScenePainter<'_> {
     ) {
         // FIXME: implement once supported in vello_hybrid
         //
-        // self.scene.set_transform(transform);
+        // self.set_transform(transform);
         // self.scene.set_paint(PaintType::Solid(color));
         // self.scene
         //     .fill_blurred_rounded_rect(&rect, radius as f32, std_dev as f32);
@@ -1,5 +1,6 @@
-use debug_timer::debug_timer;
-use multirender::{WindowHandle, WindowRenderer};
+use multirender::{
+    DEFAULT_SCALE_FACTOR, FramePhase, FrameStats, FrameTimer, WindowHandle, WindowRenderer,
+};
 use multirender_wgpu_context::{
     DeviceHandle, SurfaceRenderer, SurfaceRendererConfiguration, WGPUContext,
 };
@@ -55,6 +56,7 @@ pub struct VelloHybridWindowRenderer {
     wgpu_context: WGPUContext,
     scene: VelloHybridScene,
     config: VelloHybridRendererOptions,
+    scale_factor: f64,
     // custom_paint_sources: FxHashMap<u64, Box<dyn CustomPaintSource>>,
     cached_images: FxHashMap<u64, ImageId>,
 }
@@ -78,6 +80,7 @@ impl VelloHybridWindowRenderer {
             render_state: RenderState::Suspended,
             window_handle: None,
             scene: VelloHybridScene::new_with(0, 0, render_settings),
+            scale_factor: DEFAULT_SCALE_FACTOR,
             // custom_paint_sources: FxHashMap::default(),
             cached_images: FxHashMap::default(),
         }
@@ -200,14 +203,18 @@ impl WindowRenderer for VelloHybridWindowRenderer {
         }
     }
 
-    fn render<F: FnOnce(&mut Self::ScenePainter<'_>)>(&mut self, draw_fn: F) {
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
+    }
+
+    fn render<F: FnOnce(&mut Self::ScenePainter<'_>)>(&mut self, draw_fn: F) -> Option<FrameStats> {
         let RenderState::Active(state) = &mut self.render_state else {
-            return;
+            return None;
         };
 
         let render_surface = &mut state.render_surface;
 
-        debug_timer!(timer, feature = "log_frame_times");
+        let mut timer = FrameTimer::start();
 
         let mut encoder =
             render_surface
@@ -229,8 +236,9 @@ impl WindowRenderer for VelloHybridWindowRenderer {
             scene: &mut self.scene,
             layer_stack: Vec::new(),
             image_manager,
+            scale_factor: self.scale_factor,
         });
-        timer.record_time("cmd");
+        timer.record(FramePhase::Encode);
 
         let texture_view = render_surface.target_texture_view();
 
@@ -249,25 +257,30 @@ impl WindowRenderer for VelloHybridWindowRenderer {
             )
             .expect("failed to render to texture");
         render_surface.queue().submit([encoder.finish()]);
-        timer.record_time("render");
+        timer.record(FramePhase::Rasterize);
 
         drop(texture_view);
 
         render_surface.maybe_blit_and_present();
-        timer.record_time("present");
+        timer.record(FramePhase::Present);
 
         render_surface
             .device()
             .poll(wgpu::PollType::wait_indefinitely())
             .unwrap();
 
-        timer.record_time("wait");
-        timer.print_times("vello_hybrid: ");
+        timer.record(FramePhase::Wait);
+
+        let stats = timer.finish();
+        #[cfg(feature = "log_frame_times")]
+        println!("vello_hybrid: {stats}");
 
         // static COUNTER: AtomicU64 = AtomicU64::new(0);
         // println!("FRAME {}", COUNTER.fetch_add(1, atomic::Ordering::Relaxed));
 
         // Empty the Vello scene (memory optimisation)
         self.scene.reset();
+
+        Some(stats)
     }
 }
@@ -2,8 +2,9 @@
 
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
-use debug_timer::debug_timer;
-use multirender::{ImageRenderer, WindowHandle, WindowRenderer};
+use multirender::{
+    FramePhase, FrameStats, FrameTimer, ImageRenderer, WindowHandle, WindowRenderer,
+};
 use pixels::{Pixels, SurfaceTexture, wgpu::Color};
 use std::sync::Arc;
 
@@ -93,22 +94,34 @@ impl<Renderer: ImageRenderer> WindowRenderer for PixelsWindowRenderer<Renderer>
         };
     }
 
-    fn render<F: FnOnce(&mut Renderer::ScenePainter<'_>)>(&mut self, draw_fn: F) {
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.renderer.set_scale_factor(scale_factor);
+    }
+
+    fn render<F: FnOnce(&mut Renderer::ScenePainter<'_>)>(
+        &mut self,
+        draw_fn: F,
+    ) -> Option<FrameStats> {
         let RenderState::Active(state) = &mut self.render_state else {
-            return;
+            return None;
         };
 
-        debug_timer!(timer, feature = "log_frame_times");
+        let mut timer = FrameTimer::start();
 
         // Paint
-        self.renderer.render(draw_fn, state.pixels.frame_mut());
-        timer.record_time("render");
+        let render_stats = self.renderer.render(draw_fn, state.pixels.frame_mut());
+        timer.record_nested(render_stats);
 
         state.pixels.render().unwrap();
-        timer.record_time("present");
-        timer.print_times("pixels: ");
+        timer.record(FramePhase::Present);
+
+        let stats = timer.finish();
+        #[cfg(feature = "log_frame_times")]
+        println!("pixels: {stats}");
 
         // Reset the renderer ready for the next render
         self.renderer.reset();
+
+        Some(stats)
     }
 }
@@ -2,8 +2,9 @@
 
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
-use debug_timer::debug_timer;
-use multirender::{ImageRenderer, WindowHandle, WindowRenderer};
+use multirender::{
+    FramePhase, FrameStats, FrameTimer, ImageRenderer, WindowHandle, WindowRenderer,
+};
 use softbuffer::{Context, Surface};
 use std::{num::NonZero, sync::Arc};
 
@@ -92,21 +93,28 @@ impl<Renderer: ImageRenderer> WindowRenderer for SoftbufferWindowRenderer<Render
         };
     }
 
-    fn render<F: FnOnce(&mut Renderer::ScenePainter<'_>)>(&mut self, draw_fn: F) {
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.renderer.set_scale_factor(scale_factor);
+    }
+
+    fn render<F: FnOnce(&mut Renderer::ScenePainter<'_>)>(
+        &mut self,
+        draw_fn: F,
+    ) -> Option<FrameStats> {
         let RenderState::Active(state) = &mut self.render_state else {
-            return;
+            return None;
         };
 
-        debug_timer!(timer, feature = "log_frame_times");
+        let mut timer = FrameTimer::start();
 
         let Ok(mut surface_buffer) = state.surface.buffer_mut() else {
-            return;
+            return None;
         };
-        timer.record_time("buffer_mut");
+        timer.record(FramePhase::Wait);
 
         // Paint
-        self.renderer.render_to_vec(draw_fn, &mut self.buffer);
-        timer.record_time("render");
+        let render_stats = self.renderer.render_to_vec(draw_fn, &mut self.buffer);
+        timer.record_nested(render_stats);
 
         let out = surface_buffer.as_mut();
 
@@ -122,13 +130,18 @@ impl<Renderer: ImageRenderer> WindowRenderer for SoftbufferWindowRenderer<Render
                 *dest = (r as u32) << 16 | (g as u32) << 8 | b as u32;
             }
         }
-        timer.record_time("swizel");
+        timer.record(FramePhase::Swizzle);
 
         surface_buffer.present().unwrap();
-        timer.record_time("present");
-        timer.print_times("softbuffer: ");
+        timer.record(FramePhase::Present);
+
+        let stats = timer.finish();
+        #[cfg(feature = "log_frame_times")]
+        println!("softbuffer: {stats}");
 
         // Reset the renderer ready for the next render
         self.renderer.reset();
+
+        Some(stats)
     }
 }
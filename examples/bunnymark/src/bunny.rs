@@ -107,13 +107,10 @@ impl BunnyManager {
         }
     }
 
-    pub fn draw<S: PaintScene>(&self, scene: &mut S, scale_factor: f64) {
+    pub fn draw<S: PaintScene>(&self, scene: &mut S) {
         for bunny in &self.bunnies {
             let pos = bunny.position();
-            scene.draw_image(
-                self.bunny_image.as_ref(),
-                Affine::translate(pos).then_scale(scale_factor),
-            );
+            scene.draw_image(self.bunny_image.as_ref(), Affine::translate(pos));
         }
     }
 }
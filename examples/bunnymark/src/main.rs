@@ -59,6 +59,16 @@ impl Renderer {
             Renderer::Cpu(r) => r.set_size(w, h),
         }
     }
+
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        match self {
+            Renderer::Skia(r) => r.set_scale_factor(scale_factor),
+            Renderer::SkiaRaster(r) => r.set_scale_factor(scale_factor),
+            Renderer::Gpu(r) => r.set_scale_factor(scale_factor),
+            Renderer::Hybrid(r) => r.set_scale_factor(scale_factor),
+            Renderer::Cpu(r) => r.set_scale_factor(scale_factor),
+        }
+    }
 }
 
 enum RenderState {
@@ -91,14 +101,13 @@ impl App {
         scene: &mut T,
         width: u32,
         height: u32,
-        scale_factor: f64,
         bunny_manager: &BunnyManager,
         color: Color,
     ) {
         // Draw background
         scene.fill(
             Fill::NonZero,
-            Affine::scale(scale_factor),
+            Affine::IDENTITY,
             SKY_BLUE,
             None,
             &Rect::new(0.0, 0.0, width as f64, height as f64),
@@ -107,21 +116,21 @@ impl App {
         // Draw small circle indicating renderer in use
         scene.stroke(
             &Stroke::new(2.0),
-            Affine::scale(scale_factor),
+            Affine::IDENTITY,
             Color::BLACK,
             None,
             &Rect::new(5.0, 5.0, 35.0, 35.0),
         );
         scene.fill(
             Fill::NonZero,
-            Affine::scale(scale_factor),
+            Affine::IDENTITY,
             color,
             None,
             &Circle::new(Point::new(20.0, 20.0), 10.0),
         );
 
         // Draw bunnies
-        bunny_manager.draw(scene, scale_factor);
+        bunny_manager.draw(scene);
     }
 
     fn set_backend<R: WindowRenderer>(
@@ -149,11 +158,13 @@ impl App {
         self.scale_factor = window.scale_factor();
 
         let physical_size = window.inner_size();
+        renderer.set_scale_factor(self.scale_factor);
         if renderer
             .resume(window.clone(), physical_size.width, physical_size.height)
             .is_err()
         {
             let mut fallback_renderer = FallbackBackend::new();
+            fallback_renderer.set_scale_factor(self.scale_factor);
             fallback_renderer
                 .resume(window.clone(), physical_size.width, physical_size.height)
                 .unwrap();
@@ -216,6 +227,7 @@ impl ApplicationHandler for App {
                     let logical_size = physical_size.to_logical(scale_factor);
                     self.logical_width = logical_size.width;
                     self.logical_height = logical_size.height;
+                    renderer.set_scale_factor(scale_factor);
                     renderer.set_size(physical_size.width, physical_size.height);
                 };
             }
@@ -229,18 +241,12 @@ impl ApplicationHandler for App {
                     Renderer::Hybrid(_) => "vello_hybrid",
                     Renderer::Cpu(_) => "vello_cpu",
                 };
-                print!(
-                    "[{}] [{} bunnies] ",
-                    renderer_name,
-                    self.bunny_manager.count(),
-                );
-                match renderer {
+                let stats = match renderer {
                     Renderer::Skia(r) => r.render(|scene_painter| {
                         App::draw_scene(
                             scene_painter,
                             self.logical_width,
                             self.logical_height,
-                            self.scale_factor,
                             &self.bunny_manager,
                             Color::from_rgb8(255, 0, 0),
                         );
@@ -250,7 +256,6 @@ impl ApplicationHandler for App {
                             scene_painter,
                             self.logical_width,
                             self.logical_height,
-                            self.scale_factor,
                             &self.bunny_manager,
                             Color::from_rgb8(255, 0, 0),
                         );
@@ -260,7 +265,6 @@ impl ApplicationHandler for App {
                             scene_painter,
                             self.logical_width,
                             self.logical_height,
-                            self.scale_factor,
                             &self.bunny_manager,
                             Color::from_rgb8(255, 0, 0),
                         );
@@ -270,7 +274,6 @@ impl ApplicationHandler for App {
                             scene_painter,
                             self.logical_width,
                             self.logical_height,
-                            self.scale_factor,
                             &self.bunny_manager,
                             Color::from_rgb8(255, 0, 0),
                         );
@@ -280,11 +283,18 @@ impl ApplicationHandler for App {
                             scene_painter,
                             self.logical_width,
                             self.logical_height,
-                            self.scale_factor,
                             &self.bunny_manager,
                             Color::from_rgb8(0, 255, 0),
                         );
                     }),
+                };
+                if let Some(stats) = stats {
+                    println!(
+                        "[{}] [{} bunnies] {}",
+                        renderer_name,
+                        self.bunny_manager.count(),
+                        stats
+                    );
                 }
                 window.request_redraw();
             }
@@ -220,18 +220,28 @@ impl ApplicationHandler for App {
                 renderer.set_size(self.width, self.height);
                 self.request_redraw();
             }
-            WindowEvent::RedrawRequested => match renderer {
-                Renderer::Skia(r) => {
-                    r.render(|p| App::draw_scene(p, Color::from_rgb8(128, 128, 128)))
-                }
-                Renderer::Gpu(r) => r.render(|p| App::draw_scene(p, Color::from_rgb8(255, 0, 0))),
-                Renderer::Hybrid(r) => r.render(|p| App::draw_scene(p, Color::from_rgb8(0, 0, 0))),
-                Renderer::Cpu(r) => r.render(|p| App::draw_scene(p, Color::from_rgb8(0, 255, 0))),
-                Renderer::CpuSoftbuffer(r) => {
-                    r.render(|p| App::draw_scene(p, Color::from_rgb8(0, 0, 255)))
-                }
-                Renderer::Null(r) => r.render(|p| App::draw_scene(p, Color::from_rgb8(0, 0, 0))),
-            },
+            WindowEvent::RedrawRequested => {
+                match renderer {
+                    Renderer::Skia(r) => {
+                        r.render(|p| App::draw_scene(p, Color::from_rgb8(128, 128, 128)))
+                    }
+                    Renderer::Gpu(r) => {
+                        r.render(|p| App::draw_scene(p, Color::from_rgb8(255, 0, 0)))
+                    }
+                    Renderer::Hybrid(r) => {
+                        r.render(|p| App::draw_scene(p, Color::from_rgb8(0, 0, 0)))
+                    }
+                    Renderer::Cpu(r) => {
+                        r.render(|p| App::draw_scene(p, Color::from_rgb8(0, 255, 0)))
+                    }
+                    Renderer::CpuSoftbuffer(r) => {
+                        r.render(|p| App::draw_scene(p, Color::from_rgb8(0, 0, 255)))
+                    }
+                    Renderer::Null(r) => {
+                        r.render(|p| App::draw_scene(p, Color::from_rgb8(0, 0, 0)))
+                    }
+                };
+            }
             WindowEvent::KeyboardInput {
                 event:
                     KeyEvent {